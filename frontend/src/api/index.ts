@@ -34,7 +34,7 @@ export async function validateAuthToken(token: string) {
  * - 401: Invalid bearer token
  * - 500: Server error
  */
-export async function postCookie(cookie: string) {
+export async function postCookie(cookie: string, orgPreference?: string) {
   const token = localStorage.getItem("authToken") || "";
   const response = await fetch("/api/cookie", {
     method: "POST",
@@ -42,7 +42,10 @@ export async function postCookie(cookie: string) {
       "Content-Type": "application/json",
       Authorization: `Bearer ${token}`,
     },
-    body: JSON.stringify({ cookie }),
+    body: JSON.stringify({
+      cookie,
+      ...(orgPreference ? { org_preference: orgPreference } : {}),
+    }),
   });
 
   if (response.status === 400) {
@@ -343,6 +346,29 @@ export async function codexTokens() {
   return await res.json();
 }
 
+export async function codexAccounts() {
+  const admin = localStorage.getItem("authToken") || "";
+  const res = await fetch("/api/codex/accounts", {
+    headers: { Authorization: `Bearer ${admin}` },
+  });
+  if (!res.ok) throw new Error("Failed to get Codex accounts");
+  return await res.json(); // { valid, exhausted, invalid }
+}
+
+export async function codexDeleteAccount(accountId: string) {
+  const admin = localStorage.getItem("authToken") || "";
+  const res = await fetch("/api/codex/account", {
+    method: "DELETE",
+    headers: {
+      Authorization: `Bearer ${admin}`,
+      "Content-Type": "application/json",
+    },
+    body: JSON.stringify({ account_id: accountId }),
+  });
+  if (!res.ok) throw new Error("Failed to delete Codex account");
+  return await res.json();
+}
+
 export async function codexLogout() {
   const admin = localStorage.getItem("authToken") || "";
   const res = await fetch("/api/codex/logout", {
@@ -2,19 +2,60 @@ import React, { useEffect, useState } from "react";
 import { useTranslation } from "react-i18next";
 import Button from "../common/Button";
 import StatusMessage from "../common/StatusMessage";
-import { codexStartAuth, codexTokens, codexLogout } from "../../api";
+import {
+  codexStartAuth,
+  codexAccounts,
+  codexDeleteAccount,
+  codexLogout,
+} from "../../api";
+
+interface CodexAccount {
+  account_id: string;
+  email?: string | null;
+  last_refresh?: string | null;
+  reset_time?: number | null;
+  requests: number;
+  usage?: {
+    windows: Record<string, { utilization: number; resets_at?: number | null }>;
+  } | null;
+  invalid?: string | null;
+}
+
+type AccountState = "valid" | "exhausted" | "invalid";
+
+interface CodexAccounts {
+  valid: CodexAccount[];
+  exhausted: CodexAccount[];
+  invalid: CodexAccount[];
+}
+
+const BADGE_CLASSES: Record<AccountState, string> = {
+  valid: "bg-green-700 text-green-100",
+  exhausted: "bg-yellow-700 text-yellow-100",
+  invalid: "bg-red-700 text-red-100",
+};
+
+const formatTime = (secs?: number | null) =>
+  secs ? new Date(secs * 1000).toLocaleString() : "-";
+
+const formatUsage = (usage: CodexAccount["usage"]) =>
+  usage
+    ? Object.entries(usage.windows)
+        .map(([name, w]) => `${name} ${Math.round(w.utilization)}%`)
+        .join(" · ")
+    : "-";
 
 const CodexPanel: React.FC = () => {
   const { t } = useTranslation();
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState<string | null>(null);
-  const [status, setStatus] = useState<any>(null);
+  const [status, setStatus] = useState<CodexAccounts | null>(null);
 
   const refresh = async () => {
     setError(null);
     setLoading(true);
     try {
-      const s = await codexTokens();
+      const s = await codexAccounts();
       setStatus(s);
     } catch (e: any) {
       setError(e?.message || String(e));
@@ -40,6 +81,19 @@ const CodexPanel: React.FC = () => {
     }
   };
 
+  const onDelete = async (accountId: string) => {
+    setError(null);
+    setLoading(true);
+    try {
+      await codexDeleteAccount(accountId);
+      await refresh();
+    } catch (e: any) {
+      setError(e?.message || String(e));
+    } finally {
+      setLoading(false);
+    }
+  };
+
   const onLogout = async () => {
     setError(null);
     setLoading(true);
@@ -76,24 +130,84 @@ const CodexPanel: React.FC = () => {
       </div>
 
       <div className="bg-gray-700 p-4 rounded-lg">
-        <h4 className="text-white font-medium mb-2">{t("codex.status")}</h4>
-        {status ? (
-          <div className="text-gray-300 text-sm space-y-1">
-            <div>
-              {t("codex.authenticated")}:
-              <span className="ml-1 font-mono">{String(status.authenticated)}</span>
-            </div>
-            <div>
-              {t("codex.accountId")}:
-              <span className="ml-1 font-mono">{status.account_id || "-"}</span>
-            </div>
-            <div>
-              {t("codex.lastRefresh")}:
-              <span className="ml-1 font-mono">{status.last_refresh || "-"}</span>
-            </div>
-          </div>
-        ) : (
+        <h4 className="text-white font-medium mb-2">{t("codex.accounts")}</h4>
+        {!status ? (
           <div className="text-gray-400 text-sm">{t("codex.noStatus")}</div>
+        ) : status.valid.length +
+            status.exhausted.length +
+            (status.invalid?.length ?? 0) ===
+          0 ? (
+          <div className="text-gray-400 text-sm">{t("codex.noAccounts")}</div>
+        ) : (
+          <div className="space-y-2">
+            {[
+              ...status.valid.map((a) => ({ account: a, state: "valid" as const })),
+              ...status.exhausted.map((a) => ({
+                account: a,
+                state: "exhausted" as const,
+              })),
+              ...(status.invalid ?? []).map((a) => ({
+                account: a,
+                state: "invalid" as const,
+              })),
+            ].map(({ account, state }) => (
+              <div
+                key={account.account_id}
+                className="flex items-start justify-between bg-gray-800 p-3 rounded text-gray-300 text-sm"
+              >
+                <div className="space-y-1">
+                  <div>
+                    <span
+                      className={`mr-2 px-2 py-0.5 rounded text-xs ${BADGE_CLASSES[state]}`}
+                    >
+                      {t(`codex.${state}`)}
+                    </span>
+                    <span className="font-mono">
+                      {account.email || account.account_id}
+                    </span>
+                  </div>
+                  <div>
+                    {t("codex.accountId")}:
+                    <span className="ml-1 font-mono">{account.account_id}</span>
+                  </div>
+                  <div>
+                    {t("codex.requests")}:
+                    <span className="ml-1 font-mono">{account.requests}</span>
+                    <span className="ml-4">{t("codex.usage")}:</span>
+                    <span className="ml-1 font-mono">
+                      {formatUsage(account.usage)}
+                    </span>
+                  </div>
+                  {state === "exhausted" && (
+                    <div>
+                      {t("codex.resetTime")}:
+                      <span className="ml-1 font-mono">
+                        {formatTime(account.reset_time)}
+                      </span>
+                    </div>
+                  )}
+                  {state === "invalid" && (
+                    <div className="text-red-300">
+                      {t("codex.invalidHint")}: {account.invalid}
+                    </div>
+                  )}
+                  <div>
+                    {t("codex.lastRefresh")}:
+                    <span className="ml-1 font-mono">
+                      {account.last_refresh || "-"}
+                    </span>
+                  </div>
+                </div>
+                <Button
+                  onClick={() => onDelete(account.account_id)}
+                  disabled={loading}
+                  variant="danger"
+                >
+                  {t("codex.delete")}
+                </Button>
+              </div>
+            ))}
+          </div>
         )}
       </div>
     </div>
@@ -143,6 +143,22 @@ const ConfigForm: React.FC<ConfigFormProps> = ({ config, onChange }) => {
             onChange={onChange}
             label={t("config.sections.api.maxRetries")}
           />
+          <FormInput
+            id="preserve_chats_ttl"
+            name="preserve_chats_ttl"
+            type="number"
+            value={config.preserve_chats_ttl.toString()}
+            onChange={onChange}
+            label={t("config.sections.api.preserveChatsTtl")}
+          />
+          <FormInput
+            id="usage_poll_interval"
+            name="usage_poll_interval"
+            type="number"
+            value={config.usage_poll_interval.toString()}
+            onChange={onChange}
+            label={t("config.sections.api.usagePollInterval")}
+          />
         </div>
         <div className="flex space-x-6">
           <ConfigCheckbox
@@ -152,6 +168,13 @@ const ConfigForm: React.FC<ConfigFormProps> = ({ config, onChange }) => {
             label={t("config.sections.api.preserveChats")}
           />
 
+          <ConfigCheckbox
+            name="reuse_conversations"
+            checked={config.reuse_conversations}
+            onChange={onChange}
+            label={t("config.sections.api.reuseConversations")}
+          />
+
           <ConfigCheckbox
             name="web_search"
             checked={config.web_search}
@@ -0,0 +1,77 @@
+import React from "react";
+import { useTranslation } from "react-i18next";
+import { CookieStatus } from "../../types/cookie.types";
+
+interface CookieBadgesProps {
+  status: CookieStatus;
+}
+
+// Shortens window names such as `five_hour` or `seven_day_opus`
+const windowLabel = (name: string) =>
+  name
+    .replace("five_hour", "5h")
+    .replace("seven_day", "7d")
+    .replace(/_/g, " ");
+
+const CookieBadges: React.FC<CookieBadgesProps> = ({ status }) => {
+  const { t } = useTranslation();
+  const orgs = status.orgs ?? [];
+  const chosen = orgs.find((o) => o.uuid === status.org_uuid);
+  return (
+    <>
+      {chosen && (
+        <span
+          className="mr-2 px-1.5 py-0.5 rounded text-xs bg-purple-900/40 text-purple-300"
+          title={`${t("cookieStatus.status.organizations")}:\n${orgs
+            .map(
+              (o) =>
+                `${o.uuid === status.org_uuid ? "* " : "  "}${o.name} (${o.uuid})`
+            )
+            .join("\n")}`}
+        >
+          {chosen.name || chosen.uuid.substring(0, 8)}
+          {orgs.length > 1 && ` +${orgs.length - 1}`}
+        </span>
+      )}
+      {Object.entries(status.usage?.windows ?? {}).map(([name, w]) => (
+        <span
+          key={name}
+          className={`mr-2 px-1.5 py-0.5 rounded text-xs ${
+            w.utilization >= 100
+              ? "bg-red-900/40 text-red-300"
+              : w.utilization >= 80
+              ? "bg-amber-900/40 text-amber-300"
+              : "bg-green-900/40 text-green-300"
+          }`}
+          title={t("cookieStatus.status.usage", {
+            window: name,
+            percent: Math.round(w.utilization),
+            time: w.resets_at
+              ? new Date(w.resets_at * 1000).toLocaleString()
+              : t("cookieStatus.status.unknownReset"),
+          })}
+        >
+          {windowLabel(name)} {Math.round(w.utilization)}%
+        </span>
+      ))}
+      {status.context_1m != null && (
+        <span
+          className={`mr-2 px-1.5 py-0.5 rounded text-xs ${
+            status.context_1m
+              ? "bg-cyan-900/40 text-cyan-300"
+              : "bg-gray-700/60 text-gray-400 line-through"
+          }`}
+          title={
+            status.context_1m
+              ? t("cookieStatus.status.context1m")
+              : t("cookieStatus.status.noContext1m")
+          }
+        >
+          1M
+        </span>
+      )}
+    </>
+  );
+};
+
+export default CookieBadges;
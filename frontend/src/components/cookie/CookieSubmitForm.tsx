@@ -14,6 +14,7 @@ interface CookieResult {
 const CookieSubmitForm: React.FC = () => {
   const { t } = useTranslation();
   const [cookies, setCookies] = useState("");
+  const [orgPreference, setOrgPreference] = useState("");
   const [isSubmitting, setIsSubmitting] = useState(false);
   const [results, setResults] = useState<CookieResult[]>([]);
   const [overallStatus, setOverallStatus] = useState({
@@ -48,7 +49,7 @@ const CookieSubmitForm: React.FC = () => {
     // Process each cookie line
     for (const cookieStr of cookieLines) {
       try {
-        await postCookie(cookieStr);
+        await postCookie(cookieStr, orgPreference.trim() || undefined);
         newResults.push({
           cookie: cookieStr,
           status: "success",
@@ -128,6 +129,20 @@ const CookieSubmitForm: React.FC = () => {
           {t("cookieSubmit.descriptionMulti")}
         </p>
 
+        <FormInput
+          id="org_preference"
+          name="org_preference"
+          value={orgPreference}
+          onChange={(e) => setOrgPreference(e.target.value)}
+          placeholder={t("cookieSubmit.orgPreferencePlaceholder")}
+          label={t("cookieSubmit.orgPreference")}
+          disabled={isSubmitting}
+        />
+
+        <p className="text-xs text-gray-400 mt-1">
+          {t("cookieSubmit.orgPreferenceDescription")}
+        </p>
+
         {overallStatus.message && (
           <StatusMessage
             type={overallStatus.type}
@@ -6,6 +6,7 @@ import { CookieStatusInfo } from "../../types/cookie.types";
 import Button from "../common/Button";
 import LoadingSpinner from "../common/LoadingSpinner";
 import StatusMessage from "../common/StatusMessage";
+import CookieBadges from "./CookieBadges";
 import CookieSection from "./CookieSection";
 import CookieValue from "./CookieValue";
 import DeleteButton from "./DeleteButton";
@@ -209,6 +210,7 @@ const CookieVisualization: React.FC = () => {
                   <CookieValue cookie={status.cookie} />
                 </div>
                 <div className="flex items-center">
+                  <CookieBadges status={status} />
                   <span className="text-gray-400">
                     {t("cookieStatus.status.available")}
                   </span>
@@ -238,6 +240,7 @@ const CookieVisualization: React.FC = () => {
                   <CookieValue cookie={status.cookie} />
                 </div>
                 <div className="flex items-center">
+                  <CookieBadges status={status} />
                   <span className="text-gray-400">
                     {status.reset_time
                       ? t("cookieStatus.status.resets", {
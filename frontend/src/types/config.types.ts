@@ -19,6 +19,9 @@ export interface ConfigData {
   // API settings
   max_retries: number;
   preserve_chats: boolean;
+  preserve_chats_ttl: number;
+  usage_poll_interval: number;
+  reuse_conversations: boolean;
   web_search: boolean;
 
   // Cookie settings
@@ -2,6 +2,27 @@
 export interface CookieStatus {
   cookie: string;
   reset_time: number | null;
+  context_1m?: boolean;
+  org_preference?: string;
+  org_uuid?: string;
+  orgs?: OrgInfo[];
+  usage?: AccountUsage;
+}
+
+export interface AccountUsage {
+  windows: Record<string, UsageWindow>;
+  updated_at: number;
+}
+
+export interface UsageWindow {
+  utilization: number;
+  resets_at?: number | null;
+}
+
+export interface OrgInfo {
+  uuid: string;
+  name: string;
+  capabilities: string[];
 }
 
 export interface UselessCookie {
@@ -1,28 +1,37 @@
 use std::time::Instant;
 
-use axum::{Extension, extract::State, response::Response};
+use axum::{
+    Extension,
+    extract::State,
+    response::{IntoResponse, Response},
+};
 use colored::Colorize;
+use futures::future::try_join_all;
 use tracing::info;
 
 use crate::{
     claude_code_state::ClaudeCodeState,
     error::ClewdrError,
-    middleware::claude::{ClaudeApiFormat, ClaudeCodePreprocess, ClaudeContext},
+    middleware::claude::{ClaudeApiFormat, ClaudeCodePreprocess, merge_choices},
     utils::{enabled, print_out_json},
 };
 
 pub async fn api_claude_code(
     State(mut state): State<ClaudeCodeState>,
     ClaudeCodePreprocess(p, f): ClaudeCodePreprocess,
-) -> Result<(Extension<ClaudeContext>, Response), ClewdrError> {
+) -> Result<Response, ClewdrError> {
     state.system_prompt_hash = f.system_prompt_hash();
     state.stream = p.stream.unwrap_or_default();
     state.api_format = f.api_format();
     state.usage = f.usage().to_owned();
+    state.anthropic_beta = f.anthropic_beta().to_owned();
     print_out_json(&p, "claude_code_client_req.json");
     let format_display = match f.api_format() {
         ClaudeApiFormat::Claude => ClaudeApiFormat::Claude.to_string().green(),
         ClaudeApiFormat::OpenAI => ClaudeApiFormat::OpenAI.to_string().yellow(),
+        ClaudeApiFormat::Responses => ClaudeApiFormat::Responses.to_string().cyan(),
+        ClaudeApiFormat::Completions => ClaudeApiFormat::Completions.to_string().magenta(),
+        ClaudeApiFormat::Complete => ClaudeApiFormat::Complete.to_string().blue(),
     };
     info!(
         "[REQ] stream: {}, msgs: {}, model: {}, think: {}, format: {}",
@@ -33,7 +42,23 @@ pub async fn api_claude_code(
         format_display
     );
     let stopwatch = Instant::now();
-    let res = state.try_chat(p).await;
+    let res = match f.choices() {
+        1 => state
+            .try_chat(p)
+            .await
+            .map(|r| (Extension(f), r).into_response()),
+        n => {
+            // each choice is a separate request, possibly on a different cookie
+            let requests = (0..n).map(|_| {
+                let (mut state, p) = (state.to_owned(), p.to_owned());
+                async move { state.try_chat(p).await }
+            });
+            match try_join_all(requests).await {
+                Ok(responses) => Ok(merge_choices(responses, &f).await),
+                Err(e) => Err(e),
+            }
+        }
+    };
 
     let elapsed = stopwatch.elapsed();
     info!(
@@ -41,5 +66,5 @@ pub async fn api_claude_code(
         format!("{}", elapsed.as_secs_f32()).green()
     );
 
-    res.map(|r| (Extension(f), r))
+    res
 }
@@ -1,13 +1,19 @@
 use std::time::Instant;
 
-use axum::{Extension, extract::State, response::Response};
+use axum::{
+    Extension,
+    extract::State,
+    response::{IntoResponse, Response},
+};
 use colored::Colorize;
+use futures::future::try_join_all;
 use tracing::info;
 
 use crate::{
-    claude_web_state::ClaudeWebState,
+    claude_web_state::{ClaudeWebState, session_key},
+    config::CLEWDR_CONFIG,
     error::ClewdrError,
-    middleware::claude::{ClaudeApiFormat, ClaudeContext, ClaudeWebPreprocess},
+    middleware::claude::{ClaudeApiFormat, ClaudeWebPreprocess, merge_choices},
     utils::{enabled, print_out_json},
 };
 /// Axum handler for the API messages
@@ -24,15 +30,21 @@ use crate::{
 pub async fn api_claude_web(
     State(mut state): State<ClaudeWebState>,
     ClaudeWebPreprocess(p, f): ClaudeWebPreprocess,
-) -> Result<(Extension<ClaudeContext>, Response), ClewdrError> {
+) -> Result<Response, ClewdrError> {
     let stream = p.stream.unwrap_or_default();
     print_out_json(&p, "claude_web_client_req.json");
     state.api_format = f.api_format();
     state.stream = stream;
     state.usage = f.usage().to_owned();
+    if CLEWDR_CONFIG.load().reuse_conversations && !p.messages.is_empty() {
+        state.session_key = f.client().map(|client| session_key(client, &p));
+    }
     let format_display = match f.api_format() {
         ClaudeApiFormat::Claude => ClaudeApiFormat::Claude.to_string().green(),
         ClaudeApiFormat::OpenAI => ClaudeApiFormat::OpenAI.to_string().yellow(),
+        ClaudeApiFormat::Responses => ClaudeApiFormat::Responses.to_string().cyan(),
+        ClaudeApiFormat::Completions => ClaudeApiFormat::Completions.to_string().magenta(),
+        ClaudeApiFormat::Complete => ClaudeApiFormat::Complete.to_string().blue(),
     };
     info!(
         "[REQ] stream: {}, msgs: {}, model: {}, think: {}, format: {}",
@@ -43,7 +55,25 @@ pub async fn api_claude_web(
         format_display
     );
     let stopwatch = Instant::now();
-    let res = state.try_chat(p).await;
+    let res = match f.choices() {
+        1 => state
+            .try_chat(p)
+            .await
+            .map(|r| (Extension(f), r).into_response()),
+        n => {
+            // each choice is a separate request, possibly on a different cookie,
+            // in its own conversation that is not kept for the session
+            state.session_key = None;
+            let requests = (0..n).map(|_| {
+                let (mut state, p) = (state.to_owned(), p.to_owned());
+                async move { state.try_chat(p).await }
+            });
+            match try_join_all(requests).await {
+                Ok(responses) => Ok(merge_choices(responses, &f).await),
+                Err(e) => Err(e),
+            }
+        }
+    };
 
     let elapsed = stopwatch.elapsed();
     info!(
@@ -51,5 +81,5 @@ pub async fn api_claude_web(
         format!("{}", elapsed.as_secs_f32()).green()
     );
 
-    res.map(|r| (Extension(f), r))
+    res
 }
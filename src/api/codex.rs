@@ -2,21 +2,29 @@ use std::time::SystemTime;
 
 use axum::{
     Json,
+    body::Body,
     extract::State,
     response::{IntoResponse, Response, Sse},
 };
 use eventsource_stream::Eventsource;
 use futures::TryStreamExt;
+use http::{StatusCode, header::CONTENT_TYPE};
 use serde_json::{Value, json};
 
 use crate::{
-    codex_state::CodexState,
+    codex_state::{CodexState, DEFAULT_INSTRUCTIONS, ensure_session_id},
     error::ClewdrError,
-    types::claude::{Message, Role},
-    types::oai::CreateMessageParams as OaiCreateMessageParams,
+    middleware::codex::{
+        chat_finish_reason, chat_usage, transform_chat_stream, transform_claude_stream,
+        transforms_claude_json,
+    },
+    types::claude::{
+        CreateMessageParams as ClaudeCreateMessageParams, Message, Role, Tool, ToolChoice,
+    },
+    types::oai::{CreateMessageParams as OaiCreateMessageParams, into_claude_messages},
 };
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct CodexApiState {
     pub state: CodexState,
 }
@@ -42,7 +50,7 @@ fn system_instructions(messages: &[Message]) -> Option<String> {
             }
             crate::types::claude::MessageContent::Blocks { content } => {
                 for b in content.iter() {
-                    if let crate::types::claude::ContentBlock::Text { text } = b {
+                    if let crate::types::claude::ContentBlock::Text { text, .. } = b {
                         if !text.is_empty() {
                             parts.push(text.clone());
                         }
@@ -90,6 +98,129 @@ fn convert_tools_chat_to_responses(tools: &Value) -> Vec<Value> {
     }
 }
 
+fn convert_tools_claude_to_responses(tools: &[Tool]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|t| {
+            json!({
+                "type": "function",
+                "name": t.name,
+                "description": t.description.as_deref().unwrap_or(""),
+                "strict": false,
+                "parameters": t.input_schema,
+            })
+        })
+        .collect()
+}
+
+fn convert_tool_choice_claude_to_responses(choice: Option<&ToolChoice>) -> Value {
+    match choice {
+        None | Some(ToolChoice::Auto) => json!("auto"),
+        Some(ToolChoice::Any) => json!("required"),
+        Some(ToolChoice::None) => json!("none"),
+        Some(ToolChoice::Tool { name }) => json!({"type": "function", "name": name}),
+    }
+}
+
+/// Map a Claude thinking budget to a reasoning effort, with summaries streamed back as thinking
+fn reasoning_for_budget(budget_tokens: u64) -> Value {
+    let effort = match budget_tokens {
+        0..=4095 => "low",
+        4096..=16383 => "medium",
+        _ => "high",
+    };
+    json!({"effort": effort, "summary": "auto"})
+}
+
+/// Reasoning options of a chat completion request
+///
+/// A `reasoning` object is passed through, with its effort falling back to
+/// `reasoning_effort` and then to the model name suffix. Summaries default to
+/// `auto` so they can be streamed back as `reasoning_content`.
+fn chat_reasoning(raw: &Value, model_effort: Option<&str>) -> Option<Value> {
+    let mut reasoning = raw
+        .get("reasoning")
+        .filter(|r| r.is_object())
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    let effort = raw["reasoning_effort"].as_str().or(model_effort);
+    if reasoning["effort"].is_null()
+        && let Some(effort) = effort
+    {
+        reasoning["effort"] = json!(effort);
+    }
+    if reasoning.as_object().is_none_or(|r| r.is_empty()) {
+        return None;
+    }
+    if reasoning["summary"].is_null() {
+        reasoning["summary"] = json!("auto");
+    }
+    Some(reasoning)
+}
+
+fn claude_system_instructions(system: Option<&Value>) -> Option<String> {
+    let text = match system? {
+        Value::String(s) => s.to_owned(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(|v| v.as_str()))
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    if text.is_empty() { None } else { Some(text) }
+}
+
+/// Status and message of a failed upstream Codex response
+async fn upstream_failure(upstream: wreq::Response) -> (StatusCode, String) {
+    let status = upstream.status();
+    let body = upstream.text().await.unwrap_or_default();
+    let v: Value = serde_json::from_str(&body).unwrap_or(json!({"raw": body}));
+    let msg = v
+        .get("error")
+        .and_then(|e| e.get("message"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("Upstream error");
+    (status, msg.to_string())
+}
+
+/// Status matching the error of a `response.failed` event
+fn failed_status(error: &Value) -> StatusCode {
+    let code = error["code"].as_str().unwrap_or_default();
+    if code.contains("rate_limit") {
+        StatusCode::TOO_MANY_REQUESTS
+    } else if code.starts_with("invalid") || code.contains("context_length") {
+        StatusCode::BAD_REQUEST
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// OpenAI error response with the upstream status
+fn oai_error_response(status: StatusCode, msg: &str) -> Response {
+    (status, Json(json!({"error": {"message": msg}}))).into_response()
+}
+
+/// Anthropic error response, typed after the upstream status
+fn claude_error_response(status: StatusCode, msg: &str) -> Response {
+    let kind = match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => "invalid_request_error",
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::NOT_FOUND => "not_found_error",
+        StatusCode::PAYLOAD_TOO_LARGE => "request_too_large",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        StatusCode::SERVICE_UNAVAILABLE => "overloaded_error",
+        _ => "api_error",
+    };
+    (
+        status,
+        Json(json!({"type": "error", "error": {"type": kind, "message": msg}})),
+    )
+        .into_response()
+}
+
 pub async fn codex_chat_completions(
     State(state): State<CodexApiState>,
     Json(raw): Json<Value>,
@@ -100,7 +231,7 @@ pub async fn codex_chat_completions(
             msg: "Invalid JSON body".into(),
         })?;
     let requested_model = oai.model.clone();
-    let model = state.state.normalize_model_name(Some(&oai.model));
+    let (model, effort) = state.state.normalize_model_name(Some(&oai.model));
     let stream = oai.stream.unwrap_or(false);
     let created = unix_time();
     let include_usage = raw
@@ -117,13 +248,12 @@ pub async fn codex_chat_completions(
         .get("parallel_tool_calls")
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
-    let reasoning = raw.get("reasoning").cloned();
+    let reasoning = chat_reasoning(&raw, effort);
 
     // System instructions and input
-    let instructions = system_instructions(&oai.messages);
-    let input_items = state
-        .state
-        .convert_messages_to_responses_input(&oai.messages);
+    let messages = into_claude_messages(oai.messages);
+    let instructions = system_instructions(&messages);
+    let input_items = state.state.convert_messages_to_responses_input(&messages);
 
     // Session id from headers not accessible here; allow caller to set X-Session-Id later if needed
     let upstream = state
@@ -141,107 +271,25 @@ pub async fn codex_chat_completions(
         .await?;
 
     if !upstream.status().is_success() {
-        let body = upstream.text().await.unwrap_or_default();
-        let v: Value = serde_json::from_str(&body).unwrap_or(json!({"raw": body}));
-        let msg = v
-            .get("error")
-            .and_then(|e| e.get("message"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("Upstream error");
-        return Ok((
-            axum::http::StatusCode::BAD_GATEWAY,
-            Json(json!({"error": {"message": msg}})),
-        )
-            .into_response());
+        let (status, msg) = upstream_failure(upstream).await;
+        return Ok(oai_error_response(status, &msg));
     }
 
     if stream {
-        let s = upstream.bytes_stream().eventsource();
-        let model_out = requested_model.clone();
-        let s = s.map_ok(move |event| {
-            use axum::response::sse::Event;
-            // We'll translate each upstream event into OAI delta chunks.
-            let mut out_event = Event::default().event(event.event).id(event.id);
-            if let Some(retry) = event.retry { out_event = out_event.retry(retry); }
-            // Parse JSON
-            let v: Value = match serde_json::from_str(&event.data) { Ok(v) => v, Err(_) => return out_event.data(event.data) };
-            let kind = v.get("type").and_then(|v| v.as_str()).unwrap_or("");
-            // Track id
-            let response_id = v.get("response").and_then(|r| r.get("id")).and_then(|v| v.as_str()).unwrap_or("chatcmpl-stream");
-            if kind == "response.output_text.delta" {
-                let delta = v.get("delta").and_then(|v| v.as_str()).unwrap_or("");
-                let chunk = json!({
-                    "id": response_id,
-                    "object": "chat.completion.chunk",
-                    "created": created,
-                    "model": model_out,
-                    "choices": [{"index": 0, "delta": {"content": delta}, "finish_reason": Value::Null}],
-                });
-                return out_event.json_data(chunk).unwrap();
-            }
-            if kind == "response.output_item.done" {
-                // tool call
-                let item = v.get("item").cloned().unwrap_or(json!({}));
-                if item.get("type").and_then(|v| v.as_str()) == Some("function_call") {
-                    let call_id = item.get("call_id").or(item.get("id")).and_then(|v| v.as_str()).unwrap_or("");
-                    let name = item.get("name").and_then(|v| v.as_str()).unwrap_or("");
-                    let args = item.get("arguments").and_then(|v| v.as_str()).unwrap_or("");
-                    let delta_chunk = json!({
-                        "id": response_id,
-                        "object": "chat.completion.chunk",
-                        "created": created,
-                        "model": model_out,
-                        "choices": [{
-                            "index": 0,
-                            "delta": {"tool_calls": [{"index": 0, "id": call_id, "type": "function", "function": {"name": name, "arguments": args}}]},
-                            "finish_reason": Value::Null
-                        }],
-                    });
-                    return out_event.json_data(delta_chunk).unwrap();
-                }
-            }
-            if kind == "response.output_text.done" {
-                let chunk = json!({
-                    "id": response_id,
-                    "object": "chat.completion.chunk",
-                    "created": created,
-                    "model": model_out,
-                    "choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}],
-                });
-                return out_event.json_data(chunk).unwrap();
-            }
-            if kind == "response.failed" {
-                let msg = v.get("response").and_then(|r| r.get("error")).and_then(|e| e.get("message")).and_then(|m| m.as_str()).unwrap_or("response.failed");
-                let chunk = json!({"error": {"message": msg}});
-                return out_event.json_data(chunk).unwrap();
-            }
-            if kind == "response.completed" {
-                // Include usage chunk if requested
-                if include_usage {
-                    if let Some(usage) = v.get("response").and_then(|r| r.get("usage")).cloned() {
-                        let chunk = json!({
-                            "id": response_id,
-                            "object": "chat.completion.chunk",
-                            "created": created,
-                            "model": model_out,
-                            "choices": [{"index": 0, "delta": {}, "finish_reason": Value::Null}],
-                            "usage": usage,
-                        });
-                        return out_event.json_data(chunk).unwrap();
-                    }
-                }
-                // fall-through as normal event
-            }
-            out_event.data(event.data)
-        });
-
+        let s = transform_chat_stream(
+            upstream.bytes_stream().eventsource(),
+            requested_model,
+            created,
+            include_usage,
+        );
         return Ok(Sse::new(s).keep_alive(Default::default()).into_response());
     }
 
     // Non-stream: aggregate
     let mut full_text = String::new();
+    let mut reasoning_text = String::new();
     let mut response_id = String::from("chatcmpl");
-    let mut usage_out: Option<Value> = None;
+    let mut finished: Option<Value> = None;
     let mut tool_calls: Vec<Value> = vec![];
     let mut stream = upstream.bytes_stream().eventsource();
     while let Some(evt) = stream.try_next().await.unwrap_or(None) {
@@ -261,6 +309,14 @@ pub async fn codex_chat_completions(
             if let Some(d) = v.get("delta").and_then(|v| v.as_str()) {
                 full_text.push_str(d);
             }
+        } else if kind == "response.reasoning_summary_part.added" {
+            if !reasoning_text.is_empty() {
+                reasoning_text.push_str("\n\n");
+            }
+        } else if kind == "response.reasoning_summary_text.delta" {
+            if let Some(d) = v.get("delta").and_then(|v| v.as_str()) {
+                reasoning_text.push_str(d);
+            }
         } else if kind == "response.output_item.done" {
             let item = v.get("item").cloned().unwrap_or(json!({}));
             if item.get("type").and_then(|v| v.as_str()) == Some("function_call") {
@@ -277,24 +333,21 @@ pub async fn codex_chat_completions(
                     "function": {"name": name, "arguments": args}
                 }));
             }
-        } else if kind == "response.completed" {
-            usage_out = v.get("response").and_then(|r| r.get("usage")).cloned();
+        } else if kind == "response.completed" || kind == "response.incomplete" {
+            finished = v.get("response").cloned();
             break;
         } else if kind == "response.failed" {
-            let msg = v
-                .get("response")
-                .and_then(|r| r.get("error"))
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or("response.failed");
-            return Ok((
-                axum::http::StatusCode::BAD_GATEWAY,
-                Json(json!({"error": {"message": msg}})),
-            )
-                .into_response());
+            let error = &v["response"]["error"];
+            let msg = error["message"].as_str().unwrap_or("response.failed");
+            return Ok(oai_error_response(failed_status(error), msg));
         }
     }
+    let response = finished.unwrap_or_default();
+    let finish_reason = chat_finish_reason(&response, !tool_calls.is_empty());
     let mut message = json!({"role": "assistant", "content": full_text});
+    if !reasoning_text.is_empty() {
+        message["reasoning_content"] = json!(reasoning_text);
+    }
     if !tool_calls.is_empty() {
         message["tool_calls"] = json!(tool_calls);
     }
@@ -303,10 +356,10 @@ pub async fn codex_chat_completions(
         "object": "chat.completion",
         "created": created,
         "model": requested_model,
-        "choices": [{"index": 0, "message": message, "finish_reason": "stop"}],
+        "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}],
     });
-    let completion = if let Some(u) = usage_out {
-        merge_with_usage(completion, u)
+    let completion = if response["usage"].is_object() {
+        merge_with_usage(completion, chat_usage(&response["usage"]))
     } else {
         completion
     };
@@ -351,10 +404,11 @@ pub async fn codex_completions(
         .and_then(|v| v.as_str())
         .unwrap_or("gpt-5")
         .to_string();
-    let model = state.state.normalize_model_name(Some(&requested_model));
+    let (model, effort) = state.state.normalize_model_name(Some(&requested_model));
     let msgs = vec![Message::new_text(Role::User, prompt)];
     let instructions = system_instructions(&msgs);
     let input_items = state.state.convert_messages_to_responses_input(&msgs);
+    let reasoning = chat_reasoning(&raw, effort);
 
     let upstream = state
         .state
@@ -365,23 +419,13 @@ pub async fn codex_completions(
             vec![],
             json!("auto"),
             false,
-            None,
+            reasoning,
             None,
         )
         .await?;
     if !upstream.status().is_success() {
-        let body = upstream.text().await.unwrap_or_default();
-        let v: Value = serde_json::from_str(&body).unwrap_or(json!({"raw": body}));
-        let msg = v
-            .get("error")
-            .and_then(|e| e.get("message"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("Upstream error");
-        return Ok((
-            axum::http::StatusCode::BAD_GATEWAY,
-            Json(json!({"error": {"message": msg}})),
-        )
-            .into_response());
+        let (status, msg) = upstream_failure(upstream).await;
+        return Ok(oai_error_response(status, &msg));
     }
 
     let created = unix_time();
@@ -468,17 +512,9 @@ pub async fn codex_completions(
             usage_out = v.get("response").and_then(|r| r.get("usage")).cloned();
             break;
         } else if kind == "response.failed" {
-            let msg = v
-                .get("response")
-                .and_then(|r| r.get("error"))
-                .and_then(|e| e.get("message"))
-                .and_then(|m| m.as_str())
-                .unwrap_or("response.failed");
-            return Ok((
-                axum::http::StatusCode::BAD_GATEWAY,
-                Json(json!({"error": {"message": msg}})),
-            )
-                .into_response());
+            let error = &v["response"]["error"];
+            let msg = error["message"].as_str().unwrap_or("response.failed");
+            return Ok(oai_error_response(failed_status(error), msg));
         }
     }
 
@@ -497,6 +533,188 @@ pub async fn codex_completions(
     Ok((axum::http::StatusCode::OK, Json(completion)).into_response())
 }
 
+/// Anthropic Messages API on top of the Codex Responses backend
+pub async fn codex_messages(
+    State(state): State<CodexApiState>,
+    Json(params): Json<ClaudeCreateMessageParams>,
+) -> Result<Response, ClewdrError> {
+    let requested_model = params.model.clone();
+    let (model, effort) = state.state.normalize_model_name(Some(&params.model));
+    let stream = params.stream.unwrap_or(false);
+    let instructions = claude_system_instructions(params.system.as_ref());
+    let input_items = state
+        .state
+        .convert_messages_to_responses_input(&params.messages);
+    let tools = convert_tools_claude_to_responses(params.tools.as_deref().unwrap_or_default());
+    let tool_choice = convert_tool_choice_claude_to_responses(params.tool_choice.as_ref());
+    // a thinking budget takes precedence over the effort of the model name suffix
+    let reasoning = params
+        .thinking
+        .as_ref()
+        .map(|t| reasoning_for_budget(t.budget_tokens))
+        .or_else(|| effort.map(|e| json!({"effort": e, "summary": "auto"})));
+
+    let upstream = state
+        .state
+        .start_upstream(
+            &model,
+            instructions,
+            input_items,
+            tools,
+            tool_choice,
+            true,
+            reasoning,
+            None,
+        )
+        .await?;
+    if !upstream.status().is_success() {
+        let (status, msg) = upstream_failure(upstream).await;
+        return Ok(claude_error_response(status, &msg));
+    }
+
+    if stream {
+        let s = transform_claude_stream(upstream.bytes_stream().eventsource(), requested_model);
+        return Ok(Sse::new(s).keep_alive(Default::default()).into_response());
+    }
+
+    // Non-stream: aggregate output items
+    let mut items: Vec<Value> = vec![];
+    let mut response = json!({});
+    let mut stream = upstream.bytes_stream().eventsource();
+    while let Some(evt) = stream.try_next().await.unwrap_or(None) {
+        let v: Value = match serde_json::from_str(&evt.data) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let kind = v.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        if kind == "response.output_item.done" {
+            items.push(v.get("item").cloned().unwrap_or(json!({})));
+        } else if kind == "response.completed" || kind == "response.incomplete" {
+            response = v.get("response").cloned().unwrap_or(json!({}));
+            break;
+        } else if kind == "response.failed" {
+            let error = &v["response"]["error"];
+            let msg = error["message"].as_str().unwrap_or("response.failed");
+            return Ok(claude_error_response(failed_status(error), msg));
+        }
+    }
+    let message = transforms_claude_json(&items, &response, &requested_model);
+    Ok((axum::http::StatusCode::OK, Json(message)).into_response())
+}
+
+/// Parameters the ChatGPT Codex backend rejects, dropped from pass-through requests
+const UNSUPPORTED_RESPONSES_PARAMS: [&str; 3] = ["max_output_tokens", "temperature", "top_p"];
+
+/// Native OpenAI Responses API, forwarded to the Codex backend nearly verbatim
+///
+/// Only the model name is normalized and the fields the backend requires are set:
+/// `store: false`, `stream: true`, instructions and encrypted reasoning.
+pub async fn codex_responses(
+    State(state): State<CodexApiState>,
+    Json(mut payload): Json<Value>,
+) -> Result<Response, ClewdrError> {
+    let Some(obj) = payload.as_object_mut() else {
+        return Err(ClewdrError::BadRequest {
+            msg: "Invalid JSON body",
+        });
+    };
+    let requested_model = obj.get("model").and_then(|v| v.as_str());
+    let (model, effort) = state.state.normalize_model_name(requested_model);
+    let stream = obj.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
+    for key in UNSUPPORTED_RESPONSES_PARAMS {
+        obj.remove(key);
+    }
+    obj.insert("model".into(), json!(model));
+    obj.insert("store".into(), json!(false));
+    obj.insert("stream".into(), json!(true));
+    let instructions = obj
+        .get("instructions")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.to_string());
+    if instructions.is_none() {
+        obj.insert("instructions".into(), json!(DEFAULT_INSTRUCTIONS));
+    }
+    // the effort of the model name suffix applies when the client set none
+    if let Some(effort) = effort {
+        let reasoning = obj.entry("reasoning").or_insert_with(|| json!({}));
+        if reasoning.is_null() {
+            *reasoning = json!({});
+        }
+        if let Some(reasoning) = reasoning.as_object_mut()
+            && reasoning.get("effort").is_none_or(Value::is_null)
+        {
+            reasoning.insert("effort".into(), json!(effort));
+        }
+    }
+    // reasoning can only be carried across turns as encrypted content when not stored
+    if obj.get("reasoning").is_some_and(|r| !r.is_null()) {
+        let include = obj.entry("include").or_insert_with(|| json!([]));
+        if let Some(include) = include.as_array_mut()
+            && !include.iter().any(|v| v == "reasoning.encrypted_content")
+        {
+            include.push(json!("reasoning.encrypted_content"));
+        }
+    }
+    let sid = match obj.get("prompt_cache_key").and_then(|v| v.as_str()) {
+        Some(key) => key.to_string(),
+        None => {
+            let input = obj.get("input").and_then(|v| v.as_array());
+            let sid = ensure_session_id(instructions.as_deref(), input.map_or(&[], |v| v));
+            obj.insert("prompt_cache_key".into(), json!(sid));
+            sid
+        }
+    };
+
+    let upstream = state.state.send_upstream(&payload, &sid).await?;
+    let status = upstream.status();
+    if !status.is_success() {
+        // errors of the Responses API are already in the client's format
+        let body = upstream.bytes().await.unwrap_or_default();
+        return Ok((status, [(CONTENT_TYPE, "application/json")], body).into_response());
+    }
+
+    if stream {
+        return Ok((
+            [(CONTENT_TYPE, "text/event-stream")],
+            Body::from_stream(upstream.bytes_stream()),
+        )
+            .into_response());
+    }
+
+    // Non-stream: the final response, with the output items collected from the stream
+    let mut items: Vec<Value> = vec![];
+    let mut stream = upstream.bytes_stream().eventsource();
+    while let Some(evt) = stream.try_next().await.unwrap_or(None) {
+        let v: Value = match serde_json::from_str(&evt.data) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        let kind = v.get("type").and_then(|v| v.as_str()).unwrap_or("");
+        if kind == "response.output_item.done" {
+            items.push(v.get("item").cloned().unwrap_or(json!({})));
+        } else if kind == "response.completed" || kind == "response.incomplete" {
+            let mut response = v.get("response").cloned().unwrap_or(json!({}));
+            if response["output"].as_array().is_none_or(|o| o.is_empty()) {
+                response["output"] = json!(items);
+            }
+            return Ok((axum::http::StatusCode::OK, Json(response)).into_response());
+        } else if kind == "response.failed" {
+            let error = v
+                .get("response")
+                .and_then(|r| r.get("error"))
+                .cloned()
+                .unwrap_or(json!({"message": "response.failed"}));
+            return Ok((failed_status(&error), Json(json!({"error": error}))).into_response());
+        }
+    }
+    Ok((
+        StatusCode::BAD_GATEWAY,
+        Json(json!({"error": {"message": "Upstream stream ended without a response"}})),
+    )
+        .into_response())
+}
+
 pub async fn codex_list_models() -> impl IntoResponse {
     let data = vec![
         json!({"id": "gpt-5", "object": "model", "owned_by": "owner"}),
@@ -2,7 +2,7 @@ use std::sync::{Arc, Mutex};
 
 use axum::{
     Json,
-    extract::Query,
+    extract::{Query, State},
     response::{Html, IntoResponse},
 };
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
@@ -10,13 +10,17 @@ use chrono::Utc;
 use http::header::CONTENT_TYPE;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{Value, json};
 use sha2::{Digest, Sha256};
 use tracing::{error, info};
 use url::{Url, form_urlencoded};
 use wreq::{Client, ClientBuilder, Method};
 
-use crate::config::{CLEWDR_CONFIG, CodexTokens};
+use super::error::ApiError;
+use crate::{
+    config::{CLEWDR_CONFIG, CodexAccount, CodexTokens, parse_jwt_claim},
+    services::codex_actor::CodexActorHandle,
+};
 
 #[derive(Debug, Clone)]
 struct PendingOauth {
@@ -95,9 +99,12 @@ pub struct CallbackQuery {
 }
 
 /// GET /codex/oauth/callback
-/// Handles OAuth callback, exchanges code, persists tokens, shows a simple result page.
+/// Handles OAuth callback, exchanges code, adds the account to the pool, shows a simple result page.
 #[axum::debug_handler]
-pub async fn api_codex_oauth_callback(q: Query<CallbackQuery>) -> Html<String> {
+pub async fn api_codex_oauth_callback(
+    State(handle): State<CodexActorHandle>,
+    q: Query<CallbackQuery>,
+) -> Html<String> {
     let q = q.0;
     if let Some(err) = q.error.as_deref() {
         let desc = q.error_description.as_deref().unwrap_or("");
@@ -195,28 +202,34 @@ pub async fn api_codex_oauth_callback(q: Query<CallbackQuery>) -> Html<String> {
         .unwrap_or("")
         .to_string();
 
-    // Extract account_id from id_token claims if present
-    let account_id = parse_jwt_claim(
-        &id_token,
-        "https://api.openai.com/auth",
-        "chatgpt_account_id",
-    );
+    // Extract account_id and email from id_token claims if present
+    let claim = |top_ns: &str, key: &str| {
+        parse_jwt_claim(&id_token, top_ns, key).and_then(|v| v.as_str().map(|s| s.to_string()))
+    };
+    let account_id = claim("https://api.openai.com/auth", "chatgpt_account_id");
+    let email = claim("", "email");
 
-    // Persist tokens to config
-    crate::config::CLEWDR_CONFIG.rcu(|conf| {
-        let mut c = crate::config::ClewdrConfig::clone(conf);
-        c.codex.tokens = CodexTokens {
+    // Add the account to the pool, which persists it to config
+    let account = CodexAccount {
+        tokens: CodexTokens {
             id_token: some_if_not_empty(id_token.clone()),
             access_token: some_if_not_empty(access_token.clone()),
             refresh_token: some_if_not_empty(refresh_token.clone()),
             account_id: option_if_not_empty(account_id.clone()),
             last_refresh: Some(Utc::now().to_rfc3339()),
-            api_key: c.codex.tokens.api_key.clone(),
-        };
-        c
-    });
-    if let Err(e) = CLEWDR_CONFIG.load().save().await {
-        error!("Failed to save config: {}", e);
+            api_key: None,
+        },
+        email: option_if_not_empty(email),
+        ..Default::default()
+    };
+    if !account.is_authenticated() {
+        return Html(
+            "<html><body><h2>Login failed</h2><p>No ChatGPT account id in the id token</p></body></html>"
+                .to_string(),
+        );
+    }
+    if let Err(e) = handle.submit(account).await {
+        error!("Failed to add Codex account: {}", e);
     }
 
     // clear pending
@@ -231,29 +244,77 @@ pub async fn api_codex_oauth_callback(q: Query<CallbackQuery>) -> Html<String> {
     )
 }
 
+/// Account of the pool as shown to the admin, without its tokens
+fn account_view(account: &CodexAccount) -> Value {
+    json!({
+        "account_id": account.id(),
+        "email": account.email,
+        "last_refresh": account.tokens.last_refresh,
+        "reset_time": account.reset_time,
+        "requests": account.requests,
+        "usage": account.usage,
+        "invalid": account.invalid,
+    })
+}
+
 /// GET /api/codex/tokens (admin)
 pub async fn api_codex_tokens() -> impl IntoResponse {
     let c = CLEWDR_CONFIG.load();
-    let tokens = &c.codex.tokens;
     Json(json!({
         "authenticated": c.codex.is_authenticated(),
-        "account_id": tokens.account_id,
-        "has_access_token": tokens.access_token.as_ref().map(|s| !s.is_empty()).unwrap_or(false),
-        "last_refresh": tokens.last_refresh,
+        "accounts": c.codex.accounts.len(),
     }))
 }
 
+/// GET /api/codex/accounts (admin)
+/// Lists the valid, rate limited and invalid accounts of the pool
+pub async fn api_codex_accounts(
+    State(handle): State<CodexActorHandle>,
+) -> Result<Json<Value>, ApiError> {
+    let status = handle
+        .get_status()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get Codex accounts: {}", e)))?;
+    Ok(Json(json!({
+        "valid": status.valid.iter().map(account_view).collect::<Vec<_>>(),
+        "exhausted": status.exhausted.iter().map(account_view).collect::<Vec<_>>(),
+        "invalid": status.invalid.iter().map(account_view).collect::<Vec<_>>(),
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountBody {
+    account_id: String,
+}
+
+/// DELETE /api/codex/account (admin)
+pub async fn api_delete_codex_account(
+    State(handle): State<CodexActorHandle>,
+    Json(body): Json<DeleteAccountBody>,
+) -> Result<Json<Value>, ApiError> {
+    handle
+        .delete(body.account_id)
+        .await
+        .map_err(|e| ApiError::bad_request(format!("Failed to delete Codex account: {}", e)))?;
+    Ok(Json(json!({"ok": true})))
+}
+
 /// POST /api/codex/logout (admin)
-pub async fn api_codex_logout() -> impl IntoResponse {
-    crate::config::CLEWDR_CONFIG.rcu(|conf| {
-        let mut c = crate::config::ClewdrConfig::clone(conf);
-        c.codex.tokens = CodexTokens::default();
-        c
-    });
-    if let Err(e) = CLEWDR_CONFIG.load().save().await {
-        error!("Failed to save config: {}", e);
+/// Removes all accounts from the pool
+pub async fn api_codex_logout(
+    State(handle): State<CodexActorHandle>,
+) -> Result<Json<Value>, ApiError> {
+    let status = handle
+        .get_status()
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to get Codex accounts: {}", e)))?;
+    let accounts = status.valid.iter().chain(status.exhausted.iter());
+    for account in accounts.chain(status.invalid.iter()) {
+        if let Err(e) = handle.delete(account.id().to_string()).await {
+            error!("Failed to delete Codex account: {}", e);
+        }
     }
-    Json(json!({"ok": true}))
+    Ok(Json(json!({"ok": true})))
 }
 
 fn http_client() -> Client {
@@ -278,20 +339,6 @@ fn code_challenge_s256(verifier: &str) -> String {
     URL_SAFE_NO_PAD.encode(digest)
 }
 
-fn parse_jwt_claim(token: &str, top_ns: &str, key: &str) -> Option<String> {
-    let parts: Vec<&str> = token.split('.').collect();
-    if parts.len() != 3 {
-        return None;
-    }
-    let payload = parts[1];
-    let decoded = URL_SAFE_NO_PAD.decode(payload.as_bytes()).ok()?;
-    let v: serde_json::Value = serde_json::from_slice(&decoded).ok()?;
-    v.get(top_ns)
-        .and_then(|ns| ns.get(key))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-}
-
 fn html_escape(s: &str) -> String {
     htmlescape::encode_minimal(s)
 }
@@ -28,6 +28,9 @@ pub async fn api_get_config(
         obj.remove("wasted_cookie");
         obj.remove("gemini_keys");
         obj.remove("cli_tokens");
+        if let Some(codex) = obj.get_mut("codex").and_then(|c| c.as_object_mut()) {
+            codex.remove("accounts");
+        }
         obj["vertex"]["credential"] = "placeholder".into();
     }
 
@@ -59,6 +62,7 @@ pub async fn api_post_config(
         new_c.wasted_cookie = old_c.wasted_cookie.to_owned();
         new_c.gemini_keys = old_c.gemini_keys.to_owned();
         new_c.cli_tokens = old_c.cli_tokens.to_owned();
+        new_c.codex.accounts = old_c.codex.accounts.to_owned();
         if new_c.vertex.credential.is_none() {
             new_c.vertex.credential = old_c.vertex.credential.to_owned();
         }
@@ -1,25 +1,31 @@
 use async_stream::stream;
 use axum::{
+    Json,
     body::Body,
     extract::State,
-    response::{IntoResponse, Response},
+    response::{IntoResponse, Response, Sse},
 };
 use bytes::Bytes;
 use colored::Colorize;
+use eventsource_stream::Eventsource;
 use futures::{FutureExt, Stream, StreamExt, pin_mut};
 use http::header::CONTENT_TYPE;
 use serde::Serialize;
 use tokio::select;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
     error::ClewdrError,
     gemini_state::{GeminiApiFormat, GeminiState},
-    middleware::gemini::{GeminiContext, GeminiOaiPreprocess, GeminiPreprocess},
+    middleware::gemini::{
+        GeminiClaudePreprocess, GeminiContext, GeminiOaiPreprocess, GeminiPreprocess,
+        transform_claude_stream, transforms_claude_json,
+    },
+    types::gemini::response::GeminiResponse,
     utils::enabled,
 };
 
-// Common handler function to process both Gemini and OpenAI format requests
+// Common handler function to process Gemini, OpenAI and Claude format requests
 pub async fn handle_gemini_request<T: Serialize + Clone + Send + 'static>(
     mut state: GeminiState,
     body: T,
@@ -32,21 +38,29 @@ pub async fn handle_gemini_request<T: Serialize + Clone + Send + 'static>(
         vertex,
         ..
     } = ctx;
+    let claude = ctx.api_format == GeminiApiFormat::Claude;
     info!(
         "[REQ] stream: {}, vertex: {}, format: {}, model: {}",
         enabled(stream),
         enabled(vertex),
-        if ctx.api_format == GeminiApiFormat::Gemini {
-            ctx.api_format.to_string().green()
-        } else {
-            ctx.api_format.to_string().yellow()
+        match ctx.api_format {
+            GeminiApiFormat::Gemini => ctx.api_format.to_string().green(),
+            GeminiApiFormat::OpenAI => ctx.api_format.to_string().yellow(),
+            GeminiApiFormat::Claude => ctx.api_format.to_string().cyan(),
         },
         model.green(),
     );
 
     // For non-streaming requests, we need to handle keep-alive differently
     if !stream {
-        let stream = keep_alive_stream(state, body);
+        let future = async move {
+            match state.try_chat(body).await {
+                Ok(res) if claude => to_claude_json(res, &model).await,
+                Ok(res) => res,
+                Err(e) => e.into_response(),
+            }
+        };
+        let stream = keep_alive_stream(future);
         let res = Response::builder()
             .header(CONTENT_TYPE, "application/json")
             .body(Body::from_stream(stream))?;
@@ -55,27 +69,34 @@ pub async fn handle_gemini_request<T: Serialize + Clone + Send + 'static>(
 
     // For streaming requests, proceed as before
     let res = state.try_chat(body).await?;
+    if claude {
+        let stream = res.into_body().into_data_stream().eventsource();
+        return Ok(Sse::new(transform_claude_stream(stream, model))
+            .keep_alive(Default::default())
+            .into_response());
+    }
     Ok(res)
 }
 
-fn keep_alive_stream<T>(
-    mut state: GeminiState,
-    body: T,
-) -> impl Stream<Item = Result<Bytes, axum::Error>>
-where
-    T: Serialize + Clone + Send + 'static,
-{
+/// Converts a Gemini response into a Claude message, other responses are returned as is
+async fn to_claude_json(res: Response, model: &str) -> Response {
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .inspect_err(|e| warn!("Failed to read Gemini response body: {}", e))
+        .unwrap_or_default();
+    match serde_json::from_slice::<GeminiResponse>(&bytes) {
+        Ok(res) => Json(transforms_claude_json(res, model)).into_response(),
+        Err(_) => Response::new(Body::from(bytes)),
+    }
+}
+
+fn keep_alive_stream(
+    future: impl Future<Output = Response> + Send + 'static,
+) -> impl Stream<Item = Result<Bytes, axum::Error>> {
     let mut interval = tokio::time::interval(std::time::Duration::from_secs(15));
     let time_out = std::time::Duration::from_secs(360);
     stream! {
-        let future = async move {
-            state
-                .try_chat(body.clone())
-                .await
-                .unwrap_or_else(|e| e.into_response())
-                .into_body()
-                .into_data_stream()
-        };
+        let future = future.map(|res| res.into_body().into_data_stream());
         let stream = future.into_stream().flatten();
         pin_mut!(stream);
         let start = std::time::Instant::now();
@@ -117,3 +138,10 @@ pub async fn api_post_gemini_oai(
 ) -> Result<Response, ClewdrError> {
     handle_gemini_request(state, body, ctx).await
 }
+
+pub async fn api_post_gemini_claude(
+    State(state): State<GeminiState>,
+    GeminiClaudePreprocess(body, ctx): GeminiClaudePreprocess,
+) -> Result<Response, ClewdrError> {
+    handle_gemini_request(state, body, ctx).await
+}
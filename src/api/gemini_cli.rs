@@ -38,9 +38,9 @@ fn add_continue_turn_gemini(body: &mut GeminiRequestBody) {
 }
 
 fn add_done_instruction_oai(body: &mut CreateMessageParams) {
-    use crate::types::claude::{Message as CMessage, Role as CRole};
-    let sys = CMessage::new_text(
-        CRole::System,
+    use crate::types::oai::{Message, Role};
+    let sys = Message::new_text(
+        Role::System,
         format!(
             "请确保回答完整无遗漏，且在最后一行单独输出：{}。如果回答被中途打断，请在后续继续补充至完整再输出该标记。",
             DONE_MARKER
@@ -50,8 +50,8 @@ fn add_done_instruction_oai(body: &mut CreateMessageParams) {
 }
 
 fn add_continue_turn_oai(body: &mut CreateMessageParams) {
-    use crate::types::claude::{Message as CMessage, Role as CRole};
-    let user = CMessage::new_text(CRole::User, CONTINUATION_PROMPT);
+    use crate::types::oai::{Message, Role};
+    let user = Message::new_text(Role::User, CONTINUATION_PROMPT);
     body.messages.push(user);
 }
 
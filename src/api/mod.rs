@@ -16,14 +16,17 @@ mod storage;
 pub use claude_code::api_claude_code;
 /// Message handling endpoints for creating and managing chat conversations
 pub use claude_web::api_claude_web;
-pub use codex::{codex_chat_completions, codex_completions, codex_list_models};
+pub use codex::{
+    codex_chat_completions, codex_completions, codex_list_models, codex_messages, codex_responses,
+};
 pub use codex_oauth::{
-    api_codex_logout, api_codex_oauth_callback, api_codex_oauth_start, api_codex_tokens,
+    api_codex_accounts, api_codex_logout, api_codex_oauth_callback, api_codex_oauth_start,
+    api_codex_tokens, api_delete_codex_account,
 };
 /// Configuration related endpoints for retrieving and updating Clewdr settings
 pub use config::{api_get_config, api_post_config};
 pub use error::ApiError;
-pub use gemini::{api_post_gemini, api_post_gemini_oai};
+pub use gemini::{api_post_gemini, api_post_gemini_claude, api_post_gemini_oai};
 pub use gemini_cli::{
     api_gemini_cli_model_info, api_gemini_cli_models, api_post_gemini_cli, api_post_gemini_cli_oai,
 };
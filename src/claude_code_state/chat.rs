@@ -1,15 +1,25 @@
 use colored::Colorize;
+use itertools::Itertools;
 use snafu::ResultExt;
-use tracing::{Instrument, error, info};
+use tracing::{Instrument, error, info, warn};
 
 use crate::{
     claude_code_state::{ClaudeCodeState, TokenStatus},
     config::CLEWDR_CONFIG,
-    error::{CheckClaudeErr, ClewdrError, WreqSnafu},
+    error::{CheckClaudeErr, ClewdrError, WreqSnafu, claude_error},
     types::claude::CreateMessageParams,
     utils::forward_response,
 };
 
+/// Beta flag required for OAuth access tokens
+const OAUTH_BETA: &str = "oauth-2025-04-20";
+/// Beta flag enabling the 1M context window
+const CONTEXT_1M_BETA: &str = "context-1m-2025-08-07";
+/// Model suffix selecting the 1M context window
+const CONTEXT_1M_SUFFIX: &str = "-1M";
+/// Context window of models without the 1M beta
+const STANDARD_CONTEXT: u32 = 200_000;
+
 impl ClaudeCodeState {
     /// Attempts to send a chat message to Claude API with retry mechanism
     ///
@@ -31,16 +41,26 @@ impl ClaudeCodeState {
     /// * `Result<axum::response::Response, ClewdrError>` - Formatted response or error
     pub async fn try_chat(
         &mut self,
-        p: CreateMessageParams,
+        mut p: CreateMessageParams,
     ) -> Result<axum::response::Response, ClewdrError> {
         for i in 0..CLEWDR_CONFIG.load().max_retries + 1 {
             if i > 0 {
                 info!("[RETRY] attempt: {}", i.to_string().green());
             }
             let mut state = self.to_owned();
+            let require_1m = p.model.ends_with(CONTEXT_1M_SUFFIX);
+            let cookie = match state.request_cookie(require_1m).await {
+                Err(ClewdrError::NoCookieAvailable) if require_1m => {
+                    if !self.fits_standard_context(&p) {
+                        return Err(ClewdrError::Context1MUnavailable);
+                    }
+                    warn!("No cookie supports 1M context, falling back to standard context");
+                    p.model.truncate(p.model.len() - CONTEXT_1M_SUFFIX.len());
+                    state.request_cookie(false).await?
+                }
+                res => res?,
+            };
             let p = p.to_owned();
-
-            let cookie = state.request_cookie().await?;
             let retry = async {
                 match state.check_token() {
                     TokenStatus::None => {
@@ -75,6 +95,13 @@ impl ClaudeCodeState {
             ));
             match retry.await {
                 Ok(res) => {
+                    if require_1m
+                        && let Some(cookie) = state.cookie.as_mut()
+                        && cookie.context_1m.is_none()
+                    {
+                        cookie.context_1m = Some(true);
+                        state.return_cookie(None).await;
+                    }
                     return Ok(res);
                 }
                 Err(e) => {
@@ -88,6 +115,14 @@ impl ClaudeCodeState {
                         state.return_cookie(Some(reason.to_owned())).await;
                         continue;
                     }
+                    // remember the account lacks 1M context and try another cookie
+                    if let ClewdrError::Context1MUnavailable = e {
+                        if let Some(cookie) = state.cookie.as_mut() {
+                            cookie.context_1m = Some(false);
+                        }
+                        state.return_cookie(None).await;
+                        continue;
+                    }
                     return Err(e);
                 }
             }
@@ -101,13 +136,13 @@ impl ClaudeCodeState {
         mut p: CreateMessageParams,
     ) -> Result<axum::response::Response, ClewdrError> {
         // Check if model is 1M context version and prepare for API
-        let beta_header = if let Some(model) = p.model.strip_suffix("-1M") {
+        let mut required = vec![OAUTH_BETA];
+        if let Some(model) = p.model.strip_suffix(CONTEXT_1M_SUFFIX) {
             // Remove -1M suffix before sending to API
             p.model = model.to_string();
-            "oauth-2025-04-20,context-1m-2025-08-07"
-        } else {
-            "oauth-2025-04-20"
-        };
+            required.push(CONTEXT_1M_BETA);
+        }
+        let beta_header = self.merge_betas(&required);
 
         let api_res = self
             .client
@@ -120,9 +155,39 @@ impl ClaudeCodeState {
             .await
             .context(WreqSnafu {
                 msg: "Failed to send chat message",
-            })?
-            .check_claude()
-            .await?;
-        forward_response(api_res)
+            })?;
+        let status = api_res.status();
+        if required.contains(&CONTEXT_1M_BETA) && matches!(status.as_u16(), 400 | 429) {
+            // account lacks the long context entitlement for the 1M beta
+            let reset_header = api_res
+                .headers()
+                .get("anthropic-ratelimit-unified-reset")
+                .cloned();
+            let text = api_res.text().await.unwrap_or_default();
+            if text.to_ascii_lowercase().contains("long context") {
+                return Err(ClewdrError::Context1MUnavailable);
+            }
+            return Err(claude_error(status, reset_header.as_ref(), &text));
+        }
+        forward_response(api_res.check_claude().await?)
+    }
+
+    /// Checks whether the request fits the standard context window without the 1M beta
+    fn fits_standard_context(&self, p: &CreateMessageParams) -> bool {
+        self.usage.input_tokens.saturating_add(p.max_tokens) <= STANDARD_CONTEXT
+    }
+
+    /// Merges the required beta flags with the ones sent by the client
+    ///
+    /// Client flags are filtered through the configured allow/deny lists,
+    /// required flags always come first and duplicates are dropped
+    fn merge_betas(&self, required: &[&str]) -> String {
+        let config = CLEWDR_CONFIG.load();
+        let client = self
+            .anthropic_beta
+            .iter()
+            .map(String::as_str)
+            .filter(|b| config.cc_beta_allowed(b));
+        required.iter().copied().chain(client).unique().join(",")
     }
 }
@@ -193,6 +193,19 @@ impl ClaudeCodeState {
     }
 
     pub async fn refresh_token(&mut self) -> Result<(), ClewdrError> {
+        if self
+            .cookie
+            .as_ref()
+            .and_then(|c| c.token.as_ref())
+            .is_some_and(|t| !t.is_expired())
+        {
+            return Ok(());
+        }
+        self.renew_token().await
+    }
+
+    /// Exchanges the refresh token for a new access token, regardless of expiry
+    pub async fn renew_token(&mut self) -> Result<(), ClewdrError> {
         let wreq_client = self.get_wreq_client();
         let Some(CookieStatus {
             token: Some(ref mut token),
@@ -203,9 +216,6 @@ impl ClaudeCodeState {
                 msg: "No token found to refresh token",
             });
         };
-        if !token.is_expired() {
-            return Ok(());
-        }
 
         let cc_client_id = CLEWDR_CONFIG.load().cc_client_id();
 
@@ -31,6 +31,7 @@ pub struct ClaudeCodeState {
     pub stream: bool,
     pub system_prompt_hash: Option<u64>,
     pub usage: Usage,
+    pub anthropic_beta: Vec<String>,
 }
 
 impl ClaudeCodeState {
@@ -47,6 +48,7 @@ impl ClaudeCodeState {
             stream: false,
             system_prompt_hash: None,
             usage: Usage::default(),
+            anthropic_beta: Vec::new(),
         }
     }
 
@@ -82,13 +84,27 @@ impl ClaudeCodeState {
 
     /// Requests a new cookie from the cookie manager
     /// Updates the internal state with the new cookie and proxy configuration
-    pub async fn request_cookie(&mut self) -> Result<CookieStatus, ClewdrError> {
-        let res = self
-            .cookie_actor_handle
-            .request(self.system_prompt_hash)
-            .await?;
-        self.cookie = Some(res.to_owned());
-        self.cookie_header_value = HeaderValue::from_str(res.cookie.to_string().as_str())?;
+    ///
+    /// When `require_1m` is set, cookies known to lack the 1M context window are skipped
+    pub async fn request_cookie(&mut self, require_1m: bool) -> Result<CookieStatus, ClewdrError> {
+        let res = if require_1m {
+            self.cookie_actor_handle
+                .request_1m(self.system_prompt_hash)
+                .await?
+        } else {
+            self.cookie_actor_handle
+                .request(self.system_prompt_hash)
+                .await?
+        };
+        self.set_cookie(res.to_owned())?;
+        Ok(res)
+    }
+
+    /// Binds the state to the given cookie
+    /// Updates the internal state with the cookie and proxy configuration
+    pub fn set_cookie(&mut self, cookie: CookieStatus) -> Result<(), ClewdrError> {
+        self.cookie_header_value = HeaderValue::from_str(cookie.cookie.to_string().as_str())?;
+        self.cookie = Some(cookie);
         // Always pull latest proxy/endpoint before building the client
         self.proxy = CLEWDR_CONFIG.load().wreq_proxy.to_owned();
         self.endpoint = CLEWDR_CONFIG.load().endpoint();
@@ -101,7 +117,7 @@ impl ClaudeCodeState {
         self.client = client.build().context(WreqSnafu {
             msg: "Failed to build client with new cookie",
         })?;
-        Ok(res)
+        Ok(())
     }
 
     pub fn check_token(&self) -> TokenStatus {
@@ -5,13 +5,15 @@ use snafu::ResultExt;
 
 use super::ClaudeCodeState;
 use crate::{
-    config::Reason,
+    config::{OrgInfo, Reason},
     error::{CheckClaudeErr, ClewdrError, WreqSnafu},
     utils::print_out_json,
 };
 
 impl ClaudeCodeState {
-    pub async fn get_organization(&self) -> Result<String, ClewdrError> {
+    /// Picks the organization to authorize, following the preference of the cookie
+    /// The discovered organizations and the chosen one are recorded on the cookie
+    pub async fn get_organization(&mut self) -> Result<String, ClewdrError> {
         let end_point = format!("{}/api/bootstrap", self.endpoint);
         let res = self
             .build_request(Method::GET, end_point)
@@ -32,19 +34,17 @@ impl ClaudeCodeState {
         let memberships = bootstrap["account"]["memberships"]
             .as_array()
             .ok_or(Reason::Null)?;
-        let boot_acc_info = memberships
+        let orgs = memberships
             .iter()
-            .find(|m| {
-                m["organization"]["capabilities"]
-                    .as_array()
-                    .is_some_and(|c| c.iter().any(|c| c.as_str() == Some("chat")))
-            })
-            .and_then(|m| m["organization"].as_object())
+            .filter_map(|m| OrgInfo::from_value(&m["organization"]))
+            .collect::<Vec<_>>();
+        let boot_acc_info = self
+            .cookie
+            .as_ref()
+            .and_then(|c| c.preferred_org(&orgs))
+            .or_else(|| orgs.iter().find(|o| o.can_chat()))
             .ok_or(Reason::Null)?;
-        let capabilities = boot_acc_info["capabilities"]
-            .as_array()
-            .map(|a| a.iter().filter_map(|c| c.as_str()).collect::<Vec<_>>())
-            .unwrap_or_default();
+        let capabilities = &boot_acc_info.capabilities;
         if !capabilities.iter().any(|c| {
             c.contains("pro")
                 || c.contains("enterprise")
@@ -56,12 +56,7 @@ impl ClaudeCodeState {
         let email = bootstrap["account"]["email_address"]
             .as_str()
             .unwrap_or_default();
-        let uuid = boot_acc_info["uuid"]
-            .as_str()
-            .ok_or(ClewdrError::UnexpectedNone {
-                msg: "Failed to get organization UUID",
-            })?
-            .to_string();
+        let uuid = boot_acc_info.uuid.to_owned();
 
         println!(
             "[{}]\nemail: {}\ncapabilities: {}",
@@ -69,6 +64,9 @@ impl ClaudeCodeState {
             email.blue(),
             capabilities.join(", ").blue()
         );
+        if let Some(cookie) = self.cookie.as_mut() {
+            cookie.set_orgs(&uuid, orgs);
+        }
         Ok(uuid)
     }
 }
@@ -7,7 +7,7 @@ use wreq::Method;
 
 use crate::{
     claude_web_state::ClaudeWebState,
-    config::{CLEWDR_CONFIG, Reason},
+    config::{CLEWDR_CONFIG, OrgInfo, Reason},
     error::{CheckClaudeErr, ClewdrError, WreqSnafu},
     utils::print_out_json,
 };
@@ -48,29 +48,20 @@ impl ClaudeWebState {
                 .ok_or(ClewdrError::UnexpectedNone {
                     msg: "Failed to get memberships from bootstrap",
                 })?;
-        let boot_acc_info = memberships
+        let member_orgs = memberships
             .iter()
-            .find(|m| {
-                m["organization"]["capabilities"]
-                    .as_array()
-                    .is_some_and(|c| c.iter().any(|c| c.as_str() == Some("chat")))
-            })
-            .and_then(|m| m["organization"].as_object())
+            .filter_map(|m| OrgInfo::from_value(&m["organization"]))
+            .collect::<Vec<_>>();
+        let boot_acc_info = self
+            .preferred_org(&member_orgs)
+            .or_else(|| member_orgs.iter().find(|o| o.can_chat()))
             .ok_or(ClewdrError::UnexpectedNone {
                 msg: "Failed to find a valid organization in bootstrap",
             })?;
         let email = bootstrap["account"]["email_address"]
             .as_str()
             .unwrap_or_default();
-        self.capabilities = boot_acc_info["capabilities"]
-            .as_array()
-            .map(|a| {
-                a.iter()
-                    .filter_map(|c| c.as_str())
-                    .map(|c| c.to_string())
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default();
+        self.capabilities = boot_acc_info.capabilities.to_owned();
         if !self.is_pro() && CLEWDR_CONFIG.load().skip_non_pro {
             return Err(Reason::NonPro.into());
         }
@@ -98,22 +89,18 @@ impl ClaudeWebState {
             msg: "Failed to parse organizations response",
         })?;
         print_out_json(&ret_json, "org.json");
-        let acc_info = ret_json
-            .as_array()
-            .and_then(|a| {
-                a.iter()
-                    .filter(|v| {
-                        v.get("capabilities")
-                            .and_then(|c| c.as_array())
-                            .is_some_and(|c| c.iter().any(|c| c.as_str() == Some("chat")))
-                    })
-                    .max_by_key(|v| {
-                        v.get("capabilities")
-                            .and_then(|c| c.as_array())
-                            .map(|c| c.len())
-                            .unwrap_or_default()
-                    })
-            })
+        let org_values = ret_json.as_array().map(Vec::as_slice).unwrap_or_default();
+        let orgs = org_values
+            .iter()
+            .filter_map(OrgInfo::from_value)
+            .collect::<Vec<_>>();
+        let chosen = self.preferred_org(&orgs).or_else(|| {
+            orgs.iter()
+                .filter(|o| o.can_chat())
+                .max_by_key(|o| o.capabilities.len())
+        });
+        let acc_info = chosen
+            .and_then(|o| org_values.iter().find(|v| v["uuid"] == o.uuid.as_str()))
             .ok_or(ClewdrError::UnexpectedNone {
                 msg: "Failed to find a valid organization in response",
             })?;
@@ -128,9 +115,19 @@ impl ClaudeWebState {
                     msg: "Failed to find UUID in organization response",
                 })?;
         self.org_uuid = Some(u.to_string());
+        if let Some(cookie) = self.cookie.as_mut()
+            && cookie.set_orgs(u, orgs)
+        {
+            self.return_cookie(None).await;
+        }
         Ok(())
     }
 
+    /// Picks the organization preferred by the current cookie
+    fn preferred_org<'a>(&self, orgs: &'a [OrgInfo]) -> Option<&'a OrgInfo> {
+        self.cookie.as_ref()?.preferred_org(orgs)
+    }
+
     /// Checks if the account has any restrictions, warnings or bans
     ///
     /// Examines the account flags to determine if the account can be used:
@@ -1,11 +1,10 @@
 use colored::Colorize;
-use futures::TryFutureExt;
 use serde_json::json;
 use snafu::ResultExt;
-use tracing::{Instrument, debug, error, info, info_span, warn};
+use tracing::{Instrument, debug, error, info, info_span};
 use wreq::{Method, Response, header::ACCEPT};
 
-use super::ClaudeWebState;
+use super::{CONVERSATION_PREFIX, ClaudeWebState, conversation::prefix_hash};
 use crate::{
     config::CLEWDR_CONFIG,
     error::{CheckClaudeErr, ClewdrError, WreqSnafu},
@@ -36,6 +35,11 @@ impl ClaudeWebState {
         &mut self,
         p: CreateMessageParams,
     ) -> Result<axum::response::Response, ClewdrError> {
+        self.emulate_tools = p.tools.as_ref().is_some_and(|t| !t.is_empty());
+        let (req_hash, req_len) = (
+            prefix_hash(p.system.as_ref(), &p.messages),
+            p.messages.len(),
+        );
         for i in 0..CLEWDR_CONFIG.load().max_retries + 1 {
             if i > 0 {
                 info!("[RETRY] attempt: {}", i.to_string().green());
@@ -45,23 +49,25 @@ impl ClaudeWebState {
 
             let cookie = state.request_cookie().await?;
             // check if request is successful
-            let web_res = async { state.bootstrap().await.and(state.send_chat(p).await) };
-            let transform_res = web_res
-                .and_then(async |r| self.transform_response(r).await)
-                .instrument(info_span!("claude_web", "cookie" = cookie.cookie.ellipse()));
+            let transform_res = async {
+                state.bootstrap().await?;
+                let r = state.send_chat(p).await?;
+                state.transform_response(r).await
+            }
+            .instrument(info_span!("claude_web", "cookie" = cookie.cookie.ellipse()));
 
             match transform_res.await {
                 Ok(b) => {
-                    if let Err(e) = state.clean_chat().await {
-                        warn!("Failed to clean chat: {}", e);
+                    if state.session_key.is_some() {
+                        // keep the conversation for the next turn once the reply is complete
+                        return Ok(state.keep_on_completion(b, req_hash, req_len));
                     }
+                    state.clean_chat();
                     return Ok(b);
                 }
                 Err(e) => {
                     // delete chat after an error
-                    if let Err(e) = state.clean_chat().await {
-                        warn!("Failed to clean chat: {}", e);
-                    }
+                    state.clean_chat();
                     error!("{e}");
                     // 429 error
                     if let ClewdrError::InvalidCookie { reason } = e {
@@ -79,7 +85,8 @@ impl ClaudeWebState {
     /// Sends a message to the Claude API by creating a new conversation and processing the request
     ///
     /// This method performs several key operations:
-    /// - Creates a new conversation with a unique UUID
+    /// - Resumes the session conversation when the request extends it, sending only the new turn
+    /// - Otherwise creates a new conversation with a unique UUID
     /// - Configures thinking mode if applicable
     /// - Transforms the client request to the Claude API format
     /// - Handles image uploads if present
@@ -102,28 +109,11 @@ impl ClaudeWebState {
                 msg: "Organization UUID is not set",
             })?;
 
-        // Create a new conversation
-        let new_uuid = uuid::Uuid::new_v4().to_string();
-        let endpoint = format!(
-            "{}/api/organizations/{}/chat_conversations",
-            self.endpoint, org_uuid
-        );
-        let body = json!({
-            "uuid": new_uuid,
-            "name": format!("ClewdR-{}", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S")),
-        });
-
-        self.build_request(Method::POST, endpoint)
-            .json(&body)
-            .send()
-            .await
-            .context(WreqSnafu {
-                msg: "Failed to create new conversation",
-            })?
-            .check_claude()
-            .await?;
-        self.conv_uuid = Some(new_uuid.to_string());
-        debug!("New conversation created: {}", new_uuid);
+        let turn = self.resume(&p);
+        let new_uuid = match (&turn, &self.conv_uuid) {
+            (Some(_), Some(conv_uuid)) => conv_uuid.to_owned(),
+            _ => self.create_conversation(&org_uuid).await?,
+        };
 
         let mut body = json!({});
         // enable thinking mode
@@ -144,17 +134,34 @@ impl ClaudeWebState {
             .await;
         // generate the request body
         // check if the request is empty
-        let mut body = self.transform_request(p).ok_or(ClewdrError::BadRequest {
+        let mut body = match turn {
+            Some(turn) => {
+                let mut body = self.transform_turn(p, turn);
+                if let Some(ref mut body) = body {
+                    body.parent_message_uuid = Some(self.leaf_message_uuid().await?);
+                }
+                body
+            }
+            None => self.transform_request(p),
+        }
+        .ok_or(ClewdrError::BadRequest {
             msg: "Request body is empty",
         })?;
 
-        // check images
+        // check images, downloading remote ones
         let images = body.images.drain(..).collect::<Vec<_>>();
+        let images = self.fetch_remote_images(images).await?;
 
         // upload images
-        let files = self.upload_images(images).await;
+        let files = self.upload_images(images).await?;
         body.files = files;
 
+        // upload documents
+        let documents = body.documents.drain(..).collect::<Vec<_>>();
+        let (files, attachments) = self.upload_documents(documents).await?;
+        body.files.extend(files);
+        body.attachments.extend(attachments);
+
         // send the request
         print_out_json(&body, "claude_web_clewdr_req.json");
         let endpoint = format!(
@@ -173,4 +180,33 @@ impl ClaudeWebState {
             .check_claude()
             .await
     }
+
+    /// Creates a new conversation and sets it as the current one
+    ///
+    /// # Returns
+    /// * `Result<String, ClewdrError>` - UUID of the new conversation or error
+    async fn create_conversation(&mut self, org_uuid: &str) -> Result<String, ClewdrError> {
+        let new_uuid = uuid::Uuid::new_v4().to_string();
+        let endpoint = format!(
+            "{}/api/organizations/{}/chat_conversations",
+            self.endpoint, org_uuid
+        );
+        let body = json!({
+            "uuid": new_uuid,
+            "name": format!("{CONVERSATION_PREFIX}{}", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S")),
+        });
+
+        self.build_request(Method::POST, endpoint)
+            .json(&body)
+            .send()
+            .await
+            .context(WreqSnafu {
+                msg: "Failed to create new conversation",
+            })?
+            .check_claude()
+            .await?;
+        self.conv_uuid = Some(new_uuid.to_string());
+        debug!("New conversation created: {}", new_uuid);
+        Ok(new_uuid)
+    }
 }
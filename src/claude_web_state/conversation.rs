@@ -0,0 +1,318 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    time::Duration,
+};
+
+use axum::{body::Body, response::Response};
+use futures::StreamExt;
+use moka::{Entry, notification::RemovalCause, sync::Cache};
+use serde_json::Value;
+use snafu::ResultExt;
+use tracing::debug;
+use wreq::Method;
+
+use crate::{
+    claude_web_state::ClaudeWebState,
+    config::CookieStatus,
+    error::{CheckClaudeErr, ClewdrError, WreqSnafu},
+    services::cookie_actor::CookieActorHandle,
+    types::claude::{
+        ContentBlock, CreateMessageParams, CreateMessageResponse, Message, MessageContent, Role,
+    },
+};
+
+/// A claude.ai conversation kept alive across the requests of one client session
+#[derive(Clone, Debug)]
+pub struct WebSession {
+    cookie: CookieStatus,
+    org_uuid: String,
+    conv_uuid: String,
+    /// Hash of the system prompt and the client messages already in the conversation
+    prefix_hash: u64,
+    /// Number of client messages covered by `prefix_hash`
+    prefix_len: usize,
+    /// Hash of the text of the reply generated by claude.ai
+    reply_hash: u64,
+}
+
+impl WebSession {
+    /// Returns the new user turn if the request continues this conversation
+    ///
+    /// The request must repeat the known prefix, followed by the unedited assistant
+    /// reply and one or more user messages
+    fn new_turn(&self, p: &CreateMessageParams) -> Option<Vec<Message>> {
+        let (prefix, rest) = p.messages.split_at_checked(self.prefix_len)?;
+        let (reply, turn) = rest.split_first()?;
+        if reply.role != Role::Assistant
+            || reply_hash(&message_text(reply)) != self.reply_hash
+            || turn.is_empty()
+            || turn.iter().any(|m| m.role != Role::User)
+        {
+            return None;
+        }
+        (prefix_hash(p.system.as_ref(), prefix) == self.prefix_hash).then(|| turn.to_vec())
+    }
+}
+
+/// Builds the cache mapping client sessions to claude.ai conversations
+///
+/// Sessions idle for too long are evicted and their conversations cleaned up.
+/// Replaced sessions are cleaned up by `keep_conversation`, which can tell
+/// whether the conversation changed
+pub(super) fn session_cache(handle: CookieActorHandle) -> Cache<u64, WebSession> {
+    Cache::builder()
+        .max_capacity(1000)
+        .time_to_idle(Duration::from_secs(60 * 60))
+        .eviction_listener(move |_, session, cause| {
+            if matches!(cause, RemovalCause::Expired | RemovalCause::Size) {
+                ClaudeWebState::new(handle.to_owned()).discard(session);
+            }
+        })
+        .build()
+}
+
+/// Identifies a client session by the client, its system prompt and first message
+pub fn session_key(client: u64, p: &CreateMessageParams) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    client.hash(&mut hasher);
+    prefix_hash(p.system.as_ref(), &p.messages[..p.messages.len().min(1)]).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes the system prompt together with a slice of messages
+pub fn prefix_hash(system: Option<&Value>, msgs: &[Message]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    system.hash(&mut hasher);
+    msgs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes the text of a reply, ignoring surrounding whitespace
+fn reply_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.trim().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Concatenates the text blocks of a message
+fn message_text(msg: &Message) -> String {
+    match &msg.content {
+        MessageContent::Text { content } => content.to_owned(),
+        MessageContent::Blocks { content } => content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+/// Keeps the conversation of a reply once its body has been sent completely
+///
+/// A reply that fails or is dropped halfway leaves the conversation with a partial
+/// or still generating message, so it is cleaned up instead of being resumed
+struct ReplyWatcher {
+    state: Option<ClaudeWebState>,
+    prefix_hash: u64,
+    prefix_len: usize,
+    failed: bool,
+    /// Text of the reply, collected from the text deltas of a stream
+    text: String,
+    /// Incomplete line of a stream, or the whole body of a non-stream reply
+    buf: Vec<u8>,
+}
+
+impl ReplyWatcher {
+    fn feed(&mut self, chunk: &[u8], stream: bool) {
+        if !stream {
+            self.buf.extend_from_slice(chunk);
+            return;
+        }
+        for &b in chunk {
+            if b != b'\n' {
+                self.buf.push(b);
+                continue;
+            }
+            let line = std::mem::take(&mut self.buf);
+            if let Some(data) = line.strip_prefix(b"data:") {
+                self.observe(data);
+            }
+        }
+    }
+
+    fn observe(&mut self, data: &[u8]) {
+        let Ok(data) = serde_json::from_slice::<Value>(data) else {
+            return;
+        };
+        if let Some(text) = data["completion"].as_str() {
+            self.text.push_str(text);
+        } else if data["type"] == "content_block_delta" && data["delta"]["type"] == "text_delta" {
+            self.text
+                .push_str(data["delta"]["text"].as_str().unwrap_or_default());
+        } else if data["type"] == "error" {
+            self.failed = true;
+        }
+    }
+
+    fn finish(&mut self, stream: bool) {
+        let Some(state) = self.state.take() else {
+            return;
+        };
+        if !stream {
+            match serde_json::from_slice::<CreateMessageResponse>(&self.buf) {
+                Ok(res) => {
+                    self.text = message_text(&Message::new_blocks(Role::Assistant, res.content))
+                }
+                Err(_) => self.failed = true,
+            }
+        }
+        if self.failed {
+            state.clean_chat();
+        } else {
+            let reply_hash = reply_hash(&self.text);
+            state.keep_conversation(self.prefix_hash, self.prefix_len, reply_hash);
+        }
+    }
+}
+
+impl Drop for ReplyWatcher {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            debug!("Reply was not completed, discarding conversation");
+            state.clean_chat();
+        }
+    }
+}
+
+impl ClaudeWebState {
+    /// Remembers the current conversation for the next request of the session,
+    /// once the body of the response has been sent without error
+    ///
+    /// # Arguments
+    /// * `res` - The response whose body carries the reply
+    /// * `prefix_hash` - Hash of the system prompt and all messages of the request
+    /// * `prefix_len` - Number of messages in the request
+    pub(super) fn keep_on_completion(
+        &self,
+        res: Response,
+        prefix_hash: u64,
+        prefix_len: usize,
+    ) -> Response {
+        let mut watcher = ReplyWatcher {
+            state: Some(self.to_owned()),
+            prefix_hash,
+            prefix_len,
+            failed: false,
+            text: String::new(),
+            buf: Vec::new(),
+        };
+        let stream = self.stream;
+        res.map(|body| {
+            let mut body = body.into_data_stream();
+            Body::from_stream(async_stream::stream! {
+                while let Some(chunk) = body.next().await {
+                    match chunk {
+                        Ok(ref chunk) => watcher.feed(chunk, stream),
+                        Err(_) => watcher.failed = true,
+                    }
+                    yield chunk;
+                }
+                watcher.finish(stream);
+            })
+        })
+    }
+
+    /// Resumes the conversation of the current session if the request extends it
+    ///
+    /// On success the conversation uuid is set and the new user turn is returned,
+    /// otherwise the stale conversation is discarded and `None` is returned
+    pub(super) fn resume(&mut self, p: &CreateMessageParams) -> Option<Vec<Message>> {
+        let session = self.conversations.as_ref()?.remove(&self.session_key?)?;
+        let turn = (Some(&session.cookie) == self.cookie.as_ref()
+            && Some(&session.org_uuid) == self.org_uuid.as_ref())
+        .then(|| session.new_turn(p))
+        .flatten();
+        let Some(turn) = turn else {
+            debug!("Request does not extend conversation {}", session.conv_uuid);
+            self.discard(session);
+            return None;
+        };
+        debug!("Resuming conversation: {}", session.conv_uuid);
+        self.conv_uuid = Some(session.conv_uuid);
+        Some(turn)
+    }
+
+    /// Remembers the current conversation for the next request of the session
+    fn keep_conversation(&self, prefix_hash: u64, prefix_len: usize, reply_hash: u64) {
+        let (Some(conversations), Some(key), Some(cookie), Some(org_uuid), Some(conv_uuid)) = (
+            &self.conversations,
+            self.session_key,
+            self.cookie.to_owned(),
+            self.org_uuid.to_owned(),
+            self.conv_uuid.to_owned(),
+        ) else {
+            self.clean_chat();
+            return;
+        };
+        let session = WebSession {
+            cookie,
+            org_uuid,
+            conv_uuid,
+            prefix_hash,
+            prefix_len,
+            reply_hash,
+        };
+        conversations.entry(key).and_upsert_with(|old| {
+            // another request of the session kept a different conversation meanwhile
+            if let Some(old) = old.map(Entry::into_value)
+                && old.conv_uuid != session.conv_uuid
+            {
+                self.discard(old);
+            }
+            session
+        });
+    }
+
+    /// Fetches the uuid of the latest message in the current conversation
+    pub(super) async fn leaf_message_uuid(&self) -> Result<String, ClewdrError> {
+        let (Some(org_uuid), Some(conv_uuid)) = (&self.org_uuid, &self.conv_uuid) else {
+            return Err(ClewdrError::UnexpectedNone {
+                msg: "Conversation is not set",
+            });
+        };
+        let endpoint = format!(
+            "{}/api/organizations/{}/chat_conversations/{}?tree=False&rendering_mode=messages",
+            self.endpoint, org_uuid, conv_uuid
+        );
+        let conv = self
+            .build_request(Method::GET, endpoint)
+            .send()
+            .await
+            .context(WreqSnafu {
+                msg: "Failed to fetch conversation",
+            })?
+            .check_claude()
+            .await?
+            .json::<Value>()
+            .await
+            .context(WreqSnafu {
+                msg: "Failed to parse conversation",
+            })?;
+        conv["current_leaf_message_uuid"]
+            .as_str()
+            .map(ToString::to_string)
+            .ok_or(ClewdrError::UnexpectedNone {
+                msg: "Conversation has no leaf message",
+            })
+    }
+
+    /// Cleans up the conversation of a session that can no longer be resumed
+    fn discard(&self, session: WebSession) {
+        let mut state = self.to_owned();
+        state.cookie = Some(session.cookie);
+        state.org_uuid = Some(session.org_uuid);
+        state.conv_uuid = Some(session.conv_uuid);
+        state.clean_chat();
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use futures::{StreamExt, TryStreamExt, stream};
+use http::header::CONTENT_TYPE;
+use tracing::debug;
+use url::Url;
+use wreq::{ClientBuilder, redirect::Policy};
+
+use crate::{claude_web_state::ClaudeWebState, error::ClewdrError, types::claude::ImageSource};
+
+/// Maximum size of a remote image
+const MAX_IMAGE_SIZE: usize = 10 * 1024 * 1024;
+/// Timeout for downloading a remote image
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// Number of images downloaded concurrently
+const FETCH_CONCURRENCY: usize = 4;
+/// Image types accepted by Claude.ai
+const IMAGE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+impl ClaudeWebState {
+    /// Downloads remote images in place, turning `url` sources into base64 sources
+    ///
+    /// Any image that cannot be fetched fails the whole request,
+    /// so the client learns about it instead of the image being dropped.
+    /// Only public http(s) hosts on the default ports are fetched, and redirects are not followed
+    pub async fn fetch_remote_images(
+        &self,
+        imgs: Vec<ImageSource>,
+    ) -> Result<Vec<ImageSource>, ClewdrError> {
+        if !imgs.iter().any(|img| img.type_ == "url") {
+            return Ok(imgs);
+        }
+        stream::iter(imgs)
+            .map(async |img| {
+                if img.type_ != "url" {
+                    return Ok(img);
+                }
+                self.fetch_image(img.data).await
+            })
+            .buffered(FETCH_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+
+    /// Downloads a single image, enforcing the address, size and type limits
+    async fn fetch_image(&self, url: String) -> Result<ImageSource, ClewdrError> {
+        let err = |msg: String| ClewdrError::ImageFetchError {
+            url: url.to_owned(),
+            msg,
+        };
+        let (host, addrs) = resolve_public(&url).await.map_err(err)?;
+        debug!("Fetching remote image: {}", url);
+        // pin the checked addresses so the connection cannot be rebound to another host
+        let mut builder = ClientBuilder::new()
+            .redirect(Policy::none())
+            .resolve_to_addrs(&host, &addrs);
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(proxy.to_owned());
+        }
+        let client = builder.build().map_err(|e| err(e.to_string()))?;
+        fetch_bytes(&client, &url).await.map_err(err)
+    }
+}
+
+/// Checks that a URL points to a public host and resolves its addresses
+async fn resolve_public(url: &str) -> Result<(String, Vec<SocketAddr>), String> {
+    let parsed = Url::parse(url).map_err(|e| e.to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("unsupported scheme {}", parsed.scheme()));
+    }
+    if parsed.port().is_some_and(|p| p != 80 && p != 443) {
+        return Err("only the default ports are allowed".to_string());
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "missing host".to_string())?
+        .trim_matches(['[', ']'])
+        .to_string();
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let target = (host.to_owned(), port);
+    let addrs: Vec<SocketAddr> = tokio::task::spawn_blocking(move || target.to_socket_addrs())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?
+        .collect();
+    if addrs.is_empty() {
+        return Err("host has no addresses".to_string());
+    }
+    if let Some(addr) = addrs.iter().find(|a| !is_public(a.ip())) {
+        return Err(format!("host resolves to non-public address {}", addr.ip()));
+    }
+    Ok((host, addrs))
+}
+
+/// Whether an address is reachable on the public internet
+///
+/// Rejects loopback, private, link-local (including cloud metadata endpoints),
+/// shared, documentation, multicast and unspecified addresses
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || v6.is_unique_local()
+                || v6.is_unicast_link_local()
+                || v6.segments()[0] == 0x2001 && v6.segments()[1] == 0xdb8)
+        }
+    }
+}
+
+/// Downloads the body of an image, enforcing the size and type limits
+async fn fetch_bytes(client: &wreq::Client, url: &str) -> Result<ImageSource, String> {
+    let mut res = client
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("upstream returned {}", res.status()));
+    }
+    if res
+        .content_length()
+        .is_some_and(|l| l > MAX_IMAGE_SIZE as u64)
+    {
+        return Err(format!("image is larger than {MAX_IMAGE_SIZE} bytes"));
+    }
+    let header_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_lowercase());
+    let mut bytes = Vec::new();
+    while let Some(chunk) = res.chunk().await.map_err(|e| e.to_string())? {
+        if bytes.len() + chunk.len() > MAX_IMAGE_SIZE {
+            return Err(format!("image is larger than {MAX_IMAGE_SIZE} bytes"));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    let media_type = header_type
+        .filter(|t| IMAGE_TYPES.contains(&t.as_str()))
+        .or_else(|| sniff_image_type(&bytes).map(ToString::to_string))
+        .ok_or_else(|| "unsupported image type".to_string())?;
+    Ok(ImageSource {
+        type_: "base64".to_string(),
+        media_type,
+        data: BASE64_STANDARD.encode(bytes),
+    })
+}
+
+/// Detects the image type from the leading bytes
+fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [
+            b'R',
+            b'I',
+            b'F',
+            b'F',
+            _,
+            _,
+            _,
+            _,
+            b'W',
+            b'E',
+            b'B',
+            b'P',
+            ..,
+        ] => Some("image/webp"),
+        _ => None,
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use futures::{Stream, TryStreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, info, warn};
+
+use crate::{
+    claude_web_state::ClaudeWebState,
+    config::{CookieStatus, Reason},
+    services::cookie_actor::CookieActorHandle,
+};
+
+/// Message limit reported by claude.ai in the completion stream
+#[derive(Debug, Deserialize)]
+pub struct MessageLimit {
+    /// `within_limit`, `approaching_limit` or `exceeded_limit`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Unix timestamp in seconds at which the limit resets
+    #[serde(rename = "resetsAt", default)]
+    pub resets_at: Option<i64>,
+    /// Messages remaining in the current window
+    #[serde(default)]
+    pub remaining: Option<i64>,
+    /// Limits of the individual windows, keyed by window type
+    #[serde(default)]
+    pub windows: HashMap<String, LimitWindow>,
+}
+
+/// A single rate limit window, such as the 5 hour or 7 day window
+#[derive(Debug, Deserialize)]
+pub struct LimitWindow {
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub resets_at: Option<i64>,
+    #[serde(default)]
+    pub utilization: Option<f64>,
+}
+
+impl MessageLimit {
+    /// Returns the reset time if the limit is used up, so the next request would fail
+    pub fn exhausted_until(&self) -> Option<i64> {
+        let window = self
+            .windows
+            .values()
+            .filter(|w| {
+                w.status.as_deref() == Some("exceeded_limit")
+                    || w.utilization.is_some_and(|u| u >= 1.0)
+            })
+            .filter_map(|w| w.resets_at)
+            .max();
+        if window.is_some() {
+            return window;
+        }
+        if self.kind != "exceeded_limit" && self.remaining.is_none_or(|r| r > 0) {
+            return None;
+        }
+        // fall back to an hour when claude.ai does not tell the reset time
+        Some(
+            self.resets_at
+                .unwrap_or_else(|| chrono::Utc::now().timestamp() + 3600),
+        )
+    }
+}
+
+/// Watches a claude.ai event stream for `message_limit` events
+///
+/// When the limit is used up, the cookie is moved to the exhausted pool
+/// with the exact reset time, before the next request runs into a 429
+struct LimitWatcher {
+    handle: CookieActorHandle,
+    cookie: Option<CookieStatus>,
+    /// Incomplete line carried over from the previous chunk
+    line: Vec<u8>,
+}
+
+impl LimitWatcher {
+    fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if b != b'\n' {
+                self.line.push(b);
+                continue;
+            }
+            let line = std::mem::take(&mut self.line);
+            if let Some(data) = line.strip_prefix(b"data:") {
+                self.observe(data);
+            }
+        }
+    }
+
+    fn observe(&mut self, data: &[u8]) {
+        let Ok(data) = std::str::from_utf8(data) else {
+            return;
+        };
+        if !data.contains("message_limit") {
+            return;
+        }
+        let Ok(value) = serde_json::from_str::<Value>(data.trim()) else {
+            return;
+        };
+        if value["type"] != "message_limit" {
+            return;
+        }
+        let limit = match serde_json::from_value::<MessageLimit>(value["message_limit"].to_owned())
+        {
+            Ok(limit) => limit,
+            Err(e) => {
+                warn!("Failed to parse message limit: {}", e);
+                return;
+            }
+        };
+        debug!("Message limit: {:?}", limit);
+        let Some(reset) = limit.exhausted_until() else {
+            return;
+        };
+        // report a cookie only once per stream
+        let Some(cookie) = self.cookie.take() else {
+            return;
+        };
+        info!("Message limit reached, cookie exhausted until {}", reset);
+        let handle = self.handle.to_owned();
+        tokio::spawn(async move {
+            if let Err(e) = handle
+                .return_cookie(cookie, Some(Reason::TooManyRequest(reset)))
+                .await
+            {
+                warn!("Failed to return exhausted cookie: {}", e);
+            }
+        });
+    }
+}
+
+impl ClaudeWebState {
+    /// Passes a claude.ai event stream through, feeding its message limits to the cookie actor
+    pub(crate) fn watch_limits<S, E>(&self, stream: S) -> impl Stream<Item = S::Item> + use<S, E>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+    {
+        let mut watcher = LimitWatcher {
+            handle: self.cookie_actor_handle.to_owned(),
+            cookie: self.cookie.to_owned(),
+            line: Vec::new(),
+        };
+        stream.inspect_ok(move |chunk| watcher.feed(chunk))
+    }
+}
@@ -1,6 +1,8 @@
 use std::sync::LazyLock;
 
 use axum::http::HeaderValue;
+use moka::sync::Cache;
+use serde_json::{Value, json};
 use snafu::ResultExt;
 use tracing::{debug, error};
 use url::Url;
@@ -12,15 +14,32 @@ use wreq_util::Emulation;
 
 use crate::{
     config::{CLAUDE_ENDPOINT, CLEWDR_CONFIG, CookieStatus, Reason},
-    error::{ClewdrError, WreqSnafu},
+    error::{CheckClaudeErr, ClewdrError, WreqSnafu},
     middleware::claude::ClaudeApiFormat,
-    services::cookie_actor::CookieActorHandle,
+    services::{
+        cookie_actor::CookieActorHandle,
+        janitor::{self, CleanupJob},
+    },
     types::claude::Usage,
 };
 
 pub mod bootstrap;
 pub mod chat;
+mod conversation;
+mod fetch;
+mod limit;
+mod search;
+mod stream;
+mod tools;
 mod transform;
+mod usage;
+pub use conversation::{WebSession, session_key};
+pub use limit::MessageLimit;
+pub use search::SearchStream;
+pub use stream::transform_web_stream;
+pub use tools::parse_tool_calls;
+/// Name prefix of the conversations created by ClewdR
+pub const CONVERSATION_PREFIX: &str = "ClewdR-";
 /// Placeholder
 pub static SUPER_CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
 
@@ -40,12 +59,19 @@ pub struct ClaudeWebState {
     pub client: Client,
     pub key: Option<(u64, usize)>,
     pub usage: Usage,
+    /// Key of the client session when conversation reuse is enabled
+    pub session_key: Option<u64>,
+    /// Conversations of the client sessions, shared by the request handlers
+    pub conversations: Option<Cache<u64, WebSession>>,
+    /// Whether client tools are emulated through the prompt
+    pub emulate_tools: bool,
 }
 
 impl ClaudeWebState {
     /// Create a new AppState instance
     pub fn new(cookie_actor_handle: CookieActorHandle) -> Self {
         ClaudeWebState {
+            conversations: None,
             cookie_actor_handle,
             cookie: None,
             org_uuid: None,
@@ -59,9 +85,19 @@ impl ClaudeWebState {
             client: SUPER_CLIENT.to_owned(),
             key: None,
             usage: Usage::default(),
+            session_key: None,
+            emulate_tools: false,
         }
     }
 
+    /// Enables conversation reuse, with a session cache shared by all clones of the state
+    pub fn with_conversations(mut self) -> Self {
+        self.conversations = Some(conversation::session_cache(
+            self.cookie_actor_handle.to_owned(),
+        ));
+        self
+    }
+
     pub fn with_claude_format(mut self) -> Self {
         self.api_format = ClaudeApiFormat::Claude;
         self
@@ -102,8 +138,14 @@ impl ClaudeWebState {
     /// Requests a new cookie from the cookie manager
     /// Updates the internal state with the new cookie and proxy configuration
     pub async fn request_cookie(&mut self) -> Result<CookieStatus, ClewdrError> {
-        let res = self.cookie_actor_handle.request(None).await?;
-        self.cookie = Some(res.to_owned());
+        let res = self.cookie_actor_handle.request(self.session_key).await?;
+        self.set_cookie(res.to_owned())?;
+        Ok(res)
+    }
+
+    /// Binds the state to the given cookie
+    /// Updates the internal state with the cookie and proxy configuration
+    pub fn set_cookie(&mut self, cookie: CookieStatus) -> Result<(), ClewdrError> {
         // Always pull latest proxy/endpoint before building the client
         self.proxy = CLEWDR_CONFIG.load().wreq_proxy.to_owned();
         self.endpoint = CLEWDR_CONFIG.load().endpoint();
@@ -116,8 +158,9 @@ impl ClaudeWebState {
         self.client = client.build().context(WreqSnafu {
             msg: "Failed to build client with new cookie",
         })?;
-        self.cookie_header_value = HeaderValue::from_str(res.cookie.to_string().as_str())?;
-        Ok(res)
+        self.cookie_header_value = HeaderValue::from_str(cookie.cookie.to_string().as_str())?;
+        self.cookie = Some(cookie);
+        Ok(())
     }
 
     /// Returns the current cookie to the cookie manager
@@ -134,30 +177,77 @@ impl ClaudeWebState {
         }
     }
 
-    /// Deletes or renames the current chat conversation based on configuration
-    /// If preserve_chats is true, the chat is renamed rather than deleted
-    pub async fn clean_chat(&self) -> Result<(), ClewdrError> {
+    /// Queues the current chat conversation for deletion by the janitor
+    /// If preserve_chats is true, the chat is kept
+    pub fn clean_chat(&self) {
         if CLEWDR_CONFIG.load().preserve_chats {
-            return Ok(());
+            return;
         }
-        let Some(ref org_uuid) = self.org_uuid else {
-            return Ok(());
+        let (Some(cookie), Some(org_uuid), Some(conv_uuid)) = (
+            self.cookie.to_owned(),
+            self.org_uuid.to_owned(),
+            self.conv_uuid.to_owned(),
+        ) else {
+            return;
         };
-        let Some(ref conv_uuid) = self.conv_uuid else {
-            return Ok(());
+        debug!("Queueing chat for deletion: {}", conv_uuid);
+        janitor::enqueue(CleanupJob::new(cookie, org_uuid, conv_uuid));
+    }
+
+    /// Deletes chat conversations of an organization
+    /// Several conversations are deleted with a single request
+    pub async fn delete_chats(
+        &self,
+        org_uuid: &str,
+        conv_uuids: &[String],
+    ) -> Result<(), ClewdrError> {
+        let req = if let [conv_uuid] = conv_uuids {
+            let endpoint = format!(
+                "{}/api/organizations/{}/chat_conversations/{}",
+                self.endpoint, org_uuid, conv_uuid
+            );
+            self.build_request(Method::DELETE, endpoint)
+        } else {
+            let endpoint = format!(
+                "{}/api/organizations/{}/chat_conversations/delete_many",
+                self.endpoint, org_uuid
+            );
+            self.build_request(Method::POST, endpoint)
+                .json(&json!({ "conversation_uuids": conv_uuids }))
         };
+        req.send()
+            .await
+            .context(WreqSnafu {
+                msg: "Failed to delete chat conversations",
+            })?
+            .check_claude()
+            .await?;
+        Ok(())
+    }
+
+    /// Lists a page of the chat conversations of an organization
+    pub async fn list_chats(
+        &self,
+        org_uuid: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Value>, ClewdrError> {
         let endpoint = format!(
-            "{}/api/organizations/{}/chat_conversations/{}",
-            self.endpoint, org_uuid, conv_uuid
+            "{}/api/organizations/{}/chat_conversations?limit={}&offset={}",
+            self.endpoint, org_uuid, limit, offset
         );
-        debug!("Deleting chat: {}", conv_uuid);
-        let _ = self
-            .build_request(Method::DELETE, endpoint)
+        self.build_request(Method::GET, endpoint)
             .send()
             .await
             .context(WreqSnafu {
-                msg: "Failed to delete chat conversation",
-            });
-        Ok(())
+                msg: "Failed to list chat conversations",
+            })?
+            .check_claude()
+            .await?
+            .json::<Vec<Value>>()
+            .await
+            .context(WreqSnafu {
+                msg: "Failed to parse chat conversations",
+            })
     }
 }
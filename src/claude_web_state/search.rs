@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use serde_json::{Value, json};
+
+/// A citation whose cited text is still streaming
+struct OpenCitation {
+    uuid: String,
+    citation: Value,
+    cited_text: String,
+}
+
+impl OpenCitation {
+    /// Converts the citation into an Anthropic `web_search_result_location`
+    fn into_location(self) -> Value {
+        let source = &self.citation["sources"][0];
+        let field = |name: &str| {
+            self.citation[name]
+                .as_str()
+                .or_else(|| source[name].as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        json!({
+            "type": "web_search_result_location",
+            "url": field("url"),
+            "title": field("title"),
+            "cited_text": self.cited_text,
+            "encrypted_index": "",
+        })
+    }
+}
+
+/// Rewrites the web search events of a claude.ai stream into Anthropic API events
+///
+/// The `web_search` tool use becomes a `server_tool_use` block, its result
+/// a `web_search_tool_result` block, and citation markers in text blocks
+/// become `citations_delta` events carrying the cited text
+#[derive(Default)]
+pub struct SearchStream {
+    /// Indices of search result blocks, whose claude.ai specific deltas are dropped
+    result_blocks: HashSet<u64>,
+    citations: Vec<OpenCitation>,
+}
+
+impl SearchStream {
+    pub fn process(&mut self, mut data: Value) -> Option<Value> {
+        let index = data["index"].as_u64().unwrap_or_default();
+        match data["type"].as_str().unwrap_or_default() {
+            "content_block_start" => {
+                let block = &data["content_block"];
+                if block["name"] != "web_search" {
+                    return Some(data);
+                }
+                match block["type"].as_str() {
+                    Some("tool_use") => {
+                        data["content_block"] = json!({
+                            "type": "server_tool_use",
+                            "id": block["id"],
+                            "name": "web_search",
+                            "input": block.get("input").cloned().unwrap_or_else(|| json!({})),
+                        });
+                    }
+                    Some("tool_result") => {
+                        self.result_blocks.insert(index);
+                        data["content_block"] = json!({
+                            "type": "web_search_tool_result",
+                            "tool_use_id": block["tool_use_id"],
+                            "content": search_results(&block["content"]),
+                        });
+                    }
+                    _ => {}
+                }
+                Some(data)
+            }
+            "content_block_delta" if self.result_blocks.contains(&index) => None,
+            "content_block_delta" => match data["delta"]["type"].as_str() {
+                Some("citation_start_delta") => {
+                    let citation = data["delta"]["citation"].to_owned();
+                    self.citations.push(OpenCitation {
+                        uuid: citation["uuid"].as_str().unwrap_or_default().to_string(),
+                        citation,
+                        cited_text: String::new(),
+                    });
+                    None
+                }
+                Some("citation_end_delta") => {
+                    let uuid = data["delta"]["citation_uuid"].as_str().unwrap_or_default();
+                    let pos = self.citations.iter().position(|c| c.uuid == uuid)?;
+                    let citation = self.citations.remove(pos).into_location();
+                    Some(json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": {"type": "citations_delta", "citation": citation},
+                    }))
+                }
+                Some("text_delta") => {
+                    let text = data["delta"]["text"].as_str().unwrap_or_default();
+                    for c in self.citations.iter_mut() {
+                        c.cited_text.push_str(text);
+                    }
+                    Some(data)
+                }
+                _ => Some(data),
+            },
+            _ => Some(data),
+        }
+    }
+}
+
+/// Converts claude.ai search results into Anthropic `web_search_result` blocks
+fn search_results(content: &Value) -> Value {
+    content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|r| r["url"].is_string())
+        .map(|r| {
+            json!({
+                "type": "web_search_result",
+                "url": r["url"],
+                "title": r["title"].as_str().unwrap_or_default(),
+                "encrypted_content": "",
+                "page_age": r["metadata"]["age"],
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,51 @@
+use async_stream::try_stream;
+use axum::response::{IntoResponse, Sse, sse::Event};
+use eventsource_stream::{Event as SourceEvent, EventStreamError, Eventsource};
+use futures::Stream;
+use serde_json::Value;
+
+use super::{search::SearchStream, tools::ToolStream};
+
+type EventResult<T> = Result<T, EventStreamError<wreq::Error>>;
+
+/// Converts a claude.ai event stream into an Anthropic stream
+///
+/// Web search blocks and citations are rewritten into their API form,
+/// and tool calls are parsed out of the text when tools are emulated
+pub fn transform_web_stream(
+    wreq_res: wreq::Response,
+    emulate_tools: bool,
+) -> axum::response::Response {
+    let stream = web_stream(wreq_res.bytes_stream().eventsource(), emulate_tools);
+    Sse::new(stream)
+        .keep_alive(Default::default())
+        .into_response()
+}
+
+fn web_stream(
+    stream: impl Stream<Item = EventResult<SourceEvent>>,
+    emulate_tools: bool,
+) -> impl Stream<Item = EventResult<Event>> {
+    try_stream!({
+        let mut search = SearchStream::default();
+        let mut tools = emulate_tools.then(ToolStream::default);
+        for await event in stream {
+            let event = event?;
+            let Ok(data) = serde_json::from_str::<Value>(&event.data) else {
+                yield Event::default().event(event.event).data(event.data);
+                continue;
+            };
+            let Some(data) = search.process(data) else {
+                continue;
+            };
+            let out = match tools {
+                Some(ref mut tools) => tools.process(data),
+                None => vec![data],
+            };
+            for data in out {
+                let name = data["type"].as_str().unwrap_or("message").to_string();
+                yield Event::default().event(name).json_data(data).unwrap();
+            }
+        }
+    })
+}
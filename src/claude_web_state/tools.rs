@@ -0,0 +1,345 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    mem,
+};
+
+use serde_json::{Value, json};
+
+use crate::types::claude::{ContentBlock, Tool, ToolChoice};
+
+const TOOL_CALL_OPEN: &str = "<tool_call>";
+const TOOL_CALL_CLOSE: &str = "</tool_call>";
+
+/// Renders the tool definitions and calling convention into prompt text
+///
+/// # Arguments
+/// * `tools` - Tools the client offers to the model
+/// * `choice` - How the model is required to use the tools
+///
+/// # Returns
+/// Instructions to be appended to the system prompt
+pub fn render_tools(tools: &[Tool], choice: Option<&ToolChoice>) -> String {
+    let mut w = String::from(
+        "# Tools\n\nYou can call the following tools. \
+         Each tool is given as its name, description and the JSON schema of its input:\n<tools>\n",
+    );
+    for tool in tools {
+        let def = json!({
+            "name": tool.name,
+            "description": tool.description,
+            "input_schema": tool.input_schema,
+        });
+        let _ = writeln!(w, "{def}");
+    }
+    w += "</tools>\n\n";
+    let _ = write!(
+        w,
+        "To call a tool, write a block in exactly this format, \
+         with the input as a single JSON object matching the schema:\n\
+         {TOOL_CALL_OPEN}{{\"name\": \"tool_name\", \"input\": {{}}}}{TOOL_CALL_CLOSE}\n\
+         You may call several tools in a row. After your tool calls, end your reply immediately. \
+         The results will be given to you in the next message inside <tool_result> blocks, \
+         never write them yourself."
+    );
+    match choice {
+        Some(ToolChoice::Any) => w += "\nYou must call at least one tool in your reply.",
+        Some(ToolChoice::Tool { name }) => {
+            let _ = write!(w, "\nYou must call the tool `{name}` in your reply.");
+        }
+        Some(ToolChoice::None) => w += "\nDo not call any tool in your reply.",
+        _ => {}
+    }
+    w
+}
+
+/// Renders a tool call of the transcript in the emulated markup
+pub fn render_tool_use(name: &str, input: &Value) -> String {
+    let call = json!({ "name": name, "input": input });
+    format!("{TOOL_CALL_OPEN}{call}{TOOL_CALL_CLOSE}")
+}
+
+/// Renders a tool result of the transcript
+pub fn render_tool_result(name: Option<&str>, content: &str, is_error: bool) -> String {
+    let name = name.map(|n| format!(" name=\"{n}\"")).unwrap_or_default();
+    let error = if is_error { " is_error=\"true\"" } else { "" };
+    format!("<tool_result{name}{error}>\n{content}\n</tool_result>")
+}
+
+/// A piece of model output split around tool call markup
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    ToolUse(ContentBlock),
+}
+
+/// Incrementally splits model output into text and tool calls
+#[derive(Default)]
+struct ToolCallParser {
+    buf: String,
+    in_call: bool,
+}
+
+impl ToolCallParser {
+    /// Feeds a chunk of output, returning the segments that are complete
+    fn feed(&mut self, text: &str) -> Vec<Segment> {
+        self.buf.push_str(text);
+        let mut out = vec![];
+        loop {
+            if self.in_call {
+                let Some(end) = self.buf.find(TOOL_CALL_CLOSE) else {
+                    break;
+                };
+                let body = self.buf[..end].to_owned();
+                self.buf.drain(..end + TOOL_CALL_CLOSE.len());
+                self.in_call = false;
+                out.push(parse_call(&body).map_or_else(
+                    || Segment::Text(format!("{TOOL_CALL_OPEN}{body}{TOOL_CALL_CLOSE}")),
+                    Segment::ToolUse,
+                ));
+            } else if let Some(start) = self.buf.find(TOOL_CALL_OPEN) {
+                let text = self.buf.drain(..start).collect::<String>();
+                push_text(&mut out, text);
+                self.buf.drain(..TOOL_CALL_OPEN.len());
+                self.in_call = true;
+            } else {
+                // hold back a trailing partial opening tag
+                let keep = self
+                    .buf
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .find(|&i| TOOL_CALL_OPEN.starts_with(&self.buf[i..]))
+                    .unwrap_or(self.buf.len());
+                let text = self.buf.drain(..keep).collect::<String>();
+                push_text(&mut out, text);
+                break;
+            }
+        }
+        out
+    }
+
+    /// Flushes whatever is buffered as text
+    fn finish(&mut self) -> Vec<Segment> {
+        let mut rest = mem::take(&mut self.buf);
+        if mem::take(&mut self.in_call) {
+            rest.insert_str(0, TOOL_CALL_OPEN);
+        }
+        let mut out = vec![];
+        push_text(&mut out, rest);
+        out
+    }
+}
+
+fn push_text(out: &mut Vec<Segment>, text: String) {
+    if !text.is_empty() {
+        out.push(Segment::Text(text));
+    }
+}
+
+/// Parses the JSON body of a tool call into a `tool_use` block
+fn parse_call(body: &str) -> Option<ContentBlock> {
+    let call = serde_json::from_str::<Value>(body.trim()).ok()?;
+    let name = call["name"].as_str()?.to_string();
+    let input = match call.get("input").or_else(|| call.get("arguments")) {
+        Some(Value::Object(o)) => Value::Object(o.to_owned()),
+        _ => json!({}),
+    };
+    Some(ContentBlock::ToolUse {
+        id: format!("toolu_{}", uuid::Uuid::new_v4().simple()),
+        name,
+        input,
+    })
+}
+
+/// Splits a complete model output into text and `tool_use` blocks
+///
+/// Text following the first tool call is dropped, as it can only be a made up tool result
+pub fn parse_tool_calls(text: &str) -> Vec<ContentBlock> {
+    let mut parser = ToolCallParser::default();
+    let mut segments = parser.feed(text);
+    segments.extend(parser.finish());
+    let mut blocks = vec![];
+    let mut used_tool = false;
+    for segment in segments {
+        match segment {
+            Segment::Text(text) if !used_tool => match blocks.last_mut() {
+                Some(ContentBlock::Text { text: last, .. }) => last.push_str(&text),
+                _ => blocks.push(ContentBlock::text(text)),
+            },
+            Segment::Text(_) => {}
+            Segment::ToolUse(block) => {
+                used_tool = true;
+                blocks.push(block);
+            }
+        }
+    }
+    blocks.retain(|b| !matches!(b, ContentBlock::Text { text, .. } if text.trim().is_empty()));
+    blocks
+}
+
+/// Rewrites the text blocks of a claude.ai stream into text and `tool_use` blocks
+///
+/// Content block indices are renumbered, as one upstream text block may be split
+/// into several blocks
+#[derive(Default)]
+pub struct ToolStream {
+    parser: ToolCallParser,
+    /// Upstream indices of text blocks
+    text_blocks: HashSet<u64>,
+    /// Upstream to downstream indices of other blocks
+    index_map: HashMap<u64, u64>,
+    next_index: u64,
+    open_text: Option<u64>,
+    used_tool: bool,
+}
+
+impl ToolStream {
+    pub fn process(&mut self, mut data: Value) -> Vec<Value> {
+        let index = data["index"].as_u64().unwrap_or_default();
+        let is_text = self.text_blocks.contains(&index);
+        match data["type"].as_str().unwrap_or_default() {
+            "content_block_start" if data["content_block"]["type"] == "text" => {
+                self.text_blocks.insert(index);
+                vec![]
+            }
+            "content_block_start" => {
+                let mut out = self.close_text().into_iter().collect::<Vec<_>>();
+                self.index_map.insert(index, self.next_index);
+                data["index"] = self.next_index.into();
+                self.next_index += 1;
+                out.push(data);
+                out
+            }
+            "content_block_delta" if is_text && data["delta"]["type"] == "text_delta" => {
+                let text = data["delta"]["text"].as_str().unwrap_or_default();
+                let segments = self.parser.feed(text);
+                self.segments(segments)
+            }
+            "content_block_delta" if is_text => {
+                // other deltas, such as citations, belong to the open text block
+                match self.open_text {
+                    Some(i) if !self.used_tool => {
+                        data["index"] = i.into();
+                        vec![data]
+                    }
+                    _ => vec![],
+                }
+            }
+            "content_block_stop" if is_text => {
+                let segments = self.parser.finish();
+                let mut out = self.segments(segments);
+                out.extend(self.close_text());
+                out
+            }
+            "content_block_delta" | "content_block_stop" => {
+                if let Some(i) = self.index_map.get(&index) {
+                    data["index"] = (*i).into();
+                }
+                vec![data]
+            }
+            "message_delta" => {
+                let segments = self.parser.finish();
+                let mut out = self.segments(segments);
+                out.extend(self.close_text());
+                if self.used_tool {
+                    data["delta"]["stop_reason"] = "tool_use".into();
+                    data["delta"]["stop_sequence"] = Value::Null;
+                }
+                out.push(data);
+                out
+            }
+            _ => vec![data],
+        }
+    }
+
+    fn segments(&mut self, segments: Vec<Segment>) -> Vec<Value> {
+        let mut out = vec![];
+        for segment in segments {
+            match segment {
+                // text after a tool call can only be a made up tool result
+                Segment::Text(_) if self.used_tool => {}
+                Segment::Text(text) => {
+                    let index = match self.open_text {
+                        Some(i) => i,
+                        None => {
+                            let i = self.next_index;
+                            self.next_index += 1;
+                            self.open_text = Some(i);
+                            out.push(json!({
+                                "type": "content_block_start",
+                                "index": i,
+                                "content_block": {"type": "text", "text": ""},
+                            }));
+                            i
+                        }
+                    };
+                    out.push(json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": {"type": "text_delta", "text": text},
+                    }));
+                }
+                Segment::ToolUse(ContentBlock::ToolUse { id, name, input }) => {
+                    out.extend(self.close_text());
+                    self.used_tool = true;
+                    let index = self.next_index;
+                    self.next_index += 1;
+                    out.push(json!({
+                        "type": "content_block_start",
+                        "index": index,
+                        "content_block": {"type": "tool_use", "id": id, "name": name, "input": {}},
+                    }));
+                    out.push(json!({
+                        "type": "content_block_delta",
+                        "index": index,
+                        "delta": {"type": "input_json_delta", "partial_json": input.to_string()},
+                    }));
+                    out.push(json!({"type": "content_block_stop", "index": index}));
+                }
+                Segment::ToolUse(_) => {}
+            }
+        }
+        out
+    }
+
+    fn close_text(&mut self) -> Option<Value> {
+        let index = self.open_text.take()?;
+        Some(json!({"type": "content_block_stop", "index": index}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser_splits_across_chunks() {
+        let mut parser = ToolCallParser::default();
+        let mut segments = vec![];
+        for chunk in [
+            "Let me check.<tool",
+            "_call>{\"name\": \"get_weather\", ",
+            "\"input\": {\"city\": \"Paris\"}}</tool_",
+            "call> trailing",
+        ] {
+            segments.extend(parser.feed(chunk));
+        }
+        segments.extend(parser.finish());
+        assert_eq!(segments[0], Segment::Text("Let me check.".to_string()));
+        let Segment::ToolUse(ContentBlock::ToolUse { name, input, .. }) = &segments[1] else {
+            panic!("Expected a tool call, got {:?}", segments[1]);
+        };
+        assert_eq!(name, "get_weather");
+        assert_eq!(input, &json!({"city": "Paris"}));
+        assert_eq!(segments[2], Segment::Text(" trailing".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_call_is_kept_as_text() {
+        let blocks = parse_tool_calls("a <tool_call>not json</tool_call> b");
+        assert_eq!(
+            blocks,
+            vec![ContentBlock::text("a <tool_call>not json</tool_call> b")]
+        );
+    }
+}
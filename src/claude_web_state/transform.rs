@@ -1,17 +1,22 @@
-use std::{fmt::Write, mem};
+use std::{collections::HashMap, fmt::Write, mem};
 
 use base64::{Engine, prelude::BASE64_STANDARD};
-use futures::{StreamExt, stream};
+use futures::{StreamExt, TryStreamExt, stream};
 use itertools::Itertools;
 use serde_json::Value;
 use tracing::warn;
 use wreq::multipart::{Form, Part};
 
+use super::tools::{render_tool_result, render_tool_use, render_tools};
 use crate::{
     claude_web_state::ClaudeWebState,
     config::CLEWDR_CONFIG,
+    error::ClewdrError,
     types::{
-        claude::{ContentBlock, CreateMessageParams, ImageSource, Message, MessageContent, Role},
+        claude::{
+            ContentBlock, CreateMessageParams, DocumentSource, ImageSource, Message,
+            MessageContent, Role,
+        },
         claude_web::request::*,
     },
     utils::{TIME_ZONE, print_out_text},
@@ -21,51 +26,90 @@ impl ClaudeWebState {
     pub fn transform_request(&self, mut value: CreateMessageParams) -> Option<WebRequestBody> {
         let system = value.system.take();
         let msgs = mem::take(&mut value.messages);
-        let system = merge_system(system.unwrap_or_default());
+        let mut system = merge_system(system.unwrap_or_default());
+        if self.emulate_tools
+            && let Some(ref tools) = value.tools
+        {
+            let tools = render_tools(tools, value.tool_choice.as_ref());
+            system = format!("{}\n\n{}", system.trim(), tools);
+        }
         let merged = merge_messages(msgs, system)?;
+        Some(self.build_body(
+            value,
+            vec![Attachment::new(merged.paste)],
+            merged.prompt,
+            merged.images,
+            merged.documents,
+        ))
+    }
+
+    /// Builds the request body for a new user turn in a resumed conversation
+    /// Only the turn itself is sent, as the prompt instead of an attachment
+    pub fn transform_turn(
+        &self,
+        value: CreateMessageParams,
+        turn: Vec<Message>,
+    ) -> Option<WebRequestBody> {
+        let merged = merge_messages(turn, String::new())?;
+        Some(self.build_body(value, vec![], merged.paste, merged.images, merged.documents))
+    }
 
+    fn build_body(
+        &self,
+        value: CreateMessageParams,
+        attachments: Vec<Attachment>,
+        prompt: String,
+        images: Vec<ImageSource>,
+        documents: Vec<WebDocument>,
+    ) -> WebRequestBody {
         let mut tools = vec![];
-        if CLEWDR_CONFIG.load().web_search {
+        let web_search = CLEWDR_CONFIG.load().web_search;
+        if web_search {
             tools.push(Tool::web_search());
         }
-        Some(WebRequestBody {
+        WebRequestBody {
             max_tokens_to_sample: value.max_tokens,
-            attachments: vec![Attachment::new(merged.paste)],
+            attachments,
             files: vec![],
             model: if self.is_pro() {
                 Some(value.model)
             } else {
                 None
             },
-            rendering_mode: if value.stream.unwrap_or_default() {
+            // search results and citations only come with the messages rendering
+            rendering_mode: if value.stream.unwrap_or_default() || web_search {
                 "messages".to_string()
             } else {
                 "raw".to_string()
             },
-            prompt: merged.prompt,
+            prompt,
             timezone: TIME_ZONE.to_string(),
-            images: merged.images,
+            images,
+            documents,
             tools,
-        })
+            parent_message_uuid: None,
+        }
     }
 
     /// Upload images to the Claude.ai
-    pub async fn upload_images(&self, imgs: Vec<ImageSource>) -> Vec<String> {
+    ///
+    /// An image that cannot be uploaded fails the whole request
+    pub async fn upload_images(&self, imgs: Vec<ImageSource>) -> Result<Vec<String>, ClewdrError> {
         // upload images
         stream::iter(imgs)
-            .filter_map(async |img| {
+            .then(async |img| {
+                let err = |msg: &str| ClewdrError::ImageFetchError {
+                    url: img.media_type.to_owned(),
+                    msg: msg.to_string(),
+                };
                 // check if the image is base64
                 if img.type_ != "base64" {
-                    warn!("Image type is not base64");
-                    return None;
+                    return Err(err("image source is not base64"));
                 }
                 // decode the image
-                let bytes = BASE64_STANDARD
-                    .decode(img.data)
-                    .inspect_err(|e| {
-                        warn!("Failed to decode image: {}", e);
-                    })
-                    .ok()?;
+                let Ok(bytes) = BASE64_STANDARD.decode(&img.data) else {
+                    return Err(err("invalid base64 data"));
+                };
                 // choose the file name based on the media type
                 let file_name = match img.media_type.to_lowercase().as_str() {
                     "image/png" => "image.png",
@@ -76,46 +120,192 @@ impl ClaudeWebState {
                     "application/pdf" => "document.pdf",
                     _ => "file",
                 };
-                // create the part and form
-                let part = Part::bytes(bytes).file_name(file_name);
-                let form = Form::new().part("file", part);
-                let endpoint = format!("{}/api/{}/upload", self.endpoint, self.org_uuid.as_ref()?);
-                // send the request into future
-                let res = self
-                    .build_request(http::Method::POST, endpoint)
-                    .multipart(form)
-                    .send()
+                self.upload_file(bytes, file_name.to_string(), &img.media_type)
                     .await
-                    .inspect_err(|e| {
-                        warn!("Failed to upload image: {}", e);
-                    })
-                    .ok()?;
-                #[derive(serde::Deserialize)]
-                struct UploadResponse {
-                    file_uuid: String,
+                    .ok_or_else(|| err("upload to Claude.ai failed"))
+            })
+            .try_collect()
+            .await
+    }
+
+    /// Upload documents to the Claude.ai
+    ///
+    /// PDFs are uploaded as files, text documents are attached with their content,
+    /// and other formats are converted to text by Claude.ai first.
+    /// A document that cannot be attached fails the whole request
+    ///
+    /// # Returns
+    /// * File uuids and attachments to add to the request body
+    pub async fn upload_documents(
+        &self,
+        docs: Vec<WebDocument>,
+    ) -> Result<(Vec<String>, Vec<Attachment>), ClewdrError> {
+        let err = |name: String, msg: &str| ClewdrError::DocumentError {
+            name,
+            msg: msg.to_string(),
+        };
+        let mut files = vec![];
+        let mut attachments = vec![];
+        for (i, doc) in docs.into_iter().enumerate() {
+            let (media_type, data) = match doc.source {
+                DocumentSource::Text { media_type, data } => {
+                    let file_name = document_name(doc.title, &media_type, i);
+                    attachments.push(Attachment::document(file_name, media_type, data));
+                    continue;
                 }
-                // get the response json
-                let json = res
-                    .json::<UploadResponse>()
-                    .await
-                    .inspect_err(|e| {
-                        warn!("Failed to parse image response: {}", e);
-                    })
-                    .ok()?;
-                // extract the file_uuid
-                Some(json.file_uuid)
+                DocumentSource::Content { content } => {
+                    let text = content
+                        .into_iter()
+                        .filter_map(|b| match b {
+                            ContentBlock::Text { text, .. } => Some(text),
+                            _ => None,
+                        })
+                        .join("\n");
+                    let media_type = "text/plain".to_string();
+                    let file_name = document_name(doc.title, &media_type, i);
+                    attachments.push(Attachment::document(file_name, media_type, text));
+                    continue;
+                }
+                DocumentSource::Url { url } => {
+                    return Err(err(url, "remote documents are not supported"));
+                }
+                DocumentSource::Base64 { media_type, data } => (media_type, data),
+            };
+            let media_type = media_type.to_lowercase();
+            let file_name = document_name(doc.title, &media_type, i);
+            let Ok(bytes) = BASE64_STANDARD.decode(data) else {
+                return Err(err(file_name, "invalid base64 data"));
+            };
+            if media_type == "application/pdf" {
+                let name = file_name.to_owned();
+                let file = self.upload_file(bytes, file_name, &media_type).await;
+                files.push(file.ok_or_else(|| err(name, "upload to Claude.ai failed"))?);
+            } else if is_text_media(&media_type) {
+                let text = String::from_utf8_lossy(&bytes).into_owned();
+                attachments.push(Attachment::document(file_name, media_type, text));
+            } else {
+                let name = file_name.to_owned();
+                let attachment = self.convert_document(bytes, file_name, &media_type).await;
+                attachments
+                    .push(attachment.ok_or_else(|| err(name, "conversion by Claude.ai failed"))?);
+            }
+        }
+        Ok((files, attachments))
+    }
+
+    /// Uploads a file to Claude.ai, returning its file uuid
+    async fn upload_file(
+        &self,
+        bytes: Vec<u8>,
+        file_name: String,
+        media_type: &str,
+    ) -> Option<String> {
+        // create the part and form
+        let part = Part::bytes(bytes).file_name(file_name);
+        let part = part.mime_str(media_type).ok()?;
+        let form = Form::new().part("file", part);
+        let endpoint = format!("{}/api/{}/upload", self.endpoint, self.org_uuid.as_ref()?);
+        // send the request into future
+        let res = self
+            .build_request(http::Method::POST, endpoint)
+            .multipart(form)
+            .send()
+            .await
+            .inspect_err(|e| {
+                warn!("Failed to upload file: {}", e);
             })
-            .collect::<Vec<_>>()
+            .ok()?;
+        #[derive(serde::Deserialize)]
+        struct UploadResponse {
+            file_uuid: String,
+        }
+        // get the response json
+        let json = res
+            .json::<UploadResponse>()
             .await
+            .inspect_err(|e| {
+                warn!("Failed to parse upload response: {}", e);
+            })
+            .ok()?;
+        // extract the file_uuid
+        Some(json.file_uuid)
+    }
+
+    /// Converts a document to text through Claude.ai, returning it as an attachment
+    async fn convert_document(
+        &self,
+        bytes: Vec<u8>,
+        file_name: String,
+        media_type: &str,
+    ) -> Option<Attachment> {
+        let part = Part::bytes(bytes).file_name(file_name);
+        let part = part.mime_str(media_type).ok()?;
+        let form = Form::new().part("file", part);
+        let endpoint = format!(
+            "{}/api/organizations/{}/convert_document",
+            self.endpoint,
+            self.org_uuid.as_ref()?
+        );
+        let res = self
+            .build_request(http::Method::POST, endpoint)
+            .multipart(form)
+            .send()
+            .await
+            .inspect_err(|e| {
+                warn!("Failed to convert document: {}", e);
+            })
+            .ok()?;
+        res.json::<Attachment>()
+            .await
+            .inspect_err(|e| {
+                warn!("Failed to parse converted document: {}", e);
+            })
+            .ok()
+    }
+}
+
+/// Checks whether a media type holds plain text that can be attached directly
+fn is_text_media(media_type: &str) -> bool {
+    media_type.starts_with("text/")
+        || matches!(
+            media_type,
+            "application/json"
+                | "application/xml"
+                | "application/x-yaml"
+                | "application/javascript"
+        )
+}
+
+/// Picks a file name for a document from its title and media type
+fn document_name(title: Option<String>, media_type: &str, index: usize) -> String {
+    let ext = match media_type {
+        "application/pdf" => "pdf",
+        "text/markdown" => "md",
+        "text/html" => "html",
+        "text/csv" => "csv",
+        "application/json" => "json",
+        "application/xml" | "text/xml" => "xml",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => "docx",
+        _ if media_type.starts_with("text/") => "txt",
+        _ => "bin",
+    };
+    let title = title
+        .map(|t| t.replace(['/', '\\'], "_"))
+        .filter(|t| !t.trim().is_empty());
+    match title {
+        Some(t) if t.contains('.') => t,
+        Some(t) => format!("{t}.{ext}"),
+        None => format!("document_{}.{ext}", index + 1),
     }
 }
 
-/// Merged messages and images
+/// Merged messages, images and documents
 #[derive(Default, Debug)]
 struct Merged {
     pub paste: String,
     pub prompt: String,
     pub images: Vec<ImageSource>,
+    pub documents: Vec<WebDocument>,
 }
 
 /// Merges multiple messages into a single text prompt, handling system instructions
@@ -150,6 +340,9 @@ fn merge_messages(msgs: Vec<Message>, system: String) -> Option<Merged> {
     let mut w = String::with_capacity(size);
 
     let mut imgs: Vec<ImageSource> = vec![];
+    let mut docs: Vec<WebDocument> = vec![];
+    // tool names by call id, to label the results
+    let mut tool_names: HashMap<String, String> = HashMap::new();
 
     let chunks = msgs
         .into_iter()
@@ -159,7 +352,7 @@ fn merge_messages(msgs: Vec<Message>, system: String) -> Option<Merged> {
                 let blocks = content
                     .into_iter()
                     .filter_map(|b| match b {
-                        ContentBlock::Text { text } => Some(text.trim().to_string()),
+                        ContentBlock::Text { text, .. } => Some(text.trim().to_string()),
                         ContentBlock::Image { source } => {
                             // push image to the list
                             imgs.push(source);
@@ -172,7 +365,31 @@ fn merge_messages(msgs: Vec<Message>, system: String) -> Option<Merged> {
                             }
                             None
                         }
-                        _ => None,
+                        ContentBlock::Document { source, title, .. } => {
+                            // push document to the list
+                            docs.push(WebDocument { title, source });
+                            None
+                        }
+                        ContentBlock::ToolUse { id, name, input } => {
+                            let call = render_tool_use(&name, &input);
+                            tool_names.insert(id, name);
+                            Some(call)
+                        }
+                        ContentBlock::ToolResult {
+                            tool_use_id,
+                            content,
+                            is_error,
+                        } => Some(render_tool_result(
+                            tool_names.get(&tool_use_id).map(String::as_str),
+                            content.text().trim(),
+                            is_error.unwrap_or_default(),
+                        )),
+                        // earlier searches are reflected in the replies citing them,
+                        // and earlier thinking is not shown to the model again
+                        ContentBlock::ServerToolUse { .. }
+                        | ContentBlock::WebSearchToolResult { .. }
+                        | ContentBlock::Thinking { .. }
+                        | ContentBlock::RedactedThinking { .. } => None,
                     })
                     .collect::<Vec<_>>()
                     .join("\n");
@@ -226,6 +443,7 @@ fn merge_messages(msgs: Vec<Message>, system: String) -> Option<Merged> {
         paste: w,
         prompt: p,
         images: imgs,
+        documents: docs,
     })
 }
 
@@ -251,6 +469,14 @@ fn merge_system(sys: Value) -> String {
 }
 
 fn extract_image_from_url(url: &str) -> Option<ImageSource> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        // remote image, fetched before upload
+        return Some(ImageSource {
+            type_: "url".to_string(),
+            media_type: String::new(),
+            data: url.to_string(),
+        });
+    }
     if !url.starts_with("data:") {
         return None; // only support data URI
     }
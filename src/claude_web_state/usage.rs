@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use snafu::ResultExt;
+use wreq::Method;
+
+use crate::{
+    claude_web_state::ClaudeWebState,
+    config::{AccountUsage, UsageWindow},
+    error::{CheckClaudeErr, ClewdrError, WreqSnafu},
+};
+
+impl ClaudeWebState {
+    /// Fetches the usage of the rate limit windows of an organization
+    pub async fn fetch_usage(&self, org_uuid: &str) -> Result<AccountUsage, ClewdrError> {
+        let endpoint = format!("{}/api/organizations/{}/usage", self.endpoint, org_uuid);
+        let res = self
+            .build_request(Method::GET, endpoint)
+            .send()
+            .await
+            .context(WreqSnafu {
+                msg: "Failed to fetch account usage",
+            })?
+            .check_claude()
+            .await?
+            .json::<Value>()
+            .await
+            .context(WreqSnafu {
+                msg: "Failed to parse account usage",
+            })?;
+        Ok(parse_usage(&res))
+    }
+}
+
+/// Collects the windows of a usage response, such as `five_hour` and `seven_day`
+fn parse_usage(res: &Value) -> AccountUsage {
+    let windows = res
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, w)| {
+            let utilization = w["utilization"].as_f64()?;
+            let resets_at = w["resets_at"]
+                .as_str()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.timestamp());
+            Some((
+                name.to_owned(),
+                UsageWindow {
+                    utilization,
+                    resets_at,
+                },
+            ))
+        })
+        .collect();
+    AccountUsage {
+        windows,
+        updated_at: Utc::now().timestamp(),
+    }
+}
@@ -1,58 +1,93 @@
-use std::sync::LazyLock;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, LazyLock},
+};
 
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
-use http::header::{ACCEPT, CONTENT_TYPE};
+use futures::lock::Mutex;
+use http::{
+    HeaderMap, StatusCode,
+    header::{ACCEPT, CONTENT_TYPE},
+};
+use moka::sync::Cache;
 use serde_json::{Value, json};
 use sha2::{Digest, Sha256};
 use snafu::ResultExt;
-use tracing::warn;
+use tracing::{info, warn};
 use uuid::Uuid;
 use wreq::{Client, ClientBuilder, Method};
 
 use crate::{
-    config::CLEWDR_CONFIG,
+    config::{AccountUsage, CLEWDR_CONFIG, CODEX_OAUTH_ISSUER, CodexAccount, UsageWindow},
     error::{ClewdrError, WreqSnafu},
-    types::claude::{ContentBlock, Message, MessageContent, Role},
+    services::codex_actor::CodexActorHandle,
+    types::{
+        claude::{ContentBlock, Message, MessageContent, Role},
+        oai::tool_call_arguments,
+    },
 };
 
 pub static SUPER_CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
 
-#[derive(Clone, Default)]
+/// Instructions sent when a Responses API request has none, which the backend requires
+pub const DEFAULT_INSTRUCTIONS: &str = "You are a helpful assistant.";
+
+/// Reasoning efforts that can be selected by a model name suffix
+const REASONING_EFFORTS: [&str; 4] = ["minimal", "low", "medium", "high"];
+
+/// Fallback wait for a rate limited account whose reset time is unknown
+const DEFAULT_RESET_SECS: i64 = 60 * 60;
+
+#[derive(Clone)]
 pub struct CodexState {
     pub client: Client,
+    pub handle: CodexActorHandle,
+    /// Locks serializing the token refreshes of each account, by account id
+    refresh_locks: Cache<String, Arc<Mutex<()>>>,
 }
 
 impl CodexState {
-    pub fn new() -> Self {
+    pub fn new(handle: CodexActorHandle) -> Self {
         let mut builder = ClientBuilder::new().cookie_store(false);
         if let Some(p) = &CLEWDR_CONFIG.load().wreq_proxy {
             builder = builder.proxy(p.to_owned());
         }
         let client = builder.build().unwrap_or_else(|_| SUPER_CLIENT.to_owned());
-        Self { client }
+        Self {
+            client,
+            handle,
+            refresh_locks: Cache::new(1000),
+        }
     }
 
-    pub fn normalize_model_name(&self, name: Option<&str>) -> String {
+    /// Normalizes a model name, returning it with the reasoning effort its suffix selects
+    ///
+    /// Accepts `gpt-5:high` as well as the `gpt-5-high` and `gpt-5_high` forms.
+    pub fn normalize_model_name(&self, name: Option<&str>) -> (String, Option<&'static str>) {
         let Some(name) = name.map(|s| s.trim()).filter(|s| !s.is_empty()) else {
-            return "gpt-5".to_string();
+            return ("gpt-5".to_string(), None);
         };
-        let mut base = name.split(':').next().unwrap_or(name).trim().to_string();
+        let (base, tag) = name.split_once(':').unwrap_or((name, ""));
+        let mut base = base.trim().to_string();
+        let mut effort = REASONING_EFFORTS
+            .into_iter()
+            .find(|e| tag.trim().eq_ignore_ascii_case(e));
         for sep in ['-', '_'] {
             let lowered = base.to_lowercase();
-            for effort in ["minimal", "low", "medium", "high"] {
-                let suffix = format!("{}{}", sep, effort);
-                if lowered.ends_with(&suffix) {
-                    let n = base.len() - suffix.len();
-                    base.truncate(n);
-                    break;
-                }
+            if let Some(e) = REASONING_EFFORTS
+                .into_iter()
+                .find(|e| lowered.ends_with(&format!("{sep}{e}")))
+            {
+                base.truncate(base.len() - e.len() - 1);
+                effort = effort.or(Some(e));
             }
         }
-        match base.as_str() {
+        let model = match base.as_str() {
             "gpt5" | "gpt-5-latest" | "gpt-5" => "gpt-5".to_string(),
             "codex" | "codex-mini" | "codex-mini-latest" => "codex-mini-latest".to_string(),
             _ => base,
-        }
+        };
+        (model, effort)
     }
 
     /// Convert OpenAI messages to ChatGPT Responses input items
@@ -68,13 +103,38 @@ impl CodexState {
                     if let MessageContent::Blocks { content } = &msg.content {
                         // Also capture assistant emitted text
                         let mut items: Vec<Value> = vec![];
+                        let mut calls: Vec<Value> = vec![];
                         for blk in content.iter() {
                             match blk {
-                                ContentBlock::Text { text } => {
+                                ContentBlock::Text { text, .. } => {
                                     if !text.is_empty() {
                                         items.push(json!({"type": "output_text", "text": text}));
                                     }
                                 }
+                                ContentBlock::Thinking {
+                                    thinking,
+                                    signature,
+                                } if !signature.is_empty() => {
+                                    // reasoning is only kept by the upstream as encrypted content
+                                    let summary = if thinking.is_empty() {
+                                        json!([])
+                                    } else {
+                                        json!([{"type": "summary_text", "text": thinking}])
+                                    };
+                                    out.push(json!({
+                                        "type": "reasoning",
+                                        "summary": summary,
+                                        "encrypted_content": signature,
+                                    }));
+                                }
+                                ContentBlock::ToolUse { id, name, input } => {
+                                    calls.push(json!({
+                                        "type": "function_call",
+                                        "call_id": id,
+                                        "name": name,
+                                        "arguments": tool_call_arguments(input),
+                                    }));
+                                }
                                 _ => {}
                             }
                         }
@@ -85,6 +145,7 @@ impl CodexState {
                                 "content": items,
                             }));
                         }
+                        out.extend(calls);
                     } else if let MessageContent::Text { content } = &msg.content {
                         if !content.is_empty() {
                             out.push(json!({
@@ -108,7 +169,7 @@ impl CodexState {
                         let mut items: Vec<Value> = vec![];
                         for blk in content.iter() {
                             match blk {
-                                ContentBlock::Text { text } => {
+                                ContentBlock::Text { text, .. } => {
                                     if !text.is_empty() {
                                         let kind = if matches!(msg.role, Role::Assistant) {
                                             "output_text"
@@ -125,15 +186,23 @@ impl CodexState {
                                             .push(json!({"type": "input_image", "image_url": url}));
                                     }
                                 }
+                                ContentBlock::Image { source } => {
+                                    let url = format!(
+                                        "data:{};base64,{}",
+                                        source.media_type, source.data
+                                    );
+                                    items.push(json!({"type": "input_image", "image_url": url}));
+                                }
                                 ContentBlock::ToolResult {
                                     tool_use_id,
                                     content,
+                                    ..
                                 } => {
-                                    // Map Claude style tool_result to function_call_output
-                                    items.push(json!({
+                                    // Map Claude style tool_result to a function_call_output item
+                                    out.push(json!({
                                         "type": "function_call_output",
                                         "call_id": tool_use_id,
-                                        "output": content,
+                                        "output": content.text(),
                                     }));
                                 }
                                 _ => {}
@@ -183,21 +252,6 @@ impl CodexState {
         reasoning: Option<Value>,
         session_id: Option<String>,
     ) -> Result<wreq::Response, ClewdrError> {
-        let access_token = CLEWDR_CONFIG
-            .load()
-            .codex
-            .tokens
-            .access_token
-            .clone()
-            .ok_or(ClewdrError::BadRequest {
-                msg: "Codex not authenticated. Use /api/codex/oauth/start".into(),
-            })?;
-        let account_id = CLEWDR_CONFIG.load().codex.tokens.account_id.clone().ok_or(
-            ClewdrError::BadRequest {
-                msg: "Codex missing account_id".into(),
-            },
-        )?;
-
         let mut include: Vec<&'static str> = vec![];
         if reasoning.is_some() {
             include.push("reasoning.encrypted_content");
@@ -222,7 +276,154 @@ impl CodexState {
         if let Some(r) = reasoning {
             payload["reasoning"] = r;
         }
+        self.send_upstream(&payload, &sid).await
+    }
+
+    /// Sends a Responses API payload to the Codex backend with an account of the pool
+    ///
+    /// Rate limited accounts are rotated out, and expired access tokens refreshed once.
+    pub async fn send_upstream(
+        &self,
+        payload: &Value,
+        sid: &str,
+    ) -> Result<wreq::Response, ClewdrError> {
+        // rotate to the next account of the pool while the upstream is rate limited
+        for _ in 0..CLEWDR_CONFIG.load().max_retries + 1 {
+            let mut account = self.handle.request().await?;
+            let mut resp = self.send(&account, payload, sid).await?;
+            if resp.status() == StatusCode::UNAUTHORIZED {
+                // the access token expired or was revoked, refresh it and try once more
+                match self.refresh_account(account.to_owned()).await {
+                    Ok(refreshed) => {
+                        account = refreshed;
+                        resp = self.send(&account, payload, sid).await?;
+                    }
+                    Err(e) => {
+                        warn!("Codex token refresh failed: {}", e);
+                        continue;
+                    }
+                }
+            }
+            if let Some(usage) = rate_limit_usage(resp.headers()) {
+                account.usage = Some(usage);
+            }
+            if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+                let body = resp.text().await.unwrap_or_default();
+                let reset_time = rate_limit_reset(&body, account.usage.as_ref());
+                info!("Codex account {} hit its usage limit", account.id());
+                self.handle
+                    .return_account(account, Some(reset_time))
+                    .await?;
+                continue;
+            }
+            let reset_time = account.usage.as_ref().and_then(|u| u.exhausted_until());
+            self.handle.return_account(account, reset_time).await?;
+            return Ok(resp);
+        }
+        Err(ClewdrError::TooManyRetries)
+    }
 
+    /// Exchanges the refresh token of an account for new tokens
+    ///
+    /// Refresh tokens rotate on use, so the refreshes of an account are serialized,
+    /// and an account refreshed while waiting is returned as is.
+    /// The refreshed account is submitted to the pool, which persists it to the config.
+    pub async fn refresh_account(
+        &self,
+        account: CodexAccount,
+    ) -> Result<CodexAccount, ClewdrError> {
+        let lock = self
+            .refresh_locks
+            .get_with(account.id().to_string(), Default::default);
+        let _guard = lock.lock().await;
+        // re-read the tokens, another request may have refreshed them meanwhile
+        let status = self.handle.get_status().await?;
+        let Some(current) = status
+            .valid
+            .into_iter()
+            .chain(status.exhausted)
+            .find(|a| *a == account)
+        else {
+            return Err(ClewdrError::UnexpectedNone {
+                msg: "Codex account is no longer in the pool",
+            });
+        };
+        if current.tokens.access_token != account.tokens.access_token {
+            return Ok(current);
+        }
+        let mut account = current;
+        let refresh_token =
+            account
+                .tokens
+                .refresh_token
+                .to_owned()
+                .ok_or(ClewdrError::UnexpectedNone {
+                    msg: "Codex account has no refresh token",
+                })?;
+        info!("Refreshing tokens of Codex account {}", account.id());
+        let payload = json!({
+            "client_id": CLEWDR_CONFIG.load().codex.effective_client_id(),
+            "grant_type": "refresh_token",
+            "refresh_token": refresh_token,
+            "scope": "openid profile email",
+        });
+        let resp = self
+            .client
+            .request(Method::POST, format!("{}/oauth/token", CODEX_OAUTH_ISSUER))
+            .header(CONTENT_TYPE, "application/json")
+            .json(&payload)
+            .send()
+            .await
+            .context(WreqSnafu {
+                msg: "Codex token refresh request failed",
+            })?;
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        if !status.is_success() {
+            let message = format!("Codex token endpoint returned {}: {}", status, body);
+            // a rejected refresh token will not work again, unlike a server error
+            if status.is_client_error() {
+                self.handle
+                    .invalidate(account, format!("token refresh rejected with {status}"))
+                    .await?;
+            }
+            return Err(ClewdrError::Whatever {
+                message,
+                source: None,
+            });
+        }
+        let tokens: Value = serde_json::from_str(&body).unwrap_or_default();
+        let token = |key: &str| {
+            tokens[key]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+        };
+        let Some(access_token) = token("access_token") else {
+            return Err(ClewdrError::UnexpectedNone {
+                msg: "No access token in Codex token response",
+            });
+        };
+        account.tokens.access_token = Some(access_token);
+        // the id and refresh tokens are only rotated by some responses
+        if let Some(id_token) = token("id_token") {
+            account.tokens.id_token = Some(id_token);
+        }
+        if let Some(refresh_token) = token("refresh_token") {
+            account.tokens.refresh_token = Some(refresh_token);
+        }
+        account.tokens.last_refresh = Some(chrono::Utc::now().to_rfc3339());
+        self.handle.submit(account.to_owned()).await?;
+        Ok(account)
+    }
+
+    async fn send(
+        &self,
+        account: &CodexAccount,
+        payload: &Value,
+        session_id: &str,
+    ) -> Result<wreq::Response, ClewdrError> {
+        let access_token = account.tokens.access_token.as_deref().unwrap_or_default();
         let url = "https://chatgpt.com/backend-api/codex/responses";
         let req = self
             .client
@@ -230,16 +431,63 @@ impl CodexState {
             .header(ACCEPT, "text/event-stream")
             .header(CONTENT_TYPE, "application/json")
             .header("Authorization", format!("Bearer {}", access_token))
-            .header("chatgpt-account-id", account_id)
+            .header("chatgpt-account-id", account.id())
             .header("OpenAI-Beta", "responses=experimental")
-            .header("session_id", sid)
-            .json(&payload);
+            .header("session_id", session_id)
+            .json(payload);
         Ok(req.send().await.context(WreqSnafu {
             msg: "Codex upstream request failed",
         })?)
     }
 }
 
+/// Reads the usage of the rate limit windows from the `x-codex-*` response headers
+fn rate_limit_usage(headers: &HeaderMap) -> Option<AccountUsage> {
+    let now = chrono::Utc::now().timestamp();
+    let header = |name: String| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<f64>().ok())
+    };
+    let windows = ["primary", "secondary"]
+        .into_iter()
+        .filter_map(|window| {
+            let utilization = header(format!("x-codex-{window}-used-percent"))?;
+            let resets_at = header(format!("x-codex-{window}-reset-at"))
+                .map(|t| t as i64)
+                .or_else(|| {
+                    header(format!("x-codex-{window}-reset-after-seconds")).map(|s| now + s as i64)
+                });
+            Some((
+                window.to_string(),
+                UsageWindow {
+                    utilization,
+                    resets_at,
+                },
+            ))
+        })
+        .collect::<BTreeMap<_, _>>();
+    if windows.is_empty() {
+        return None;
+    }
+    Some(AccountUsage {
+        windows,
+        updated_at: now,
+    })
+}
+
+/// Time at which a rate limited account resets, from the error body or the usage headers
+fn rate_limit_reset(body: &str, usage: Option<&AccountUsage>) -> i64 {
+    let now = chrono::Utc::now().timestamp();
+    let error = serde_json::from_str::<Value>(body).unwrap_or_default()["error"].take();
+    error["resets_at"]
+        .as_i64()
+        .or_else(|| error["resets_in_seconds"].as_i64().map(|s| now + s))
+        .or_else(|| usage.and_then(|u| u.exhausted_until()))
+        .unwrap_or(now + DEFAULT_RESET_SECS)
+}
+
 /// Generate a deterministic session id from instructions + first user message
 pub fn ensure_session_id(instructions: Option<&str>, input_items: &[Value]) -> String {
     let mut prefix = String::new();
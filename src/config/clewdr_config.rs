@@ -24,8 +24,9 @@ use super::{CONFIG_PATH, ENDPOINT_URL, key::KeyStatus};
 use crate::{
     Args,
     config::{
-        CC_CLIENT_ID, CookieStatus, UselessCookie, default_check_update, default_ip,
-        default_max_retries, default_port, default_skip_cool_down, default_use_real_roles,
+        CC_CLIENT_ID, CodexAccount, CookieStatus, UselessCookie, default_check_update, default_ip,
+        default_max_retries, default_port, default_skip_cool_down, default_token_refresh_window,
+        default_usage_poll_interval, default_use_real_roles,
     },
     error::ClewdrError,
     utils::enabled,
@@ -141,6 +142,11 @@ pub struct ClewdrConfig {
     pub max_retries: usize,
     #[serde(default)]
     pub preserve_chats: bool,
+    /// Hours after their last update in which preserved conversations are deleted, 0 keeps them
+    #[serde(default)]
+    pub preserve_chats_ttl: u64,
+    #[serde(default)]
+    pub reuse_conversations: bool,
     #[serde(default)]
     pub web_search: bool,
 
@@ -173,6 +179,16 @@ pub struct ClewdrConfig {
     pub claude_code_client_id: Option<String>,
     #[serde(default)]
     pub custom_system: Option<String>,
+    #[serde(default)]
+    pub claude_code_beta_allow: Vec<String>,
+    #[serde(default)]
+    pub claude_code_beta_deny: Vec<String>,
+    /// Minutes before `expires_at` in which tokens are refreshed in background, 0 disables it
+    #[serde(default = "default_token_refresh_window")]
+    pub token_refresh_window: u64,
+    /// Minutes between polls of the account usage of cookies, 0 disables it
+    #[serde(default = "default_usage_poll_interval")]
+    pub usage_poll_interval: u64,
 
     // Skip field, can hot reload
     #[serde(skip)]
@@ -207,6 +223,8 @@ impl Default for ClewdrConfig {
             custom_a: None,
             wreq_proxy: None,
             preserve_chats: false,
+            preserve_chats_ttl: 0,
+            reuse_conversations: false,
             web_search: false,
             skip_first_warning: false,
             skip_second_warning: false,
@@ -216,6 +234,10 @@ impl Default for ClewdrConfig {
             skip_normal_pro: false,
             claude_code_client_id: None,
             custom_system: None,
+            claude_code_beta_allow: Vec::new(),
+            claude_code_beta_deny: Vec::new(),
+            token_refresh_window: default_token_refresh_window(),
+            usage_poll_interval: default_usage_poll_interval(),
             no_fs: false,
             log_to_file: false,
             codex: Default::default(),
@@ -353,6 +375,19 @@ impl ClewdrConfig {
             .to_string()
     }
 
+    /// Checks whether a client supplied `anthropic-beta` flag may be forwarded
+    /// on the Claude Code route
+    ///
+    /// Denied flags are always dropped; when the allow list is not empty,
+    /// only flags on it are forwarded
+    pub fn cc_beta_allowed(&self, beta: &str) -> bool {
+        if self.claude_code_beta_deny.iter().any(|b| b == beta) {
+            return false;
+        }
+        self.claude_code_beta_allow.is_empty()
+            || self.claude_code_beta_allow.iter().any(|b| b == beta)
+    }
+
     /// Loads configuration from files and environment variables
     /// Combines settings from config.toml, clewdr.toml, and environment variables
     /// Also loads cookies from a file if specified
@@ -462,8 +497,12 @@ impl ClewdrConfig {
 pub struct CodexConfig {
     #[serde(default)]
     pub client_id: Option<String>,
-    #[serde(default)]
+    /// Tokens of the single account of older configs, moved into `accounts` on load
+    #[serde(default, skip_serializing)]
     pub tokens: CodexTokens,
+    /// ChatGPT accounts of the Codex pool
+    #[serde(default)]
+    pub accounts: Vec<CodexAccount>,
     /// Optional base URL prefix used to build the OAuth callback redirect_uri.
     /// Example: "https://your-domain.example.com" (no trailing slash needed)
     /// If not set, defaults to "http://localhost:{port}" where port is the server listen port.
@@ -497,6 +536,13 @@ impl CodexConfig {
                 }
             }
         }
+        let legacy = CodexAccount {
+            tokens: std::mem::take(&mut self.tokens),
+            ..Default::default()
+        };
+        if legacy.is_authenticated() && !self.accounts.contains(&legacy) {
+            self.accounts.push(legacy);
+        }
         self
     }
 
@@ -508,16 +554,6 @@ impl CodexConfig {
     }
 
     pub fn is_authenticated(&self) -> bool {
-        self.tokens
-            .access_token
-            .as_deref()
-            .map(|s| !s.is_empty())
-            .unwrap_or(false)
-            && self
-                .tokens
-                .account_id
-                .as_deref()
-                .map(|s| !s.is_empty())
-                .unwrap_or(false)
+        self.accounts.iter().any(CodexAccount::is_authenticated)
     }
 }
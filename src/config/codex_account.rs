@@ -0,0 +1,122 @@
+use std::{hash::Hash, time::Duration};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+use crate::config::{AccountUsage, CodexTokens};
+
+/// Days after which tokens without a known expiry are refreshed
+const MAX_TOKEN_AGE_DAYS: i64 = 8;
+
+/// Reads a claim of a JWT, without verifying its signature
+///
+/// `top_ns` names the namespace object holding the claim, or is empty for a top
+/// level claim such as `exp` or `email`
+pub fn parse_jwt_claim(token: &str, top_ns: &str, key: &str) -> Option<Value> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let payload = parts[1];
+    let decoded = URL_SAFE_NO_PAD.decode(payload.as_bytes()).ok()?;
+    let v: Value = serde_json::from_slice(&decoded).ok()?;
+    let ns = if top_ns.is_empty() {
+        &v
+    } else {
+        v.get(top_ns)?
+    };
+    ns.get(key).cloned()
+}
+
+/// A ChatGPT account in the Codex pool
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CodexAccount {
+    /// OAuth tokens of the account
+    #[serde(flatten)]
+    pub tokens: CodexTokens,
+    /// Email of the account, from the id token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Unix timestamp at which a rate limited account can be used again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_time: Option<i64>,
+    /// Number of requests sent with the account
+    #[serde(default)]
+    pub requests: u64,
+    /// Latest usage of the rate limit windows, reported in the Codex response headers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<AccountUsage>,
+    /// Why the tokens can no longer be refreshed, the account needs a new login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid: Option<String>,
+}
+
+impl PartialEq for CodexAccount {
+    fn eq(&self, other: &Self) -> bool {
+        self.tokens.account_id == other.tokens.account_id
+    }
+}
+
+impl Eq for CodexAccount {}
+
+impl Hash for CodexAccount {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.tokens.account_id.hash(state);
+    }
+}
+
+impl CodexAccount {
+    /// ChatGPT account id, which identifies the account in the pool
+    pub fn id(&self) -> &str {
+        self.tokens.account_id.as_deref().unwrap_or_default()
+    }
+
+    /// Whether the account has the tokens needed to call Codex
+    pub fn is_authenticated(&self) -> bool {
+        self.tokens
+            .access_token
+            .as_deref()
+            .is_some_and(|s| !s.is_empty())
+            && !self.id().is_empty()
+    }
+
+    /// Checks if the access token expires within the given window
+    ///
+    /// The expiry is read from the `exp` claim of the access token. Tokens without
+    /// one are refreshed once `MAX_TOKEN_AGE_DAYS` have passed since the last refresh.
+    pub fn expires_within(&self, window: Duration) -> bool {
+        let now = Utc::now();
+        let expires_at = self
+            .tokens
+            .access_token
+            .as_deref()
+            .and_then(|token| parse_jwt_claim(token, "", "exp"))
+            .and_then(|exp| exp.as_i64())
+            .and_then(|exp| DateTime::from_timestamp(exp, 0))
+            .or_else(|| {
+                let last_refresh = self.tokens.last_refresh.as_deref()?;
+                let last_refresh = DateTime::parse_from_rfc3339(last_refresh).ok()?;
+                Some(last_refresh.to_utc() + TimeDelta::days(MAX_TOKEN_AGE_DAYS))
+            });
+        let window = TimeDelta::from_std(window).unwrap_or_default();
+        expires_at.is_none_or(|t| t - now <= window)
+    }
+
+    /// Checks if the account's reset time has expired
+    /// If the reset time has passed, sets it to None so the account becomes valid again
+    pub fn reset(self) -> Self {
+        if let Some(t) = self.reset_time
+            && t < chrono::Utc::now().timestamp()
+        {
+            info!("Codex account reset time expired");
+            return Self {
+                reset_time: None,
+                ..self
+            };
+        }
+        self
+    }
+}
@@ -139,5 +139,21 @@ pub const fn default_skip_cool_down() -> bool {
     true
 }
 
+/// Default window before token expiry in which Claude Code tokens are refreshed in background
+///
+/// # Returns
+/// * `u64` - The default value of 30 minutes
+pub const fn default_token_refresh_window() -> u64 {
+    30
+}
+
+/// Default interval between polls of the claude.ai account usage
+///
+/// # Returns
+/// * `u64` - The default value of 10 minutes
+pub const fn default_usage_poll_interval() -> u64 {
+    10
+}
+
 /// Default cookie value for testing purposes
 pub const PLACEHOLDER_COOKIE: &str = "sk-ant-REDACTED";
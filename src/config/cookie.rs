@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fmt::{Debug, Display},
     hash::Hash,
     ops::Deref,
@@ -8,6 +9,7 @@ use std::{
 
 use regex;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use snafu::{GenerateImplicitData, Location};
 use tracing::info;
 
@@ -49,6 +51,99 @@ pub struct CookieStatus {
     pub token: Option<TokenInfo>,
     #[serde(default)]
     pub reset_time: Option<i64>,
+    /// Whether the account is entitled to the 1M context window, `None` if not yet known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_1m: Option<bool>,
+    /// Preferred organization, matched by uuid or name, or `team` / `max` to prefer that plan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_preference: Option<String>,
+    /// UUID of the organization chosen for this cookie
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_uuid: Option<String>,
+    /// All organizations discovered for the account
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orgs: Vec<OrgInfo>,
+    /// Latest usage of the account limits, `None` if not yet polled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<AccountUsage>,
+}
+
+/// Usage of the rate limit windows of an account, as reported by claude.ai
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AccountUsage {
+    /// Windows keyed by name, such as `five_hour` or `seven_day`
+    pub windows: BTreeMap<String, UsageWindow>,
+    /// Unix timestamp of the poll
+    pub updated_at: i64,
+}
+
+/// A single rate limit window of an account
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct UsageWindow {
+    /// Percentage of the window used
+    pub utilization: f64,
+    /// Unix timestamp at which the window resets
+    #[serde(default)]
+    pub resets_at: Option<i64>,
+}
+
+impl AccountUsage {
+    /// Percentage left in the most used window
+    pub fn headroom(&self) -> f64 {
+        let used = self
+            .windows
+            .values()
+            .map(|w| w.utilization)
+            .fold(0.0, f64::max);
+        (100.0 - used).max(0.0)
+    }
+
+    /// Returns the latest reset time among the used up windows
+    pub fn exhausted_until(&self) -> Option<i64> {
+        self.windows
+            .values()
+            .filter(|w| w.utilization >= 100.0)
+            .filter_map(|w| w.resets_at)
+            .max()
+    }
+}
+
+/// An organization the account of a cookie belongs to
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct OrgInfo {
+    pub uuid: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl OrgInfo {
+    /// Parses an organization object of the claude.ai API
+    pub fn from_value(org: &Value) -> Option<Self> {
+        Some(Self {
+            uuid: org["uuid"].as_str()?.to_string(),
+            name: org["name"].as_str().unwrap_or_default().to_string(),
+            capabilities: org["capabilities"]
+                .as_array()
+                .map(|a| {
+                    a.iter()
+                        .filter_map(|c| c.as_str())
+                        .map(|c| c.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Checks if the organization can be used for chat
+    pub fn can_chat(&self) -> bool {
+        self.capabilities.iter().any(|c| c == "chat")
+    }
+
+    fn has_capability(&self, needle: &str) -> bool {
+        self.capabilities.iter().any(|c| c.contains(needle))
+    }
 }
 
 impl PartialEq for CookieStatus {
@@ -92,9 +187,50 @@ impl CookieStatus {
             cookie,
             token: None,
             reset_time,
+            context_1m: None,
+            org_preference: None,
+            org_uuid: None,
+            orgs: Vec::new(),
+            usage: None,
         })
     }
 
+    /// Picks the chat organization matching the preference of this cookie
+    ///
+    /// A uuid or name (case insensitive) is matched first, then `team` prefers
+    /// team organizations and `max` prefers Max plan organizations
+    ///
+    /// # Returns
+    /// The preferred organization, or `None` when no preference is set or none matches
+    pub fn preferred_org<'a>(&self, orgs: &'a [OrgInfo]) -> Option<&'a OrgInfo> {
+        let pref = self.org_preference.as_deref()?.trim();
+        let mut chat = orgs.iter().filter(|o| o.can_chat());
+        if let Some(org) = chat
+            .clone()
+            .find(|o| o.uuid == pref || o.name.eq_ignore_ascii_case(pref))
+        {
+            return Some(org);
+        }
+        match pref.to_ascii_lowercase().as_str() {
+            "team" => chat.find(|o| o.has_capability("raven")),
+            "max" => chat.find(|o| o.has_capability("max")),
+            _ => None,
+        }
+    }
+
+    /// Records the discovered organizations and the chosen one
+    ///
+    /// # Returns
+    /// Whether anything changed
+    pub fn set_orgs(&mut self, org_uuid: &str, orgs: Vec<OrgInfo>) -> bool {
+        if self.org_uuid.as_deref() == Some(org_uuid) && self.orgs == orgs {
+            return false;
+        }
+        self.org_uuid = Some(org_uuid.to_string());
+        self.orgs = orgs;
+        true
+    }
+
     /// Checks if the cookie's reset time has expired
     /// If the reset time has passed, sets it to None so the cookie becomes valid again
     ///
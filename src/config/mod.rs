@@ -1,6 +1,7 @@
 // Re-export all items from submodules
 mod clewdr_config;
 mod cli_token;
+mod codex_account;
 mod constants;
 mod cookie;
 mod key;
@@ -9,6 +10,7 @@ mod token;
 
 pub use clewdr_config::*;
 pub use cli_token::*;
+pub use codex_account::*;
 pub use constants::*;
 pub use cookie::*;
 pub use key::*;
@@ -47,4 +47,9 @@ impl TokenInfo {
         debug!("Expires at: {}", self.expires_at.to_rfc3339());
         Utc::now() >= self.expires_at - Duration::from_secs(60 * 5) // 5 minutes
     }
+
+    /// Checks whether the token expires within the given window
+    pub fn expires_within(&self, window: Duration) -> bool {
+        Utc::now() >= self.expires_at - window
+    }
 }
@@ -14,7 +14,10 @@ use snafu::Location;
 use strum::IntoStaticStr;
 use tokio::sync::oneshot;
 use tracing::{debug, error};
-use wreq::{Response, StatusCode, header::InvalidHeaderValue};
+use wreq::{
+    Response, StatusCode,
+    header::{HeaderValue, InvalidHeaderValue},
+};
 
 use crate::{config::Reason, types::claude::Message};
 
@@ -118,6 +121,16 @@ pub enum ClewdrError {
     NoCookieAvailable,
     #[snafu(display("No key available"))]
     NoKeyAvailable,
+    #[snafu(display("All Codex accounts are rate limited"))]
+    NoCodexAccountAvailable,
+    #[snafu(display("1M context window is not available for this account"))]
+    Context1MUnavailable,
+    #[snafu(display("Failed to fetch image {}: {}", url, msg))]
+    ImageFetchError { url: String, msg: String },
+    #[snafu(display("Failed to attach document {}: {}", name, msg))]
+    DocumentError { name: String, msg: String },
+    #[snafu(display("Output does not match the response format: {}", msg))]
+    InvalidResponseFormat { msg: String },
     #[snafu(display("Invalid Cookie: {}", reason))]
     #[snafu(context(false))]
     InvalidCookie {
@@ -220,6 +233,16 @@ impl IntoResponse for ClewdrError {
             ClewdrError::PathNotFound { .. } => (StatusCode::NOT_FOUND, json!(self.to_string())),
             ClewdrError::InvalidAuth => (StatusCode::UNAUTHORIZED, json!(self.to_string())),
             ClewdrError::BadRequest { .. } => (StatusCode::BAD_REQUEST, json!(self.to_string())),
+            ClewdrError::Context1MUnavailable => (StatusCode::BAD_REQUEST, json!(self.to_string())),
+            ClewdrError::NoCodexAccountAvailable => {
+                (StatusCode::TOO_MANY_REQUESTS, json!(self.to_string()))
+            }
+            ClewdrError::ImageFetchError { .. } | ClewdrError::DocumentError { .. } => {
+                (StatusCode::BAD_REQUEST, json!(self.to_string()))
+            }
+            ClewdrError::InvalidResponseFormat { .. } => {
+                (StatusCode::BAD_GATEWAY, json!(self.to_string()))
+            }
             ClewdrError::InvalidHeaderValue { .. } => {
                 (StatusCode::BAD_REQUEST, json!(self.to_string()))
             }
@@ -388,67 +411,78 @@ impl CheckClaudeErr for Response {
                 });
             }
         };
-        let Ok(err) = serde_json::from_str::<ClaudeError>(&text) else {
-            let error = ClaudeErrorBody {
-                message: format!("Unknown error: {text}").into(),
-                r#type: "error_parse_error_body".to_string(),
-                code: Some(status.as_u16()),
-            };
-            return Err(ClewdrError::ClaudeHttpError {
-                code: status,
-                inner: error,
-            });
+        Err(claude_error(status, reset_header.as_ref(), &text))
+    }
+}
+
+/// Converts the status and body of a failed Claude response into an error
+///
+/// Disabled, unauthorized and rate limited accounts are reported as invalid cookies
+pub fn claude_error(
+    status: StatusCode,
+    reset_header: Option<&HeaderValue>,
+    text: &str,
+) -> ClewdrError {
+    let Ok(err) = serde_json::from_str::<ClaudeError>(text) else {
+        let error = ClaudeErrorBody {
+            message: format!("Unknown error: {text}").into(),
+            r#type: "error_parse_error_body".to_string(),
+            code: Some(status.as_u16()),
         };
-        if status == 400 && err.error.message == json!("This organization has been disabled.") {
-            // account disabled
-            return Err(Reason::Disabled.into());
-        }
-        if status == 401 {
-            return Err(Reason::Null.into());
-        }
-        const OAUTH_403_PHRASE: &str =
-            "oauth authentication is currently not allowed for this organization";
-        if status == 403
-            && err
-                .error
-                .message
-                .to_string()
-                .to_ascii_lowercase()
-                .contains(OAUTH_403_PHRASE)
-        {
-            return Err(Reason::Null.into());
-        }
-        let inner_error = err.error;
-        // check if the error is a rate limit error
-        if status == 429 {
-            // get the reset time from the error message
-            let ts = inner_error.message["resetsAt"]
-                .as_i64()
-                .or_else(|| reset_header.and_then(|h| h.to_str().ok()?.parse::<i64>().ok()));
-            if let Some(ts) = ts {
-                let reset_time = chrono::DateTime::from_timestamp(ts, 0)
-                    .ok_or(ClewdrError::TimestampError { timestamp: ts })?
-                    .to_utc();
-                let now = chrono::Utc::now();
-                let diff = reset_time - now;
-                let mins = diff.num_minutes();
-                error!(
-                    "Rate limit exceeded, expires in {} hours",
-                    mins as f64 / 60.0
-                );
-                return Err(ClewdrError::InvalidCookie {
-                    reason: Reason::TooManyRequest(ts),
-                });
-            } else {
-                error!("Rate limit exceeded, but no reset time provided");
-                return Err(ClewdrError::InvalidCookie {
-                    reason: Reason::TooManyRequest(Utc::now().timestamp() + 3600),
-                });
-            }
-        }
-        Err(ClewdrError::ClaudeHttpError {
+        return ClewdrError::ClaudeHttpError {
             code: status,
-            inner: inner_error,
-        })
+            inner: error,
+        };
+    };
+    if status == 400 && err.error.message == json!("This organization has been disabled.") {
+        // account disabled
+        return Reason::Disabled.into();
+    }
+    if status == 401 {
+        return Reason::Null.into();
+    }
+    const OAUTH_403_PHRASE: &str =
+        "oauth authentication is currently not allowed for this organization";
+    if status == 403
+        && err
+            .error
+            .message
+            .to_string()
+            .to_ascii_lowercase()
+            .contains(OAUTH_403_PHRASE)
+    {
+        return Reason::Null.into();
+    }
+    let inner_error = err.error;
+    // check if the error is a rate limit error
+    if status == 429 {
+        // get the reset time from the error message
+        let ts = inner_error.message["resetsAt"]
+            .as_i64()
+            .or_else(|| reset_header.and_then(|h| h.to_str().ok()?.parse::<i64>().ok()));
+        if let Some(ts) = ts {
+            let Some(reset_time) = chrono::DateTime::from_timestamp(ts, 0) else {
+                return ClewdrError::TimestampError { timestamp: ts };
+            };
+            let now = chrono::Utc::now();
+            let diff = reset_time - now;
+            let mins = diff.num_minutes();
+            error!(
+                "Rate limit exceeded, expires in {} hours",
+                mins as f64 / 60.0
+            );
+            return ClewdrError::InvalidCookie {
+                reason: Reason::TooManyRequest(ts),
+            };
+        } else {
+            error!("Rate limit exceeded, but no reset time provided");
+            return ClewdrError::InvalidCookie {
+                reason: Reason::TooManyRequest(Utc::now().timestamp() + 3600),
+            };
+        }
+    }
+    ClewdrError::ClaudeHttpError {
+        code: status,
+        inner: inner_error,
     }
 }
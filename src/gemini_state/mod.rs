@@ -26,6 +26,8 @@ use crate::{
 pub enum GeminiApiFormat {
     Gemini,
     OpenAI,
+    /// Claude Messages API, sent upstream in Gemini format
+    Claude,
 }
 
 static DUMMY_CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
@@ -165,7 +167,7 @@ impl GeminiState {
         let access_token = get_token(cred.to_owned()).await?;
         let bearer = format!("Bearer {access_token}");
         let res = match self.api_format {
-            GeminiApiFormat::Gemini => {
+            GeminiApiFormat::Gemini | GeminiApiFormat::Claude => {
                 let endpoint = format!(
                     "https://aiplatform.googleapis.com/v1/projects/{}/locations/global/publishers/google/models/{}:{method}",
                     cred.project_id.unwrap_or_default(),
@@ -323,7 +325,7 @@ impl GeminiState {
             info!("[KEY] {}", key.key.ellipse().green());
             let key = key.key.to_string();
             match self.api_format {
-                GeminiApiFormat::Gemini => {
+                GeminiApiFormat::Gemini | GeminiApiFormat::Claude => {
                     let mut query_vec = self.query.to_vec();
                     query_vec.push(("key", key.as_str()));
                     self.client
@@ -409,7 +411,7 @@ impl GeminiState {
         })?;
 
         match self.api_format {
-            GeminiApiFormat::Gemini => {
+            GeminiApiFormat::Gemini | GeminiApiFormat::Claude => {
                 let res = serde_json::from_slice::<GeminiResponse>(&bytes)?;
                 if res.candidates.is_empty() {
                     return Err(ClewdrError::EmptyChoices);
@@ -0,0 +1,163 @@
+use async_stream::try_stream;
+use axum::response::{IntoResponse, Response, Sse, sse::Event};
+use eventsource_stream::{EventStreamError, Eventsource};
+use futures::{Stream, StreamExt, TryStreamExt, stream};
+use http::header::CONTENT_TYPE;
+use serde_json::{Value, json};
+
+use super::{
+    ClaudeContext, add_usage_info, apply_stop_sequences, check_overloaded,
+    response::parse_response, to_oai,
+};
+
+/// Converts an upstream response into an OpenAI response with a single choice
+///
+/// Runs the same steps the routes apply to a single response. Claude.ai replies
+/// carry no usage, so it is added as on the Claude web route.
+async fn to_choice(mut resp: Response, cx: &ClaudeContext) -> Response {
+    resp.extensions_mut().insert(cx.to_owned());
+    let resp = check_overloaded(resp).await;
+    let resp = apply_stop_sequences(resp).await;
+    let resp = match cx {
+        ClaudeContext::Web(_) => add_usage_info(resp).await.into_response(),
+        ClaudeContext::Code(_) => resp,
+    };
+    to_oai(resp).await.into_response()
+}
+
+/// Merges the responses of a request with `n > 1` into one response with indexed choices
+///
+/// Non-streaming completions are merged into a single `choices` array. Streams are
+/// interleaved as their chunks arrive, with the choice index set to the position of the
+/// upstream response and a single usage chunk and `[DONE]` at the end.
+/// The first failed response is returned as is.
+///
+/// # Arguments
+/// * `responses` - Upstream responses, one per choice
+/// * `cx` - Context of the request
+pub async fn merge_choices(responses: Vec<Response>, cx: &ClaudeContext) -> Response {
+    let mut choices = Vec::with_capacity(responses.len());
+    for resp in responses {
+        let resp = to_choice(resp, cx).await;
+        if !resp.status().is_success() {
+            return resp;
+        }
+        choices.push(resp);
+    }
+    if cx.is_stream() {
+        merge_streams(choices)
+    } else {
+        merge_completions(choices).await
+    }
+}
+
+async fn merge_completions(responses: Vec<Response>) -> Response {
+    let mut merged: Option<Value> = None;
+    let mut choices = vec![];
+    let mut completion_tokens = 0;
+    for (i, resp) in responses.into_iter().enumerate() {
+        let mut completion = match parse_response::<Value>(resp).await {
+            Ok(completion) => completion,
+            Err(resp) => return resp,
+        };
+        if let Some(Value::Array(c)) = completion.get_mut("choices").map(Value::take) {
+            choices.extend(c.into_iter().map(|mut c| {
+                c["index"] = json!(i);
+                c
+            }));
+        }
+        completion_tokens += completion["usage"]["completion_tokens"]
+            .as_u64()
+            .unwrap_or_default();
+        merged.get_or_insert(completion);
+    }
+    let mut merged = merged.unwrap_or_default();
+    merged["choices"] = json!(choices);
+    if let Some(usage) = merged.get_mut("usage").filter(|u| u.is_object()) {
+        set_completion_tokens(usage, completion_tokens);
+    }
+    axum::Json(merged).into_response()
+}
+
+fn merge_streams(responses: Vec<Response>) -> Response {
+    // a response that is not a stream is an error of the upstream
+    if let Some(i) = responses.iter().position(|r| {
+        r.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| !v.contains("text/event-stream"))
+    }) {
+        return responses.into_iter().nth(i).unwrap();
+    }
+    Sse::new(interleave(responses))
+        .keep_alive(Default::default())
+        .into_response()
+}
+
+/// Interleaves OpenAI streams, indexing the choices by the position of their stream
+fn interleave(
+    responses: Vec<Response>,
+) -> impl Stream<Item = Result<Event, EventStreamError<axum::Error>>> {
+    let streams = responses.into_iter().enumerate().map(|(i, r)| {
+        r.into_body()
+            .into_data_stream()
+            .eventsource()
+            .map_ok(move |e| (i, e))
+            .boxed()
+    });
+    let mut merged = stream::select_all(streams);
+    try_stream!({
+        let mut id = None;
+        let mut usage_chunk: Option<Value> = None;
+        while let Some(event) = merged.next().await {
+            let (i, event) = event?;
+            if event.data == "[DONE]" {
+                continue;
+            }
+            let Ok(mut chunk) = serde_json::from_str::<Value>(&event.data) else {
+                yield Event::default().data(event.data);
+                continue;
+            };
+            let id = id.get_or_insert_with(|| chunk["id"].to_owned()).to_owned();
+            let Some(choices) = chunk.get_mut("choices").and_then(Value::as_array_mut) else {
+                // errors carry no choices
+                yield Event::default().data(event.data);
+                continue;
+            };
+            if choices.is_empty() {
+                // usage chunks of the choices are summed into one
+                let tokens = chunk["usage"]["completion_tokens"]
+                    .as_u64()
+                    .unwrap_or_default();
+                match usage_chunk {
+                    Some(ref mut u) => {
+                        let total =
+                            u["usage"]["completion_tokens"].as_u64().unwrap_or_default() + tokens;
+                        set_completion_tokens(&mut u["usage"], total);
+                    }
+                    None => usage_chunk = Some(chunk),
+                }
+                continue;
+            }
+            for c in choices.iter_mut() {
+                c["index"] = json!(i);
+            }
+            chunk["id"] = id;
+            yield Event::default().json_data(chunk).unwrap();
+        }
+        if let Some(mut chunk) = usage_chunk {
+            if let Some(id) = id {
+                chunk["id"] = id;
+            }
+            yield Event::default().json_data(chunk).unwrap();
+        }
+        yield Event::default().data("[DONE]");
+    })
+}
+
+/// Sets the completion tokens of an OpenAI usage object, updating the total
+fn set_completion_tokens(usage: &mut Value, completion_tokens: u64) {
+    let prompt_tokens = usage["prompt_tokens"].as_u64().unwrap_or_default();
+    usage["completion_tokens"] = json!(completion_tokens);
+    usage["total_tokens"] = json!(prompt_tokens + completion_tokens);
+}
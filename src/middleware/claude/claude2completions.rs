@@ -0,0 +1,226 @@
+use axum::response::sse::Event;
+use futures::{Stream, StreamExt, TryStreamExt, future, stream};
+use serde_json::{Value, json};
+use tiktoken_rs::o200k_base;
+
+use super::claude2oai::{created, finish_reason, usage_json};
+use crate::types::claude::{
+    ContentBlock, ContentBlockDelta, CreateMessageResponse, StopReason, StreamEvent, Usage,
+};
+
+/// Concatenates the text blocks of a reply
+fn reply_text(input: &CreateMessageResponse) -> String {
+    input
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Maps a Claude stop reason to a legacy Anthropic stop reason
+fn legacy_stop_reason(stop_reason: Option<&StopReason>) -> &'static str {
+    match stop_reason {
+        Some(StopReason::MaxTokens) => "max_tokens",
+        _ => "stop_sequence",
+    }
+}
+
+/// Converts a Claude reply into an OpenAI `text_completion` object
+pub fn transforms_completion_json(input: CreateMessageResponse) -> Value {
+    json!({
+        "id": input.id,
+        "object": "text_completion",
+        "created": created(),
+        "model": input.model,
+        "choices": [{
+            "text": reply_text(&input),
+            "index": 0,
+            "logprobs": null,
+            "finish_reason": finish_reason(input.stop_reason.as_ref()),
+        }],
+        "usage": input.usage.as_ref().map(usage_json),
+    })
+}
+
+/// Converts a Claude reply into a legacy Anthropic `completion` object
+pub fn transforms_complete_json(input: CreateMessageResponse) -> Value {
+    json!({
+        "type": "completion",
+        "id": input.id,
+        "completion": reply_text(&input),
+        "stop_reason": legacy_stop_reason(input.stop_reason.as_ref()),
+        "stop": input.stop_sequence,
+        "model": input.model,
+    })
+}
+
+/// State carried across the events of an OpenAI `text_completion` stream
+struct CompletionStream {
+    id: String,
+    model: String,
+    created: u64,
+    /// Whether to send a final chunk with the token usage
+    include_usage: bool,
+    /// Token usage, updated from the usage reported in the stream
+    usage: Usage,
+    /// Generated text, to count output tokens when the stream reports none
+    completion: String,
+}
+
+impl CompletionStream {
+    fn chunk(&self, choices: Value, usage: Option<Value>) -> Event {
+        let mut data = json!({
+            "id": self.id,
+            "object": "text_completion",
+            "created": self.created,
+            "model": self.model,
+            "choices": choices,
+        });
+        if let Some(usage) = usage {
+            data["usage"] = usage;
+        }
+        Event::default().json_data(data).unwrap()
+    }
+
+    fn text(&self, text: &str, finish_reason: Option<&str>) -> Event {
+        let choice = json!({
+            "text": text,
+            "index": 0,
+            "logprobs": null,
+            "finish_reason": finish_reason,
+        });
+        self.chunk(json!([choice]), None)
+    }
+
+    fn process(&mut self, data: &str) -> Option<Event> {
+        match serde_json::from_str(data).ok()? {
+            StreamEvent::MessageStart { message } => {
+                self.id = message.id;
+                self.model = message.model;
+                if let Some(usage) = message.usage.filter(|u| u.input_tokens > 0) {
+                    self.usage.input_tokens = usage.input_tokens;
+                }
+                None
+            }
+            StreamEvent::ContentBlockDelta {
+                delta: ContentBlockDelta::TextDelta { text },
+                ..
+            } => {
+                if self.include_usage {
+                    self.completion.push_str(&text);
+                }
+                Some(self.text(&text, None))
+            }
+            StreamEvent::MessageDelta { delta, usage } => {
+                if let Some(usage) = usage.filter(|u| u.output_tokens > 0) {
+                    self.usage.output_tokens = usage.output_tokens;
+                }
+                Some(self.text("", Some(finish_reason(delta.stop_reason.as_ref()))))
+            }
+            StreamEvent::MessageStop if self.include_usage => {
+                if self.usage.output_tokens == 0 {
+                    let bpe = o200k_base().expect("Failed to get encoding");
+                    self.usage.output_tokens =
+                        bpe.encode_with_special_tokens(&self.completion).len() as u32;
+                }
+                Some(self.chunk(json!([]), Some(usage_json(&self.usage))))
+            }
+            StreamEvent::Error { error } => {
+                let data = json!({"error": {"message": error.message, "type": error.type_}});
+                Some(Event::default().json_data(data).unwrap())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Transforms a Claude event stream into an OpenAI `text_completion` stream
+///
+/// Text deltas are sent as completion chunks, followed by a chunk with the
+/// finish reason, a usage chunk if requested, and `[DONE]`.
+///
+/// # Arguments
+/// * `s` - The input stream of Claude events
+/// * `include_usage` - Whether to send a final usage chunk, from `stream_options`
+/// * `usage` - Token usage of the request, updated from the usage reported in the stream
+pub fn transform_completion_stream<I, E>(
+    s: I,
+    include_usage: bool,
+    usage: Usage,
+) -> impl Stream<Item = Result<Event, E>>
+where
+    I: Stream<Item = Result<eventsource_stream::Event, E>>,
+{
+    let mut state = CompletionStream {
+        id: String::new(),
+        model: String::new(),
+        created: created(),
+        include_usage,
+        usage,
+        completion: String::new(),
+    };
+    s.try_filter_map(move |eventsource_stream::Event { data, .. }| {
+        future::ready(Ok(state.process(&data)))
+    })
+    .chain(stream::once(future::ready(Ok(
+        Event::default().data("[DONE]")
+    ))))
+}
+
+/// Transforms a Claude event stream into a legacy Anthropic `completion` stream
+///
+/// Text deltas are sent as `completion` events, and the stop reason in a last
+/// `completion` event with empty text.
+pub fn transform_complete_stream<I, E>(s: I) -> impl Stream<Item = Result<Event, E>>
+where
+    I: Stream<Item = Result<eventsource_stream::Event, E>>,
+{
+    let (mut id, mut model) = (String::new(), String::new());
+    s.try_filter_map(move |eventsource_stream::Event { data, .. }| {
+        let completion = |id: &str, model: &str, text: &str, reason: Option<&str>, stop| {
+            let data = json!({
+                "type": "completion",
+                "id": id,
+                "completion": text,
+                "stop_reason": reason,
+                "stop": stop,
+                "model": model,
+            });
+            Event::default()
+                .event("completion")
+                .json_data(data)
+                .unwrap()
+        };
+        let event = match serde_json::from_str(&data) {
+            Ok(StreamEvent::MessageStart { message }) => {
+                (id, model) = (message.id, message.model);
+                None
+            }
+            Ok(StreamEvent::ContentBlockDelta {
+                delta: ContentBlockDelta::TextDelta { text },
+                ..
+            }) => Some(completion(&id, &model, &text, None, None)),
+            Ok(StreamEvent::MessageDelta { delta, .. }) => Some(completion(
+                &id,
+                &model,
+                "",
+                Some(legacy_stop_reason(delta.stop_reason.as_ref())),
+                delta.stop_sequence,
+            )),
+            Ok(StreamEvent::Ping) => {
+                Some(Event::default().event("ping").data(r#"{"type":"ping"}"#))
+            }
+            Ok(StreamEvent::Error { error }) => Some(
+                Event::default()
+                    .event("error")
+                    .json_data(json!({"type": "error", "error": error}))
+                    .unwrap(),
+            ),
+            _ => None,
+        };
+        future::ready(Ok(event))
+    })
+}
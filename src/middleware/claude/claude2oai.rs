@@ -1,37 +1,39 @@
+use std::collections::HashMap;
+
 use axum::response::sse::Event;
-use futures::{Stream, TryStreamExt};
+use futures::{Stream, StreamExt, TryStreamExt, future, stream};
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{Value, json};
+use tiktoken_rs::o200k_base;
 
-use crate::types::claude::{ContentBlockDelta, CreateMessageResponse, StreamEvent};
+use super::{RESPONSE_FORMAT_TOOL, validate_output};
+use crate::types::{
+    claude::{
+        ContentBlock, ContentBlockDelta, CreateMessageResponse, StopReason, StreamEvent, Usage,
+    },
+    oai::{ResponseFormat, tool_call_arguments},
+};
 
 /// Represents the data structure for streaming events in OpenAI API format
-/// Contains a choices array with deltas of content
+/// A `chat.completion.chunk` object, with a choices array with deltas of content
 #[derive(Debug, Serialize)]
-struct StreamEventData {
+struct StreamEventData<'a> {
+    id: &'a str,
+    object: &'static str,
+    created: u64,
+    model: &'a str,
     choices: Vec<StreamEventDelta>,
-}
-
-impl StreamEventData {
-    /// Creates a new StreamEventData with the given content
-    ///
-    /// # Arguments
-    /// * `content` - The event content to include
-    ///
-    /// # Returns
-    /// A new StreamEventData instance with the content wrapped in choices array
-    fn new(content: EventContent) -> Self {
-        Self {
-            choices: vec![StreamEventDelta { delta: content }],
-        }
-    }
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<Value>,
 }
 
 /// Represents a delta update in a streaming response
 /// Contains the content change for the current chunk
 #[derive(Debug, Serialize)]
 struct StreamEventDelta {
+    index: u32,
     delta: EventContent,
+    finish_reason: Option<&'static str>,
 }
 
 /// Content of an event, either regular content or reasoning (thinking mode)
@@ -39,31 +41,146 @@ struct StreamEventDelta {
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum EventContent {
+    Role { role: &'static str, content: String },
     Content { content: String },
     Reasoning { reasoning_content: String },
+    Annotations { annotations: Vec<Value> },
+    ToolCalls { tool_calls: Vec<Value> },
+    Empty {},
 }
 
-/// Creates an SSE event with the given content in OpenAI format
-///
-/// # Arguments
-/// * `content` - The event content to include
-///
-/// # Returns
-/// A formatted SSE Event ready to be sent to the client
-pub fn build_event(content: EventContent) -> Event {
-    let event = Event::default();
-    let data = StreamEventData::new(content);
-    event.json_data(data).unwrap()
+/// State carried across the events of a stream
+struct StreamState {
+    /// Id of the completion, taken from the Claude message
+    id: String,
+    /// Model of the completion, taken from the Claude message
+    model: String,
+    /// Unix timestamp of the start of the stream
+    created: u64,
+    /// Whether to send a final chunk with the token usage
+    include_usage: bool,
+    /// Token usage, updated from the usage reported in the stream
+    usage: Usage,
+    /// Generated text, to count output tokens when the stream reports none
+    completion: String,
+    /// Characters of content sent so far, to locate citations
+    offset: usize,
+    /// OpenAI tool call indices keyed by Claude content block index
+    tool_calls: HashMap<usize, usize>,
+    /// Format the output must follow, from `response_format`
+    response_format: Option<ResponseFormat>,
+    /// Claude content block index of the response format tool, streamed as content
+    format_block: Option<usize>,
+    /// Structured output sent so far, validated at the end of the message
+    output: String,
+}
+
+impl StreamState {
+    fn new(include_usage: bool, usage: Usage, response_format: Option<ResponseFormat>) -> Self {
+        Self {
+            id: String::new(),
+            model: String::new(),
+            created: created(),
+            include_usage,
+            usage,
+            completion: String::new(),
+            offset: 0,
+            tool_calls: HashMap::new(),
+            response_format,
+            format_block: None,
+            output: String::new(),
+        }
+    }
+
+    /// Records content for the usage chunk and the response format validation
+    fn record_content(&mut self, text: &str) {
+        self.record(text);
+        if self.response_format.is_some() {
+            self.output.push_str(text);
+        }
+    }
+
+    /// Validates the structured output, unless the model is calling client tools
+    ///
+    /// # Returns
+    /// An error event if the output does not match the response format
+    fn validate(&self) -> Option<Event> {
+        let format = self.response_format.as_ref()?;
+        if !self.tool_calls.is_empty() {
+            return None;
+        }
+        let err = validate_output(format, None, &self.output).err()?;
+        let data = json!({
+            "error": {
+                "message": err.to_string(),
+                "type": "invalid_response_format",
+            }
+        });
+        Some(Event::default().json_data(data).unwrap())
+    }
+
+    /// Creates an SSE event in OpenAI format
+    ///
+    /// # Arguments
+    /// * `content` - The event content to include
+    /// * `finish_reason` - The finish reason, for the last chunk of the choice
+    ///
+    /// # Returns
+    /// A formatted SSE Event ready to be sent to the client
+    fn event(&self, content: EventContent, finish_reason: Option<&'static str>) -> Event {
+        self.chunk(
+            vec![StreamEventDelta {
+                index: 0,
+                delta: content,
+                finish_reason,
+            }],
+            None,
+        )
+    }
+
+    /// Records generated text for the usage chunk
+    fn record(&mut self, text: &str) {
+        if self.include_usage {
+            self.completion.push_str(text);
+        }
+    }
+
+    /// Creates the usage chunk sent after the last choice, with empty choices
+    fn usage_event(&mut self) -> Event {
+        if self.usage.output_tokens == 0 {
+            let bpe = o200k_base().expect("Failed to get encoding");
+            self.usage.output_tokens =
+                bpe.encode_with_special_tokens(&self.completion).len() as u32;
+        }
+        self.chunk(vec![], Some(usage_json(&self.usage)))
+    }
+
+    fn chunk(&self, choices: Vec<StreamEventDelta>, usage: Option<Value>) -> Event {
+        let data = StreamEventData {
+            id: &self.id,
+            object: "chat.completion.chunk",
+            created: self.created,
+            model: &self.model,
+            choices,
+            usage,
+        };
+        Event::default().json_data(data).unwrap()
+    }
 }
 
 /// Transforms a Claude.ai event stream into an OpenAI-compatible event stream
 ///
 /// Extracts content from Claude events and reformats them to match OpenAI's streaming format.
 /// This function processes each event in the stream, identifying the delta content type
-/// (text or thinking), and converting it to the appropriate OpenAI-compatible event format.
+/// (text, thinking or tool call), and converting it to the appropriate OpenAI-compatible event format.
+/// The stop reason of the message is sent as `finish_reason` in a final event,
+/// followed by a usage chunk if requested, and the stream is terminated with `[DONE]`.
 ///
 /// # Arguments
 /// * `s` - The input stream of Claude.ai events
+/// * `include_usage` - Whether to send a final usage chunk, from `stream_options`
+/// * `usage` - Token usage of the request, updated from the usage reported in the stream
+/// * `response_format` - Format the output must follow, sent as content and validated at the end
 ///
 /// # Returns
 /// A stream of OpenAI-compatible SSE events
@@ -71,73 +188,251 @@ pub fn build_event(content: EventContent) -> Event {
 /// # Type Parameters
 /// * `I` - The input stream type
 /// * `E` - The error type for the stream
-pub fn transform_stream<I, E>(s: I) -> impl Stream<Item = Result<Event, E>>
+pub fn transform_stream<I, E>(
+    s: I,
+    include_usage: bool,
+    usage: Usage,
+    response_format: Option<ResponseFormat>,
+) -> impl Stream<Item = Result<Event, E>>
 where
     I: Stream<Item = Result<eventsource_stream::Event, E>>,
 {
-    s.try_filter_map(async |eventsource_stream::Event { data, .. }| {
-        let Ok(parsed) = serde_json::from_str::<StreamEvent>(&data) else {
-            return Ok(None);
-        };
-        let StreamEvent::ContentBlockDelta { delta, .. } = parsed else {
-            return Ok(None);
-        };
-        match delta {
-            ContentBlockDelta::TextDelta { text } => {
-                Ok(Some(build_event(EventContent::Content { content: text })))
+    let mut state = StreamState::new(include_usage, usage, response_format);
+    s.try_filter_map(move |eventsource_stream::Event { data, .. }| {
+        future::ready(Ok(transform_event(&data, &mut state)))
+    })
+    .chain(stream::once(future::ready(Ok(
+        Event::default().data("[DONE]")
+    ))))
+}
+
+/// Converts a single Claude event into an OpenAI event
+///
+/// # Arguments
+/// * `data` - Data of the Claude event
+/// * `state` - State of the stream, such as the tool calls started so far
+fn transform_event(data: &str, state: &mut StreamState) -> Option<Event> {
+    let (index, delta) = match serde_json::from_str(data).ok()? {
+        StreamEvent::ContentBlockDelta { index, delta } => (index, delta),
+        StreamEvent::MessageStart { message } => {
+            if !message.id.is_empty() {
+                state.id = message.id;
             }
-            ContentBlockDelta::ThinkingDelta { thinking } => {
-                Ok(Some(build_event(EventContent::Reasoning {
-                    reasoning_content: thinking,
-                })))
+            state.model = message.model;
+            if let Some(usage) = message.usage.filter(|u| u.input_tokens > 0) {
+                state.usage.input_tokens = usage.input_tokens;
+            }
+            return Some(state.event(
+                EventContent::Role {
+                    role: "assistant",
+                    content: String::new(),
+                },
+                None,
+            ));
+        }
+        StreamEvent::ContentBlockStart {
+            index,
+            content_block: ContentBlock::ToolUse { name, input, .. },
+        } if name == RESPONSE_FORMAT_TOOL => {
+            // the structured output is the answer, not a tool call of the client
+            state.format_block = Some(index);
+            let Value::Object(ref o) = input else {
+                return None;
+            };
+            if o.is_empty() {
+                return None;
             }
-            _ => Ok(None),
+            let content = input.to_string();
+            state.record_content(&content);
+            return Some(state.event(EventContent::Content { content }, None));
+        }
+        StreamEvent::ContentBlockStart {
+            index,
+            content_block: ContentBlock::ToolUse { id, name, input },
+        } => {
+            let tool_index = state.tool_calls.len();
+            state.tool_calls.insert(index, tool_index);
+            // input is streamed as deltas, unless the block carries it already
+            let arguments = match input {
+                Value::Object(ref o) if !o.is_empty() => tool_call_arguments(&input),
+                _ => String::new(),
+            };
+            return Some(state.event(
+                EventContent::ToolCalls {
+                    tool_calls: vec![json!({
+                        "index": tool_index,
+                        "id": id,
+                        "type": "function",
+                        "function": {"name": name, "arguments": arguments},
+                    })],
+                },
+                None,
+            ));
+        }
+        StreamEvent::MessageDelta { delta, usage } => {
+            if let Some(usage) = usage.filter(|u| u.output_tokens > 0) {
+                state.usage.output_tokens = usage.output_tokens;
+            }
+            if let Some(error) = state.validate() {
+                return Some(error);
+            }
+            let reason = match delta.stop_reason {
+                Some(StopReason::ToolUse) if state.tool_calls.is_empty() => "stop",
+                ref r => finish_reason(r.as_ref()),
+            };
+            return Some(state.event(EventContent::Empty {}, Some(reason)));
+        }
+        StreamEvent::MessageStop if state.include_usage => {
+            return Some(state.usage_event());
+        }
+        _ => return None,
+    };
+    match delta {
+        ContentBlockDelta::InputJsonDelta { partial_json } if state.format_block == Some(index) => {
+            state.record_content(&partial_json);
+            Some(state.event(
+                EventContent::Content {
+                    content: partial_json,
+                },
+                None,
+            ))
+        }
+        ContentBlockDelta::InputJsonDelta { partial_json } => {
+            let tool_index = *state.tool_calls.get(&index)?;
+            state.record(&partial_json);
+            Some(state.event(
+                EventContent::ToolCalls {
+                    tool_calls: vec![json!({
+                        "index": tool_index,
+                        "function": {"arguments": partial_json},
+                    })],
+                },
+                None,
+            ))
+        }
+        ContentBlockDelta::TextDelta { text } => {
+            state.offset += text.chars().count();
+            state.record_content(&text);
+            Some(state.event(EventContent::Content { content: text }, None))
+        }
+        ContentBlockDelta::ThinkingDelta { thinking } => {
+            state.record(&thinking);
+            Some(state.event(
+                EventContent::Reasoning {
+                    reasoning_content: thinking,
+                },
+                None,
+            ))
+        }
+        ContentBlockDelta::CitationsDelta { citation } => {
+            let cited = citation["cited_text"].as_str().unwrap_or_default();
+            let start = state.offset.saturating_sub(cited.chars().count());
+            Some(state.event(
+                EventContent::Annotations {
+                    annotations: vec![url_citation(&citation, start, state.offset)],
+                },
+                None,
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Maps a Claude stop reason to an OpenAI finish reason
+pub(super) fn finish_reason(stop_reason: Option<&StopReason>) -> &'static str {
+    match stop_reason {
+        Some(StopReason::EndTurn) | Some(StopReason::StopSequence) | None => "stop",
+        Some(StopReason::MaxTokens) => "length",
+        Some(StopReason::ToolUse) => "tool_calls",
+        Some(StopReason::Refusal) => "content_filter",
+    }
+}
+
+/// Converts Claude token usage into OpenAI usage
+pub(super) fn usage_json(usage: &Usage) -> Value {
+    json!({
+        "prompt_tokens": usage.input_tokens,
+        "completion_tokens": usage.output_tokens,
+        "total_tokens": usage.input_tokens + usage.output_tokens
+    })
+}
+
+/// Current Unix timestamp, for the `created` field of completions
+pub(super) fn created() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Converts an Anthropic citation into an OpenAI `url_citation` annotation
+///
+/// # Arguments
+/// * `citation` - The citation of a text block
+/// * `start` - Character index in the content where the cited text starts
+/// * `end` - Character index in the content where the cited text ends
+fn url_citation(citation: &Value, start: usize, end: usize) -> Value {
+    json!({
+        "type": "url_citation",
+        "url_citation": {
+            "url": citation["url"],
+            "title": citation["title"],
+            "start_index": start,
+            "end_index": end,
         }
     })
 }
 
 pub fn transforms_json(input: CreateMessageResponse) -> Value {
-    let content = input
-        .content
-        .iter()
-        .filter_map(|block| match block {
-            crate::types::claude::ContentBlock::Text { text } => Some(text.clone()),
-            _ => None,
-        })
-        .collect::<Vec<_>>()
-        .join("");
-
-    let usage = input.usage.as_ref().map(|u| {
-        serde_json::json!({
-            "prompt_tokens": u.input_tokens,
-            "completion_tokens": u.output_tokens,
-            "total_tokens": u.input_tokens + u.output_tokens
-        })
-    });
+    let mut content = String::new();
+    let mut annotations = vec![];
+    let mut tool_calls = vec![];
+    for block in input.content.iter() {
+        let (text, citations) = match block {
+            ContentBlock::Text { text, citations } => (text, citations),
+            ContentBlock::ToolUse { id, name, input } => {
+                tool_calls.push(json!({
+                    "id": id,
+                    "type": "function",
+                    "function": {"name": name, "arguments": tool_call_arguments(input)},
+                }));
+                continue;
+            }
+            _ => continue,
+        };
+        let offset = content.chars().count();
+        for citation in citations.iter().flatten() {
+            // locate the cited text in the block, falling back to the whole block
+            let cited = citation["cited_text"].as_str().unwrap_or_default();
+            let (start, len) = match text.find(cited).filter(|_| !cited.is_empty()) {
+                Some(i) => (text[..i].chars().count(), cited.chars().count()),
+                None => (0, text.chars().count()),
+            };
+            annotations.push(url_citation(citation, offset + start, offset + start + len));
+        }
+        content += text;
+    }
 
-    let finish_reason = match input.stop_reason {
-        Some(crate::types::claude::StopReason::EndTurn) => "stop",
-        Some(crate::types::claude::StopReason::MaxTokens) => "length",
-        Some(crate::types::claude::StopReason::StopSequence) => "stop",
-        Some(crate::types::claude::StopReason::ToolUse) => "tool_calls",
-        Some(crate::types::claude::StopReason::Refusal) => "content_filter",
-        None => "stop",
-    };
+    let usage = input.usage.as_ref().map(usage_json);
+
+    let finish_reason = finish_reason(input.stop_reason.as_ref());
+    let mut message = json!({
+        "role": "assistant",
+        "content": content,
+        "annotations": annotations
+    });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = json!(tool_calls);
+    }
 
     serde_json::json!({
         "id": input.id,
         "object": "chat.completion",
-        "created": std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs(),
+        "created": created(),
         "model": input.model,
         "choices": [{
             "index": 0,
-            "message": {
-                "role": "assistant",
-                "content": content
-            },
+            "message": message,
             "finish_reason": finish_reason
         }],
         "usage": usage
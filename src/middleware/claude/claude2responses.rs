@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+
+use axum::response::sse::Event;
+use futures::{Stream, TryStreamExt, stream};
+use serde_json::{Value, json};
+use tiktoken_rs::o200k_base;
+use uuid::Uuid;
+
+use crate::types::{
+    claude::{
+        ContentBlock, ContentBlockDelta, CreateMessageResponse, StopReason, StreamEvent, Usage,
+    },
+    oai::tool_call_arguments,
+};
+
+/// Creates an id for a Responses API object, such as `msg_...` for a message item
+fn new_id(prefix: &str) -> String {
+    format!("{prefix}_{}", Uuid::new_v4().simple())
+}
+
+/// Current Unix timestamp, for the `created_at` field of responses
+fn created_at() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Builds an output message item holding a single `output_text` part
+fn message_item(id: &str, text: &str, status: &str) -> Value {
+    json!({
+        "type": "message",
+        "id": id,
+        "status": status,
+        "role": "assistant",
+        "content": [{"type": "output_text", "text": text, "annotations": []}],
+    })
+}
+
+/// Builds a reasoning item whose summary is the thinking of Claude
+///
+/// The thinking signature is carried as encrypted content, so the client can send
+/// the thinking back before the tool uses of the next turn
+fn reasoning_item(id: &str, text: &str, signature: &str) -> Value {
+    json!({
+        "type": "reasoning",
+        "id": id,
+        "summary": [{"type": "summary_text", "text": text}],
+        "encrypted_content": signature,
+    })
+}
+
+/// Builds a function call item
+fn function_call_item(id: &str, call_id: &str, name: &str, arguments: &str, status: &str) -> Value {
+    json!({
+        "type": "function_call",
+        "id": id,
+        "call_id": call_id,
+        "name": name,
+        "arguments": arguments,
+        "status": status,
+    })
+}
+
+/// Builds a Responses API `response` object
+///
+/// # Arguments
+/// * `id` - Id of the response
+/// * `model` - Model that handled the request
+/// * `created_at` - Unix timestamp of the creation of the response
+/// * `status` - `in_progress`, `completed` or `incomplete`
+/// * `output` - Output items generated so far
+/// * `usage` - Token usage, `None` while in progress
+fn response_object(
+    id: &str,
+    model: &str,
+    created_at: u64,
+    status: &str,
+    output: &[Value],
+    usage: Option<&Usage>,
+) -> Value {
+    let incomplete_details =
+        (status == "incomplete").then(|| json!({"reason": "max_output_tokens"}));
+    json!({
+        "id": id,
+        "object": "response",
+        "created_at": created_at,
+        "status": status,
+        "error": null,
+        "incomplete_details": incomplete_details,
+        "model": model,
+        "output": output,
+        "usage": usage.map(|u| json!({
+            "input_tokens": u.input_tokens,
+            "output_tokens": u.output_tokens,
+            "total_tokens": u.input_tokens + u.output_tokens,
+        })),
+    })
+}
+
+/// Maps a Claude stop reason to the status of a response
+fn status(stop_reason: Option<&StopReason>) -> &'static str {
+    match stop_reason {
+        Some(StopReason::MaxTokens) => "incomplete",
+        _ => "completed",
+    }
+}
+
+/// Converts a Claude message into an OpenAI Responses API object
+///
+/// Text blocks become message items, thinking blocks become reasoning items
+/// with the thinking as summary and the signature as encrypted content, and
+/// tool uses become function call items.
+pub fn transforms_responses_json(input: CreateMessageResponse) -> Value {
+    let output = input
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text, .. } => {
+                Some(message_item(&new_id("msg"), text, "completed"))
+            }
+            ContentBlock::Thinking {
+                thinking,
+                signature,
+            } => Some(reasoning_item(&new_id("rs"), thinking, signature)),
+            ContentBlock::ToolUse { id, name, input } => Some(function_call_item(
+                &new_id("fc"),
+                id,
+                name,
+                &tool_call_arguments(input),
+                "completed",
+            )),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    response_object(
+        &format!("resp_{}", input.id),
+        &input.model,
+        created_at(),
+        status(input.stop_reason.as_ref()),
+        &output,
+        input.usage.as_ref(),
+    )
+}
+
+/// State of a Claude stream being converted into Responses API events
+struct ResponsesStream {
+    id: String,
+    model: String,
+    created_at: u64,
+    /// Token usage, updated from the usage reported in the stream
+    usage: Usage,
+    /// Generated text, to count output tokens when the stream reports none
+    completion: String,
+    /// Sequence number of the next event
+    sequence: u64,
+    /// Output items, updated as their content streams in
+    output: Vec<Value>,
+    /// Output indices keyed by Claude content block index
+    items: HashMap<usize, usize>,
+    stop_reason: Option<StopReason>,
+}
+
+impl ResponsesStream {
+    fn new(usage: Usage) -> Self {
+        Self {
+            id: new_id("resp"),
+            model: String::new(),
+            created_at: created_at(),
+            usage,
+            completion: String::new(),
+            sequence: 0,
+            output: vec![],
+            items: HashMap::new(),
+            stop_reason: None,
+        }
+    }
+
+    /// Creates an SSE event of the given type, numbered in sequence
+    fn event(&mut self, kind: &str, mut data: Value) -> Event {
+        data["type"] = json!(kind);
+        data["sequence_number"] = json!(self.sequence);
+        self.sequence += 1;
+        Event::default().event(kind).json_data(data).unwrap()
+    }
+
+    fn response(&self, status: &str) -> Value {
+        let usage = (status != "in_progress").then_some(&self.usage);
+        response_object(
+            &self.id,
+            &self.model,
+            self.created_at,
+            status,
+            &self.output,
+            usage,
+        )
+    }
+
+    /// Converts a single Claude event into Responses API events
+    fn process(&mut self, data: &str) -> Vec<Event> {
+        let Ok(event) = serde_json::from_str::<StreamEvent>(data) else {
+            return vec![];
+        };
+        match event {
+            StreamEvent::MessageStart { message } => {
+                if !message.id.is_empty() {
+                    self.id = format!("resp_{}", message.id);
+                }
+                self.model = message.model;
+                if let Some(usage) = message.usage.filter(|u| u.input_tokens > 0) {
+                    self.usage.input_tokens = usage.input_tokens;
+                }
+                let response = self.response("in_progress");
+                vec![
+                    self.event("response.created", json!({ "response": response })),
+                    self.event("response.in_progress", json!({ "response": response })),
+                ]
+            }
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => self.start_item(index, content_block),
+            StreamEvent::ContentBlockDelta { index, delta } => self.delta(index, delta),
+            StreamEvent::ContentBlockStop { index } => self.stop_item(index),
+            StreamEvent::MessageDelta { delta, usage } => {
+                if let Some(usage) = usage.filter(|u| u.output_tokens > 0) {
+                    self.usage.output_tokens = usage.output_tokens;
+                }
+                self.stop_reason = delta.stop_reason;
+                vec![]
+            }
+            StreamEvent::MessageStop => {
+                if self.usage.output_tokens == 0 {
+                    let bpe = o200k_base().expect("Failed to get encoding");
+                    self.usage.output_tokens =
+                        bpe.encode_with_special_tokens(&self.completion).len() as u32;
+                }
+                let status = status(self.stop_reason.as_ref());
+                let response = self.response(status);
+                vec![self.event(
+                    &format!("response.{status}"),
+                    json!({ "response": response }),
+                )]
+            }
+            StreamEvent::Error { error } => vec![self.event(
+                "error",
+                json!({"code": error.type_, "message": error.message, "param": null}),
+            )],
+            StreamEvent::Ping => vec![],
+        }
+    }
+
+    /// Opens the output item of a content block
+    fn start_item(&mut self, index: usize, block: ContentBlock) -> Vec<Event> {
+        let output_index = self.output.len();
+        let (item, part) = match block {
+            ContentBlock::Text { .. } => {
+                let id = new_id("msg");
+                let part = json!({
+                    "item_id": id,
+                    "output_index": output_index,
+                    "content_index": 0,
+                    "part": {"type": "output_text", "text": "", "annotations": []},
+                });
+                (
+                    message_item(&id, "", "in_progress"),
+                    Some(("response.content_part.added", part)),
+                )
+            }
+            ContentBlock::Thinking { signature, .. } => {
+                let id = new_id("rs");
+                let part = json!({
+                    "item_id": id,
+                    "output_index": output_index,
+                    "summary_index": 0,
+                    "part": {"type": "summary_text", "text": ""},
+                });
+                (
+                    reasoning_item(&id, "", &signature),
+                    Some(("response.reasoning_summary_part.added", part)),
+                )
+            }
+            ContentBlock::ToolUse { id, name, input } => {
+                // input is streamed as deltas, unless the block carries it already
+                let arguments = match input {
+                    Value::Object(ref o) if !o.is_empty() => tool_call_arguments(&input),
+                    _ => String::new(),
+                };
+                let item = function_call_item(&new_id("fc"), &id, &name, &arguments, "in_progress");
+                (item, None)
+            }
+            _ => return vec![],
+        };
+        // the item is announced empty, its content is filled in by the deltas
+        let mut added = item.to_owned();
+        match added["type"].as_str() {
+            Some("message") => added["content"] = json!([]),
+            Some("reasoning") => added["summary"] = json!([]),
+            _ => {}
+        }
+        self.items.insert(index, output_index);
+        self.output.push(item);
+        let mut events = vec![self.event(
+            "response.output_item.added",
+            json!({"output_index": output_index, "item": added}),
+        )];
+        if let Some((kind, part)) = part {
+            events.push(self.event(kind, part));
+        }
+        events
+    }
+
+    /// Appends a delta to its output item
+    fn delta(&mut self, index: usize, delta: ContentBlockDelta) -> Vec<Event> {
+        let Some(&output_index) = self.items.get(&index) else {
+            return vec![];
+        };
+        if let ContentBlockDelta::SignatureDelta { signature } = delta {
+            // the signature has no event of its own, it is sent with the done item
+            if let Some(Value::String(content)) =
+                self.output[output_index].get_mut("encrypted_content")
+            {
+                content.push_str(&signature);
+            }
+            return vec![];
+        }
+        let (pointer, kind, text) = match delta {
+            ContentBlockDelta::TextDelta { text } => {
+                ("/content/0/text", "response.output_text.delta", text)
+            }
+            ContentBlockDelta::ThinkingDelta { thinking } => (
+                "/summary/0/text",
+                "response.reasoning_summary_text.delta",
+                thinking,
+            ),
+            ContentBlockDelta::InputJsonDelta { partial_json } => (
+                "/arguments",
+                "response.function_call_arguments.delta",
+                partial_json,
+            ),
+            _ => return vec![],
+        };
+        let item = &mut self.output[output_index];
+        let Some(Value::String(content)) = item.pointer_mut(pointer) else {
+            return vec![];
+        };
+        content.push_str(&text);
+        let mut data = json!({
+            "item_id": item["id"],
+            "output_index": output_index,
+            "delta": text,
+        });
+        match item["type"].as_str() {
+            Some("message") => data["content_index"] = json!(0),
+            Some("reasoning") => data["summary_index"] = json!(0),
+            _ => {}
+        }
+        self.completion.push_str(&text);
+        vec![self.event(kind, data)]
+    }
+
+    /// Completes the output item of a content block
+    fn stop_item(&mut self, index: usize) -> Vec<Event> {
+        let Some(output_index) = self.items.remove(&index) else {
+            return vec![];
+        };
+        let item = &mut self.output[output_index];
+        if item.get("status").is_some() {
+            item["status"] = json!("completed");
+        }
+        let item = item.to_owned();
+        let base = json!({"item_id": item["id"], "output_index": output_index});
+        let with = |extra: Value| {
+            let mut data = base.to_owned();
+            if let (Some(data), Value::Object(extra)) = (data.as_object_mut(), extra) {
+                data.extend(extra);
+            }
+            data
+        };
+        let mut events = match item["type"].as_str() {
+            Some("message") => {
+                let part = item["content"][0].to_owned();
+                vec![
+                    self.event(
+                        "response.output_text.done",
+                        with(json!({"content_index": 0, "text": part["text"]})),
+                    ),
+                    self.event(
+                        "response.content_part.done",
+                        with(json!({"content_index": 0, "part": part})),
+                    ),
+                ]
+            }
+            Some("reasoning") => {
+                let part = item["summary"][0].to_owned();
+                vec![
+                    self.event(
+                        "response.reasoning_summary_text.done",
+                        with(json!({"summary_index": 0, "text": part["text"]})),
+                    ),
+                    self.event(
+                        "response.reasoning_summary_part.done",
+                        with(json!({"summary_index": 0, "part": part})),
+                    ),
+                ]
+            }
+            _ => vec![self.event(
+                "response.function_call_arguments.done",
+                with(json!({"arguments": item["arguments"]})),
+            )],
+        };
+        events.push(self.event(
+            "response.output_item.done",
+            json!({"output_index": output_index, "item": item}),
+        ));
+        events
+    }
+}
+
+/// Transforms a Claude event stream into an OpenAI Responses API event stream
+///
+/// Emits `response.created` on start, output item, content part and delta events
+/// for text, thinking and tool use blocks, and `response.completed` at the end.
+///
+/// # Arguments
+/// * `s` - The input stream of Claude events
+/// * `usage` - Token usage of the request, updated from the usage reported in the stream
+///
+/// # Returns
+/// A stream of Responses API SSE events
+pub fn transform_responses_stream<I, E>(s: I, usage: Usage) -> impl Stream<Item = Result<Event, E>>
+where
+    I: Stream<Item = Result<eventsource_stream::Event, E>>,
+{
+    let mut state = ResponsesStream::new(usage);
+    s.map_ok(move |eventsource_stream::Event { data, .. }| {
+        stream::iter(state.process(&data).into_iter().map(Ok::<_, E>))
+    })
+    .try_flatten()
+}
@@ -1,15 +1,23 @@
+mod choices;
+mod claude2completions;
 mod claude2oai;
+mod claude2responses;
 mod request;
 mod response;
 mod stop_sequences;
+mod structured;
 
+pub use choices::*;
+pub(crate) use claude2completions::*;
 pub(crate) use claude2oai::*;
+pub(crate) use claude2responses::*;
 pub use request::*;
 pub use response::*;
 pub use stop_sequences::*;
+pub use structured::*;
 use strum::Display;
 
-use crate::types::claude::Usage;
+use crate::types::{claude::Usage, oai::ResponseFormat};
 
 /// Represents the format of the API response
 ///
@@ -22,6 +30,12 @@ pub enum ClaudeApiFormat {
     Claude,
     /// OpenAI compatible format
     OpenAI,
+    /// OpenAI Responses API format
+    Responses,
+    /// OpenAI legacy text completions format
+    Completions,
+    /// Anthropic legacy text completions format
+    Complete,
 }
 
 #[derive(Debug, Clone)]
@@ -67,10 +81,46 @@ impl ClaudeContext {
         }
     }
 
+    pub fn anthropic_beta(&self) -> &[String] {
+        match self {
+            ClaudeContext::Web(_) => &[],
+            ClaudeContext::Code(ctx) => &ctx.anthropic_beta,
+        }
+    }
+
     pub fn usage(&self) -> &Usage {
         match self {
             ClaudeContext::Web(ctx) => &ctx.usage,
             ClaudeContext::Code(ctx) => &ctx.usage,
         }
     }
+
+    pub fn include_usage(&self) -> bool {
+        match self {
+            ClaudeContext::Web(ctx) => ctx.include_usage,
+            ClaudeContext::Code(ctx) => ctx.include_usage,
+        }
+    }
+
+    pub fn response_format(&self) -> Option<&ResponseFormat> {
+        match self {
+            ClaudeContext::Web(ctx) => ctx.response_format.as_ref(),
+            ClaudeContext::Code(ctx) => ctx.response_format.as_ref(),
+        }
+    }
+
+    pub fn choices(&self) -> u32 {
+        match self {
+            ClaudeContext::Web(ctx) => ctx.choices,
+            ClaudeContext::Code(ctx) => ctx.choices,
+        }
+    }
+
+    /// Identifies the client of a web request, to keep its sessions apart from others
+    pub fn client(&self) -> Option<u64> {
+        match self {
+            ClaudeContext::Web(ctx) => Some(ctx.client),
+            ClaudeContext::Code(_) => None,
+        }
+    }
 }
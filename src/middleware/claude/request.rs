@@ -7,16 +7,21 @@ use std::{
 use axum::{
     Json,
     extract::{FromRequest, Request},
+    http::header::AUTHORIZATION,
 };
 use serde_json::{Value, json};
 
 use crate::{
     config::CLEWDR_CONFIG,
     error::ClewdrError,
-    middleware::claude::{ClaudeApiFormat, ClaudeContext},
+    middleware::claude::{
+        ClaudeApiFormat, ClaudeContext, add_format_instruction, force_response_tool,
+    },
     types::{
         claude::{ContentBlock, CreateMessageParams, Message, Role, Thinking, Usage},
-        oai::CreateMessageParams as OaiCreateMessageParams,
+        completions::{CreateCompleteParams, CreateCompletionParams, StringOrList},
+        oai::{CreateMessageParams as OaiCreateMessageParams, ResponseFormat},
+        responses::CreateResponseParams,
     },
 };
 
@@ -51,6 +56,14 @@ pub struct ClaudeWebContext {
     pub(super) stop_sequences: Vec<String>,
     /// User information about input and output tokens
     pub(super) usage: Usage,
+    /// Whether to send a usage chunk at the end of an OpenAI stream
+    pub(super) include_usage: bool,
+    /// Format the output must follow, validated after generation
+    pub(super) response_format: Option<ResponseFormat>,
+    /// Number of choices to generate, each by a separate upstream request
+    pub(super) choices: u32,
+    /// Hash of the API key and the user id of the client
+    pub(super) client: u64,
 }
 
 /// Predefined test message in Claude format for connection testing
@@ -58,19 +71,26 @@ pub struct ClaudeWebContext {
 /// This is a standard test message sent by clients like SillyTavern
 /// to verify connectivity. The system detects these messages and
 /// responds with a predefined test response to confirm service availability.
-static TEST_MESSAGE_CLAUDE: LazyLock<Message> = LazyLock::new(|| {
-    Message::new_blocks(
-        Role::User,
-        vec![ContentBlock::Text {
-            text: "Hi".to_string(),
-        }],
-    )
-});
+static TEST_MESSAGE_CLAUDE: LazyLock<Message> =
+    LazyLock::new(|| Message::new_blocks(Role::User, vec![ContentBlock::text("Hi")]));
 
 /// Predefined test message in OpenAI format for connection testing
 static TEST_MESSAGE_OAI: LazyLock<Message> = LazyLock::new(|| Message::new_text(Role::User, "Hi"));
 
-struct NormalizeRequest(CreateMessageParams, ClaudeApiFormat);
+/// A request normalized to Claude format, with the format it was sent in
+struct NormalizeRequest {
+    body: CreateMessageParams,
+    format: ClaudeApiFormat,
+    /// Whether an OpenAI client asked for a usage chunk at the end of the stream
+    include_usage: bool,
+    /// Format an OpenAI client requires the output to follow
+    response_format: Option<ResponseFormat>,
+    /// Number of choices an OpenAI client asked for
+    choices: u32,
+}
+
+/// Maximum number of choices of a request, each one is a separate upstream request
+const MAX_CHOICES: u32 = 8;
 
 impl<S> FromRequest<S> for NormalizeRequest
 where
@@ -82,21 +102,74 @@ where
         let uri = req.uri().to_string();
         let format = if uri.contains("chat/completions") {
             ClaudeApiFormat::OpenAI
+        } else if uri.contains("/responses") {
+            ClaudeApiFormat::Responses
+        } else if uri.contains("/completions") {
+            ClaudeApiFormat::Completions
+        } else if uri.contains("/complete") {
+            ClaudeApiFormat::Complete
         } else {
             ClaudeApiFormat::Claude
         };
-        let Json(mut body) = match format {
+        let (Json(mut body), include_usage, response_format) = match format {
             ClaudeApiFormat::OpenAI => {
                 let Json(json) = Json::<OaiCreateMessageParams>::from_request(req, &()).await?;
-                Json(json.into())
+                let include_usage = json
+                    .stream_options
+                    .as_ref()
+                    .is_some_and(|o| o.include_usage);
+                let response_format = json.response_format.to_owned();
+                (Json(json.into()), include_usage, response_format)
             }
-            ClaudeApiFormat::Claude => Json::<CreateMessageParams>::from_request(req, &()).await?,
+            ClaudeApiFormat::Responses => {
+                let Json(json) = Json::<CreateResponseParams>::from_request(req, &()).await?;
+                (Json(json.into()), false, None)
+            }
+            ClaudeApiFormat::Completions => {
+                let Json(json) = Json::<CreateCompletionParams>::from_request(req, &()).await?;
+                if matches!(json.prompt, StringOrList::Many(ref p) if p.len() > 1) {
+                    return Err(ClewdrError::BadRequest {
+                        msg: "Only one prompt per completion request is supported",
+                    });
+                }
+                let include_usage = json
+                    .stream_options
+                    .as_ref()
+                    .is_some_and(|o| o.include_usage);
+                (Json(json.into()), include_usage, None)
+            }
+            ClaudeApiFormat::Complete => {
+                let Json(json) = Json::<CreateCompleteParams>::from_request(req, &()).await?;
+                (Json(json.into()), false, None)
+            }
+            ClaudeApiFormat::Claude => (
+                Json::<CreateMessageParams>::from_request(req, &()).await?,
+                false,
+                None,
+            ),
         };
         if body.model.ends_with("-thinking") {
             body.model = body.model.trim_end_matches("-thinking").to_string();
             body.thinking.get_or_insert(Thinking::new(4096));
         }
-        Ok(Self(body, format))
+        // Claude has no `n`, choices are generated by separate requests
+        let choices = body.n.take().unwrap_or(1).max(1);
+        let choices = match format {
+            ClaudeApiFormat::OpenAI | ClaudeApiFormat::Completions => choices,
+            _ => 1,
+        };
+        if choices > MAX_CHOICES {
+            return Err(ClewdrError::BadRequest {
+                msg: "n must not be greater than 8",
+            });
+        }
+        Ok(Self {
+            body,
+            format,
+            include_usage,
+            response_format,
+            choices,
+        })
     }
 }
 
@@ -107,7 +180,20 @@ where
     type Rejection = ClewdrError;
 
     async fn from_request(req: Request, _: &S) -> Result<Self, Self::Rejection> {
-        let NormalizeRequest(body, format) = NormalizeRequest::from_request(req, &()).await?;
+        // Identify the client before the body consumes the request
+        let api_key = req
+            .headers()
+            .get("x-api-key")
+            .or_else(|| req.headers().get(AUTHORIZATION))
+            .and_then(|v| v.to_str().ok())
+            .map(ToOwned::to_owned);
+        let NormalizeRequest {
+            mut body,
+            format,
+            include_usage,
+            response_format,
+            choices,
+        } = NormalizeRequest::from_request(req, &()).await?;
 
         // Check for test messages and respond appropriately
         if !body.stream.unwrap_or_default()
@@ -121,6 +207,19 @@ where
         // Determine streaming status and API format
         let stream = body.stream.unwrap_or_default();
 
+        let mut hasher = DefaultHasher::new();
+        api_key.hash(&mut hasher);
+        body.metadata
+            .as_ref()
+            .and_then(|m| m.fields.get("user_id"))
+            .hash(&mut hasher);
+        let client = hasher.finish();
+
+        // claude.ai has no forced tool use, so the output is validated afterwards
+        if let Some(ref f) = response_format {
+            add_format_instruction(&mut body, f);
+        }
+
         let input_tokens = body.count_tokens();
         let info = ClaudeWebContext {
             stream,
@@ -130,6 +229,10 @@ where
                 input_tokens,
                 output_tokens: 0, // Placeholder for output token count
             },
+            include_usage,
+            response_format,
+            choices,
+            client,
         };
 
         Ok(Self(body, ClaudeContext::Web(info)))
@@ -146,6 +249,14 @@ pub struct ClaudeCodeContext {
     pub(super) system_prompt_hash: Option<u64>,
     // Usage information for the request
     pub(super) usage: Usage,
+    /// Beta flags sent by the client in the `anthropic-beta` header
+    pub(super) anthropic_beta: Vec<String>,
+    /// Whether to send a usage chunk at the end of an OpenAI stream
+    pub(super) include_usage: bool,
+    /// Format the output must follow, validated after generation
+    pub(super) response_format: Option<ResponseFormat>,
+    /// Number of choices to generate, each by a separate upstream request
+    pub(super) choices: u32,
 }
 
 pub struct ClaudeCodePreprocess(pub CreateMessageParams, pub ClaudeContext);
@@ -157,7 +268,23 @@ where
     type Rejection = ClewdrError;
 
     async fn from_request(req: Request, _: &S) -> Result<Self, Self::Rejection> {
-        let NormalizeRequest(mut body, format) = NormalizeRequest::from_request(req, &()).await?;
+        // Collect client beta flags before the body consumes the request
+        let anthropic_beta = req
+            .headers()
+            .get_all("anthropic-beta")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|b| b.trim().to_string())
+            .filter(|b| !b.is_empty())
+            .collect::<Vec<_>>();
+        let NormalizeRequest {
+            mut body,
+            format,
+            include_usage,
+            response_format,
+            choices,
+        } = NormalizeRequest::from_request(req, &()).await?;
         // Handle thinking mode by modifying the model name
         if body.model.contains("opus-4-1") && body.temperature.is_some() {
             body.top_p = None; // temperature and top_p cannot be used together in Opus-4-1
@@ -178,20 +305,18 @@ where
         // Add a prelude text block to the system messages
         const PRELUDE_TEXT: &str = "You are Claude Code, Anthropic's official CLI for Claude.";
         let prelude_blk = || -> ContentBlock {
-            ContentBlock::Text {
-                text: CLEWDR_CONFIG
+            ContentBlock::text(
+                CLEWDR_CONFIG
                     .load()
                     .custom_system
                     .clone()
                     .unwrap_or_else(|| PRELUDE_TEXT.to_string()),
-            }
+            )
         };
         match body.system {
             Some(Value::String(ref text)) => {
                 if text != PRELUDE_TEXT {
-                    let text_content = ContentBlock::Text {
-                        text: text.to_owned(),
-                    };
+                    let text_content = ContentBlock::text(text.to_owned());
                     body.system = Some(json!([prelude_blk(), text_content]));
                 }
             }
@@ -206,6 +331,10 @@ where
             }
         }
 
+        if let Some(ref f) = response_format {
+            force_response_tool(&mut body, f)?;
+        }
+
         let cache_systems = body
             .system
             .as_ref()
@@ -231,6 +360,10 @@ where
                 input_tokens,
                 output_tokens: 0, // Placeholder for output token count
             },
+            anthropic_beta,
+            include_usage,
+            response_format,
+            choices,
         };
 
         Ok(Self(body, ClaudeContext::Code(info)))
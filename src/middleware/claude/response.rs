@@ -10,11 +10,15 @@ use tracing::warn;
 
 use super::{ClaudeApiFormat, transform_stream};
 use crate::{
-    middleware::claude::{ClaudeContext, transforms_json},
+    middleware::claude::{
+        ClaudeContext, apply_response_format, transform_complete_stream,
+        transform_completion_stream, transform_responses_stream, transforms_complete_json,
+        transforms_completion_json, transforms_json, transforms_responses_json,
+    },
     types::claude::{CreateMessageResponse, StreamEvent},
 };
 
-async fn parse_response<T>(resp: Response) -> Result<T, Response>
+pub(super) async fn parse_response<T>(resp: Response) -> Result<T, Response>
 where
     T: serde::de::DeserializeOwned,
 {
@@ -43,6 +47,9 @@ where
 /// - Not streaming: No transformation needed
 /// - Has a non-200 status code: No transformation needed
 /// - OpenAI format and streaming: Transforms the stream to match OpenAI event format
+/// - Responses API format: Transforms the message or stream into Responses API objects and events
+/// - Legacy completions formats: Transforms the message or stream into OpenAI `text_completion`
+///   or Anthropic `completion` objects and events
 ///
 /// # Arguments
 ///
@@ -58,21 +65,55 @@ pub async fn to_oai(resp: Response) -> impl IntoResponse {
     if ClaudeApiFormat::Claude == cx.api_format() {
         return resp;
     }
+    let format = cx.api_format();
+    let response_format = cx.response_format().cloned();
     if !cx.is_stream() {
-        match parse_response::<CreateMessageResponse>(resp).await {
-            Ok(response) => return Json(transforms_json(response)).into_response(),
+        let mut response = match parse_response::<CreateMessageResponse>(resp).await {
+            Ok(response) => response,
             Err(resp) => return resp,
-        }
+        };
+        let body = match format {
+            ClaudeApiFormat::Responses => transforms_responses_json(response),
+            ClaudeApiFormat::Completions => transforms_completion_json(response),
+            ClaudeApiFormat::Complete => transforms_complete_json(response),
+            _ => {
+                if let Some(ref f) = response_format
+                    && let Err(e) = apply_response_format(&mut response, f)
+                {
+                    return e.into_response();
+                }
+                transforms_json(response)
+            }
+        };
+        return Json(body).into_response();
     }
+    let (include_usage, usage) = (cx.include_usage(), cx.usage().to_owned());
     let stream = resp.into_body().into_data_stream().eventsource();
-    let stream = transform_stream(stream);
-    Sse::new(stream)
+    match format {
+        ClaudeApiFormat::Responses => Sse::new(transform_responses_stream(stream, usage))
+            .keep_alive(Default::default())
+            .into_response(),
+        ClaudeApiFormat::Completions => {
+            Sse::new(transform_completion_stream(stream, include_usage, usage))
+                .keep_alive(Default::default())
+                .into_response()
+        }
+        ClaudeApiFormat::Complete => Sse::new(transform_complete_stream(stream))
+            .keep_alive(Default::default())
+            .into_response(),
+        _ => Sse::new(transform_stream(
+            stream,
+            include_usage,
+            usage,
+            response_format,
+        ))
         .keep_alive(Default::default())
-        .into_response()
+        .into_response(),
+    }
 }
 
 pub async fn add_usage_info(resp: Response) -> impl IntoResponse {
-    let Some(cx) = resp.extensions().get::<ClaudeContext>() else {
+    let Some(cx) = resp.extensions().get::<ClaudeContext>().cloned() else {
         return resp;
     };
     let (mut usage, stream) = (cx.usage().to_owned(), cx.is_stream());
@@ -84,7 +125,10 @@ pub async fn add_usage_info(resp: Response) -> impl IntoResponse {
         let output_tokens = response.count_tokens();
         usage.output_tokens = output_tokens;
         response.usage = Some(usage);
-        return Json(response).into_response();
+        // keep the context for the layers converting the response afterwards
+        let mut resp = Json(response).into_response();
+        resp.extensions_mut().insert(cx);
+        return resp;
     }
     let stream = resp
         .into_body()
@@ -122,9 +166,11 @@ pub async fn add_usage_info(resp: Response) -> impl IntoResponse {
             }
         });
 
-    Sse::new(stream)
+    let mut resp = Sse::new(stream)
         .keep_alive(Default::default())
-        .into_response()
+        .into_response();
+    resp.extensions_mut().insert(cx);
+    resp
 }
 
 pub async fn check_overloaded(mut resp: Response) -> Response {
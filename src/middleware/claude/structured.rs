@@ -0,0 +1,150 @@
+use serde_json::{Value, json};
+
+use crate::{
+    error::ClewdrError,
+    types::{
+        claude::{
+            ContentBlock, CreateMessageParams, CreateMessageResponse, StopReason, Tool, ToolChoice,
+        },
+        oai::ResponseFormat,
+    },
+    utils::json_schema,
+};
+
+/// Name of the tool Claude is forced to call with the structured output
+pub const RESPONSE_FORMAT_TOOL: &str = "json_response";
+
+/// Forces Claude to answer through a tool whose input schema is the response format
+///
+/// Used for the Claude API, which validates tool inputs against their schema.
+/// When the client supplies tools of its own, Claude must call one of the tools,
+/// the client's or the response tool, unless the client chose a tool or no tools.
+pub fn force_response_tool(
+    body: &mut CreateMessageParams,
+    format: &ResponseFormat,
+) -> Result<(), ClewdrError> {
+    let Some(schema) = format.schema() else {
+        return Ok(());
+    };
+    let client_tools = body.tools.as_ref().is_some_and(|t| !t.is_empty());
+    let tool_choice = match body.tool_choice.take() {
+        // a client tool or plain text is required, the text answer is validated
+        Some(choice @ (ToolChoice::Tool { .. } | ToolChoice::None)) if client_tools => {
+            if matches!(choice, ToolChoice::None) {
+                add_format_instruction(body, format);
+            }
+            choice
+        }
+        _ if client_tools => ToolChoice::Any,
+        _ => ToolChoice::Tool {
+            name: RESPONSE_FORMAT_TOOL.to_string(),
+        },
+    };
+    // forced tool use is not allowed together with extended thinking
+    let forced = !client_tools || matches!(tool_choice, ToolChoice::Any);
+    if forced && body.thinking.is_some() {
+        return Err(ClewdrError::BadRequest {
+            msg: "response_format cannot be used together with thinking",
+        });
+    }
+    let description = format
+        .description()
+        .unwrap_or("Respond to the user with the final answer as the input of this tool.");
+    body.tools.get_or_insert_default().push(Tool {
+        name: RESPONSE_FORMAT_TOOL.to_string(),
+        description: Some(description.to_string()),
+        input_schema: schema,
+    });
+    body.tool_choice = Some(tool_choice);
+    Ok(())
+}
+
+/// Instructs Claude to answer with JSON matching the response format
+///
+/// Used for claude.ai, where the output is validated after generation
+pub fn add_format_instruction(body: &mut CreateMessageParams, format: &ResponseFormat) {
+    let Some(schema) = format.schema() else {
+        return;
+    };
+    let mut text = String::from(
+        "Respond only with a single JSON value matching the JSON schema below. \
+         Do not wrap it in code fences and do not write anything before or after it.",
+    );
+    if let Some(description) = format.description() {
+        text += &format!("\nDescription of the value: {description}");
+    }
+    text += &format!("\n<schema>\n{schema}\n</schema>");
+    let block = json!(ContentBlock::text(text));
+    body.system = Some(match body.system.take() {
+        Some(Value::String(s)) => json!([ContentBlock::text(s), block]),
+        Some(Value::Array(mut a)) => {
+            a.push(block);
+            Value::Array(a)
+        }
+        _ => json!([block]),
+    });
+}
+
+/// Parses a JSON reply, tolerating code fences around it
+fn parse_reply(text: &str) -> Result<Value, ClewdrError> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|t| t.strip_suffix("```"))
+        .unwrap_or(text);
+    serde_json::from_str(text.trim()).map_err(|e| ClewdrError::InvalidResponseFormat {
+        msg: format!("output is not valid JSON: {e}"),
+    })
+}
+
+/// Validates structured output, given as the input of the response tool or as text
+///
+/// # Returns
+/// * `Ok(Some(value))` - The validated JSON value
+/// * `Ok(None)` - The response format is plain text
+pub fn validate_output(
+    format: &ResponseFormat,
+    tool_input: Option<Value>,
+    text: &str,
+) -> Result<Option<Value>, ClewdrError> {
+    let Some(schema) = format.schema() else {
+        return Ok(None);
+    };
+    let value = match tool_input {
+        Some(input) => input,
+        None => parse_reply(text)?,
+    };
+    json_schema::validate(&schema, &value)
+        .map_err(|msg| ClewdrError::InvalidResponseFormat { msg })?;
+    Ok(Some(value))
+}
+
+/// Replaces the content of a reply with its validated structured output
+///
+/// Replies calling a client tool are left untouched, as they carry no final answer yet
+pub fn apply_response_format(
+    response: &mut CreateMessageResponse,
+    format: &ResponseFormat,
+) -> Result<(), ClewdrError> {
+    let mut tool_input = None;
+    let mut text = String::new();
+    for block in response.content.iter() {
+        match block {
+            ContentBlock::ToolUse { name, input, .. } if name == RESPONSE_FORMAT_TOOL => {
+                tool_input = Some(input.to_owned());
+            }
+            ContentBlock::ToolUse { .. } => return Ok(()),
+            ContentBlock::Text { text: t, .. } => text += t,
+            _ => {}
+        }
+    }
+    let Some(value) = validate_output(format, tool_input, &text)? else {
+        return Ok(());
+    };
+    response.content = vec![ContentBlock::text(value.to_string())];
+    if matches!(response.stop_reason, Some(StopReason::ToolUse)) {
+        response.stop_reason = Some(StopReason::EndTurn);
+    }
+    Ok(())
+}
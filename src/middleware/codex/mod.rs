@@ -0,0 +1,5 @@
+mod responses2claude;
+mod responses2oai;
+
+pub use responses2claude::{transform_claude_stream, transforms_claude_json};
+pub use responses2oai::{chat_finish_reason, chat_usage, transform_chat_stream};
@@ -0,0 +1,299 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::response::sse::Event;
+use futures::{Stream, TryStreamExt, stream};
+use serde_json::{Value, json};
+
+use crate::types::{
+    claude::{
+        ContentBlock, ContentBlockDelta, CreateMessageResponse, MessageDeltaContent,
+        MessageStartContent, Role, StopReason, StreamError, StreamEvent, StreamUsage, Usage,
+    },
+    oai::tool_call_input,
+};
+
+/// Converts the usage of a Responses API response into Claude usage
+fn usage(response: &Value) -> Usage {
+    let count = |key: &str| response["usage"][key].as_u64().unwrap_or_default() as u32;
+    Usage {
+        input_tokens: count("input_tokens"),
+        output_tokens: count("output_tokens"),
+    }
+}
+
+/// Maps the status of a Responses API response to a Claude stop reason
+fn stop_reason(response: &Value, tool_use: bool) -> StopReason {
+    match response["incomplete_details"]["reason"].as_str() {
+        _ if tool_use => StopReason::ToolUse,
+        Some("max_output_tokens") => StopReason::MaxTokens,
+        Some("content_filter") => StopReason::Refusal,
+        _ => StopReason::EndTurn,
+    }
+}
+
+/// Joins the reasoning summary parts of a reasoning item
+fn reasoning_summary(item: &Value) -> String {
+    item["summary"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|s| s["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Arguments of a function call item, parsed as the input of a tool use
+fn tool_input(item: &Value) -> Value {
+    tool_call_input(item["arguments"].as_str().unwrap_or("{}"))
+}
+
+/// Converts a Responses API output item into Claude content blocks
+///
+/// The encrypted content of a reasoning item is kept as the thinking signature,
+/// so that the reasoning can be sent back on the next turn.
+fn content_blocks(item: &Value) -> Vec<ContentBlock> {
+    match item["type"].as_str() {
+        Some("reasoning") => vec![ContentBlock::Thinking {
+            thinking: reasoning_summary(item),
+            signature: item["encrypted_content"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        }],
+        Some("message") => item["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|c| match c["type"].as_str() {
+                Some("output_text") => c["text"].as_str(),
+                Some("refusal") => c["refusal"].as_str(),
+                _ => None,
+            })
+            .map(ContentBlock::text)
+            .collect(),
+        Some("function_call") => vec![ContentBlock::ToolUse {
+            id: item["call_id"].as_str().unwrap_or_default().to_string(),
+            name: item["name"].as_str().unwrap_or_default().to_string(),
+            input: tool_input(item),
+        }],
+        _ => vec![],
+    }
+}
+
+/// Converts the output items of a Responses API response into a Claude message
+///
+/// # Arguments
+/// * `items` - Output items, in the order they were completed
+/// * `response` - The final response object, for the id, status and usage
+/// * `model` - Model requested by the client
+pub fn transforms_claude_json(
+    items: &[Value],
+    response: &Value,
+    model: &str,
+) -> CreateMessageResponse {
+    let content = items.iter().flat_map(content_blocks).collect::<Vec<_>>();
+    let tool_use = content
+        .iter()
+        .any(|b| matches!(b, ContentBlock::ToolUse { .. }));
+    CreateMessageResponse {
+        id: response["id"].as_str().unwrap_or_default().to_string(),
+        model: model.to_string(),
+        role: Role::Assistant,
+        stop_reason: Some(stop_reason(response, tool_use)),
+        stop_sequence: None,
+        type_: "message".to_string(),
+        usage: Some(usage(response)),
+        content,
+    }
+}
+
+/// Creates a Claude SSE event, named after the type of the event
+fn claude_event(e: StreamEvent) -> Event {
+    let data = serde_json::to_value(&e).unwrap_or_default();
+    let name = data["type"].as_str().unwrap_or_default().to_string();
+    Event::default().event(name).json_data(data).unwrap()
+}
+
+/// State carried across the events of a Responses API stream
+struct ClaudeStream {
+    model: String,
+    /// Claude block index of each open output item, by output index
+    blocks: HashMap<u64, usize>,
+    next_index: usize,
+    /// Output items whose function call arguments were streamed as deltas
+    streamed_arguments: HashSet<u64>,
+    tool_use: bool,
+}
+
+impl ClaudeStream {
+    fn start_block(&mut self, output_index: u64, content_block: ContentBlock) -> Event {
+        let index = self.next_index;
+        self.next_index += 1;
+        self.blocks.insert(output_index, index);
+        claude_event(StreamEvent::ContentBlockStart {
+            index,
+            content_block,
+        })
+    }
+
+    fn delta(&self, output_index: u64, delta: ContentBlockDelta) -> Option<Event> {
+        let index = *self.blocks.get(&output_index)?;
+        Some(claude_event(StreamEvent::ContentBlockDelta {
+            index,
+            delta,
+        }))
+    }
+
+    fn finish(&self, response: &Value) -> Vec<Event> {
+        let usage = usage(response);
+        vec![
+            claude_event(StreamEvent::MessageDelta {
+                delta: MessageDeltaContent {
+                    stop_reason: Some(stop_reason(response, self.tool_use)),
+                    stop_sequence: None,
+                },
+                usage: Some(StreamUsage {
+                    input_tokens: usage.input_tokens,
+                    output_tokens: usage.output_tokens,
+                }),
+            }),
+            claude_event(StreamEvent::MessageStop),
+        ]
+    }
+
+    fn process(&mut self, data: &str) -> Vec<Event> {
+        let Ok(v) = serde_json::from_str::<Value>(data) else {
+            return vec![];
+        };
+        let output_index = v["output_index"].as_u64().unwrap_or_default();
+        let text = |key: &str| v[key].as_str().unwrap_or_default().to_string();
+        match v["type"].as_str().unwrap_or_default() {
+            "response.created" => {
+                let message = MessageStartContent {
+                    id: v["response"]["id"].as_str().unwrap_or_default().to_string(),
+                    type_: "message".to_string(),
+                    role: Role::Assistant,
+                    content: vec![],
+                    model: self.model.to_owned(),
+                    stop_reason: None,
+                    stop_sequence: None,
+                    usage: Some(Usage::default()),
+                };
+                vec![claude_event(StreamEvent::MessageStart { message })]
+            }
+            "response.output_item.added" => {
+                let item = &v["item"];
+                let content_block = match item["type"].as_str() {
+                    Some("reasoning") => ContentBlock::Thinking {
+                        thinking: String::new(),
+                        signature: String::new(),
+                    },
+                    Some("message") => ContentBlock::text(""),
+                    Some("function_call") => {
+                        self.tool_use = true;
+                        ContentBlock::ToolUse {
+                            id: item["call_id"].as_str().unwrap_or_default().to_string(),
+                            name: item["name"].as_str().unwrap_or_default().to_string(),
+                            input: json!({}),
+                        }
+                    }
+                    _ => return vec![],
+                };
+                vec![self.start_block(output_index, content_block)]
+            }
+            "response.reasoning_summary_part.added"
+                if v["summary_index"].as_u64().is_some_and(|i| i > 0) =>
+            {
+                let delta = ContentBlockDelta::ThinkingDelta {
+                    thinking: "\n\n".to_string(),
+                };
+                self.delta(output_index, delta).into_iter().collect()
+            }
+            "response.reasoning_summary_text.delta" => {
+                let delta = ContentBlockDelta::ThinkingDelta {
+                    thinking: text("delta"),
+                };
+                self.delta(output_index, delta).into_iter().collect()
+            }
+            "response.output_text.delta" => {
+                let delta = ContentBlockDelta::TextDelta {
+                    text: text("delta"),
+                };
+                self.delta(output_index, delta).into_iter().collect()
+            }
+            "response.function_call_arguments.delta" => {
+                self.streamed_arguments.insert(output_index);
+                let delta = ContentBlockDelta::InputJsonDelta {
+                    partial_json: text("delta"),
+                };
+                self.delta(output_index, delta).into_iter().collect()
+            }
+            "response.output_item.done" => {
+                let item = &v["item"];
+                let delta = match item["type"].as_str() {
+                    Some("reasoning") => item["encrypted_content"].as_str().map(|s| {
+                        ContentBlockDelta::SignatureDelta {
+                            signature: s.to_string(),
+                        }
+                    }),
+                    Some("function_call") if !self.streamed_arguments.contains(&output_index) => {
+                        Some(ContentBlockDelta::InputJsonDelta {
+                            partial_json: tool_input(item).to_string(),
+                        })
+                    }
+                    _ => None,
+                };
+                let mut events = vec![];
+                events.extend(delta.and_then(|d| self.delta(output_index, d)));
+                if let Some(index) = self.blocks.remove(&output_index) {
+                    events.push(claude_event(StreamEvent::ContentBlockStop { index }));
+                }
+                events
+            }
+            "response.completed" | "response.incomplete" => self.finish(&v["response"]),
+            kind @ ("response.failed" | "error") => {
+                let error = if kind == "error" {
+                    &v
+                } else {
+                    &v["response"]["error"]
+                };
+                let message = error["message"]
+                    .as_str()
+                    .unwrap_or("Codex response failed")
+                    .to_string();
+                vec![claude_event(StreamEvent::Error {
+                    error: StreamError {
+                        type_: "api_error".to_string(),
+                        message,
+                    },
+                })]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// Transforms a Responses API event stream into a Claude event stream
+///
+/// Reasoning summaries are streamed as thinking blocks, with the encrypted reasoning
+/// as signature, output text as text blocks and function calls as tool use blocks.
+///
+/// # Arguments
+/// * `s` - The input stream of Responses API events
+/// * `model` - Model requested by the client
+pub fn transform_claude_stream<I, E>(s: I, model: String) -> impl Stream<Item = Result<Event, E>>
+where
+    I: Stream<Item = Result<eventsource_stream::Event, E>>,
+{
+    let mut state = ClaudeStream {
+        model,
+        blocks: HashMap::new(),
+        next_index: 0,
+        streamed_arguments: HashSet::new(),
+        tool_use: false,
+    };
+    s.map_ok(move |eventsource_stream::Event { data, .. }| {
+        stream::iter(state.process(&data).into_iter().map(Ok::<_, E>))
+    })
+    .try_flatten()
+}
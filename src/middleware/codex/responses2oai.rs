@@ -0,0 +1,198 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::response::sse::Event;
+use futures::{Stream, TryStreamExt, stream};
+use serde_json::{Value, json};
+
+/// Converts the usage of a Responses API response into OpenAI chat completion usage
+pub fn chat_usage(usage: &Value) -> Value {
+    let prompt_tokens = usage["input_tokens"].as_u64().unwrap_or_default();
+    let completion_tokens = usage["output_tokens"].as_u64().unwrap_or_default();
+    json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+        "completion_tokens_details": {
+            "reasoning_tokens": usage["output_tokens_details"]["reasoning_tokens"]
+                .as_u64()
+                .unwrap_or_default(),
+        },
+    })
+}
+
+/// Maps the status of a Responses API response to a chat completion finish reason
+pub fn chat_finish_reason(response: &Value, tool_calls: bool) -> &'static str {
+    match response["incomplete_details"]["reason"].as_str() {
+        _ if tool_calls => "tool_calls",
+        Some("max_output_tokens") => "length",
+        Some("content_filter") => "content_filter",
+        _ => "stop",
+    }
+}
+
+/// State carried across the events of a Responses API stream
+struct ChatStream {
+    id: String,
+    model: String,
+    created: i64,
+    /// Whether to send a final chunk with the token usage
+    include_usage: bool,
+    /// Tool call index of each function call item, by output index
+    tool_calls: HashMap<u64, usize>,
+    /// Function call items whose arguments were streamed as deltas
+    streamed_arguments: HashSet<u64>,
+}
+
+impl ChatStream {
+    fn chunk(&self, choices: Value, usage: Option<Value>) -> Event {
+        let mut data = json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": choices,
+        });
+        if let Some(usage) = usage {
+            data["usage"] = usage;
+        }
+        Event::default().json_data(data).unwrap()
+    }
+
+    fn delta(&self, delta: Value, finish_reason: Option<&str>) -> Event {
+        let choice = json!({"index": 0, "delta": delta, "finish_reason": finish_reason});
+        self.chunk(json!([choice]), None)
+    }
+
+    /// Assigns the next tool call index to a function call item
+    fn tool_call_index(&mut self, output_index: u64) -> usize {
+        let next = self.tool_calls.len();
+        *self.tool_calls.entry(output_index).or_insert(next)
+    }
+
+    fn tool_call(&self, index: usize, call: Value) -> Event {
+        let mut call = call;
+        call["index"] = json!(index);
+        self.delta(json!({"tool_calls": [call]}), None)
+    }
+
+    fn process(&mut self, data: &str) -> Vec<Event> {
+        let Ok(v) = serde_json::from_str::<Value>(data) else {
+            return vec![];
+        };
+        let output_index = v["output_index"].as_u64().unwrap_or_default();
+        let text = |key: &str| v[key].as_str().unwrap_or_default().to_string();
+        let item = &v["item"];
+        let is_call = item["type"].as_str() == Some("function_call");
+        match v["type"].as_str().unwrap_or_default() {
+            "response.created" => {
+                if let Some(id) = v["response"]["id"].as_str() {
+                    self.id = id.to_string();
+                }
+                vec![self.delta(json!({"role": "assistant", "content": ""}), None)]
+            }
+            "response.output_text.delta" => {
+                vec![self.delta(json!({"content": text("delta")}), None)]
+            }
+            "response.reasoning_summary_part.added"
+                if v["summary_index"].as_u64().is_some_and(|i| i > 0) =>
+            {
+                vec![self.delta(json!({"reasoning_content": "\n\n"}), None)]
+            }
+            "response.reasoning_summary_text.delta" => {
+                vec![self.delta(json!({"reasoning_content": text("delta")}), None)]
+            }
+            "response.output_item.added" if is_call => {
+                let index = self.tool_call_index(output_index);
+                let call = json!({
+                    "id": item["call_id"],
+                    "type": "function",
+                    "function": {"name": item["name"], "arguments": ""},
+                });
+                vec![self.tool_call(index, call)]
+            }
+            "response.function_call_arguments.delta" => {
+                let Some(&index) = self.tool_calls.get(&output_index) else {
+                    return vec![];
+                };
+                self.streamed_arguments.insert(output_index);
+                let call = json!({"function": {"arguments": text("delta")}});
+                vec![self.tool_call(index, call)]
+            }
+            "response.output_item.done" if is_call => {
+                if self.streamed_arguments.contains(&output_index) {
+                    return vec![];
+                }
+                // the call was not announced or its arguments were not streamed
+                let announced = self.tool_calls.contains_key(&output_index);
+                let index = self.tool_call_index(output_index);
+                let arguments = item["arguments"].as_str().unwrap_or_default();
+                let call = if announced {
+                    json!({"function": {"arguments": arguments}})
+                } else {
+                    json!({
+                        "id": item["call_id"],
+                        "type": "function",
+                        "function": {"name": item["name"], "arguments": arguments},
+                    })
+                };
+                vec![self.tool_call(index, call)]
+            }
+            "response.completed" | "response.incomplete" => {
+                let response = &v["response"];
+                let finish_reason = chat_finish_reason(response, !self.tool_calls.is_empty());
+                let mut events = vec![self.delta(json!({}), Some(finish_reason))];
+                if self.include_usage && response["usage"].is_object() {
+                    let usage = chat_usage(&response["usage"]);
+                    events.push(self.chunk(json!([]), Some(usage)));
+                }
+                events.push(Event::default().data("[DONE]"));
+                events
+            }
+            kind @ ("response.failed" | "error") => {
+                let error = if kind == "error" {
+                    &v
+                } else {
+                    &v["response"]["error"]
+                };
+                let message = error["message"].as_str().unwrap_or("response.failed");
+                let data = json!({"error": {"message": message}});
+                vec![Event::default().json_data(data).unwrap()]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// Transforms a Responses API event stream into an OpenAI chat completion stream
+///
+/// Output text is streamed as content, reasoning summaries as `reasoning_content`,
+/// and function calls as `tool_calls` deltas indexed in the order the calls start.
+/// The finish reason, a usage chunk if requested, and `[DONE]` end the stream.
+///
+/// # Arguments
+/// * `s` - The input stream of Responses API events
+/// * `model` - Model requested by the client
+/// * `created` - Creation time of the completion
+/// * `include_usage` - Whether to send a final usage chunk, from `stream_options`
+pub fn transform_chat_stream<I, E>(
+    s: I,
+    model: String,
+    created: i64,
+    include_usage: bool,
+) -> impl Stream<Item = Result<Event, E>>
+where
+    I: Stream<Item = Result<eventsource_stream::Event, E>>,
+{
+    let mut state = ChatStream {
+        id: "chatcmpl-stream".to_string(),
+        model,
+        created,
+        include_usage,
+        tool_calls: HashMap::new(),
+        streamed_arguments: HashSet::new(),
+    };
+    s.map_ok(move |eventsource_stream::Event { data, .. }| {
+        stream::iter(state.process(&data).into_iter().map(Ok::<_, E>))
+    })
+    .try_flatten()
+}
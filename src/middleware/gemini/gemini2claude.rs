@@ -0,0 +1,298 @@
+use async_stream::try_stream;
+use axum::response::sse::Event;
+use eventsource_stream::EventStreamError;
+use futures::{Stream, StreamExt};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::types::{
+    claude::{
+        ContentBlock, ContentBlockDelta, CreateMessageResponse, MessageDeltaContent,
+        MessageStartContent, Role, StopReason, StreamEvent, StreamUsage, Usage,
+    },
+    gemini::{
+        request::{ContentPart, Part},
+        response::{FinishReason, GeminiResponse},
+    },
+};
+
+/// Maps a Gemini finish reason to a Claude stop reason
+fn stop_reason(reason: Option<&FinishReason>, tool_use: bool) -> StopReason {
+    match reason {
+        _ if tool_use => StopReason::ToolUse,
+        Some(FinishReason::MAX_TOKENS) => StopReason::MaxTokens,
+        Some(
+            FinishReason::SAFETY
+            | FinishReason::RECITATION
+            | FinishReason::BLOCKLIST
+            | FinishReason::PROHIBITED_CONTENT
+            | FinishReason::SPII
+            | FinishReason::IMAGE_SAFETY,
+        ) => StopReason::Refusal,
+        _ => StopReason::EndTurn,
+    }
+}
+
+/// Converts Gemini usage metadata into Claude usage, thoughts count as output
+fn usage(metadata: &Value) -> Usage {
+    let count = |key: &str| metadata[key].as_u64().unwrap_or_default() as u32;
+    Usage {
+        input_tokens: count("promptTokenCount"),
+        output_tokens: count("candidatesTokenCount") + count("thoughtsTokenCount"),
+    }
+}
+
+fn tool_use_id(id: Option<String>) -> String {
+    id.unwrap_or_else(|| format!("toolu_{}", Uuid::new_v4().simple()))
+}
+
+/// Whether a part holds thoughts, whose signature is kept on their thinking block
+fn is_thought(part: &Part) -> bool {
+    matches!(
+        part,
+        Part::Text {
+            thought: Some(true),
+            ..
+        }
+    )
+}
+
+/// Converts a Gemini `generateContent` response into a Claude message
+///
+/// Thought signatures are kept in the `signature` of thinking blocks. A signature on
+/// another part is kept in an empty thinking block placed before that part,
+/// so the client replays it to Gemini on the next turn
+pub fn transforms_claude_json(input: GeminiResponse, model: &str) -> CreateMessageResponse {
+    let candidate = input.candidates.into_iter().next();
+    let finish_reason = candidate.as_ref().and_then(|c| c.finishReason.as_ref());
+    let mut content = vec![];
+    let mut tool_use = false;
+    for ContentPart {
+        part,
+        mut thought_signature,
+    } in candidate.iter().flat_map(|c| c.content.parts.to_owned())
+    {
+        if !is_thought(&part)
+            && let Some(signature) = thought_signature.take()
+        {
+            content.push(ContentBlock::Thinking {
+                thinking: String::new(),
+                signature,
+            });
+        }
+        match part {
+            Part::Text {
+                text,
+                thought: Some(true),
+            } => content.push(ContentBlock::Thinking {
+                thinking: text,
+                signature: thought_signature.unwrap_or_default(),
+            }),
+            Part::Text { text, .. } if text.is_empty() => {}
+            Part::Text { text, .. } => content.push(ContentBlock::text(text)),
+            Part::functionCall(call) => {
+                tool_use = true;
+                content.push(ContentBlock::ToolUse {
+                    id: tool_use_id(call.id),
+                    name: call.name,
+                    input: call.args.unwrap_or_else(|| json!({})),
+                });
+            }
+            _ => {}
+        }
+    }
+    CreateMessageResponse {
+        id: format!("msg_{}", Uuid::new_v4().simple()),
+        model: model.to_string(),
+        role: Role::Assistant,
+        stop_reason: Some(stop_reason(finish_reason, tool_use)),
+        stop_sequence: None,
+        type_: "message".to_string(),
+        usage: Some(usage(&input.usageMetadata)),
+        content,
+    }
+}
+
+/// Kind of the content block being streamed
+#[derive(PartialEq)]
+enum BlockKind {
+    Text,
+    Thinking,
+}
+
+/// Creates a Claude SSE event, named after the type of the event
+fn claude_event(e: StreamEvent) -> Event {
+    let data = serde_json::to_value(&e).unwrap_or_default();
+    let name = data["type"].as_str().unwrap_or_default().to_string();
+    Event::default().event(name).json_data(data).unwrap()
+}
+
+/// Transforms a Gemini `streamGenerateContent` SSE stream into a Claude event stream
+///
+/// Text and thought parts are streamed as text and thinking blocks, function calls
+/// as complete tool use blocks. Thought signatures are sent as signature deltas,
+/// as in [`transforms_claude_json`]. The stop reason and usage are sent when the stream ends.
+///
+/// # Arguments
+/// * `s` - The input stream of Gemini events
+/// * `model` - Model requested by the client
+pub fn transform_claude_stream<I>(
+    s: I,
+    model: String,
+) -> impl Stream<Item = Result<Event, EventStreamError<axum::Error>>>
+where
+    I: Stream<Item = Result<eventsource_stream::Event, EventStreamError<axum::Error>>>,
+{
+    try_stream!({
+        let mut s = std::pin::pin!(s);
+        let mut started = false;
+        // index and kind of the open block
+        let mut open: Option<(usize, BlockKind)> = None;
+        let mut next_index = 0;
+        let mut tool_use = false;
+        let mut finish_reason = None;
+        let mut usage_out = Usage::default();
+        while let Some(source) = s.next().await {
+            let Ok(chunk) = serde_json::from_str::<GeminiResponse>(&source?.data) else {
+                continue;
+            };
+            if !chunk.usageMetadata.is_null() {
+                usage_out = usage(&chunk.usageMetadata);
+            }
+            if !started {
+                started = true;
+                let message = MessageStartContent {
+                    id: format!("msg_{}", Uuid::new_v4().simple()),
+                    type_: "message".to_string(),
+                    role: Role::Assistant,
+                    content: vec![],
+                    model: model.to_owned(),
+                    stop_reason: None,
+                    stop_sequence: None,
+                    usage: Some(Usage {
+                        input_tokens: usage_out.input_tokens,
+                        output_tokens: 0,
+                    }),
+                };
+                yield claude_event(StreamEvent::MessageStart { message });
+            }
+            let Some(candidate) = chunk.candidates.into_iter().next() else {
+                continue;
+            };
+            if candidate.finishReason.is_some() {
+                finish_reason = candidate.finishReason;
+            }
+            for ContentPart {
+                part,
+                mut thought_signature,
+            } in candidate.content.parts
+            {
+                if !is_thought(&part)
+                    && let Some(signature) = thought_signature.take()
+                {
+                    if let Some((index, _)) = open.take() {
+                        yield claude_event(StreamEvent::ContentBlockStop { index });
+                    }
+                    let index = next_index;
+                    next_index += 1;
+                    let content_block = ContentBlock::Thinking {
+                        thinking: String::new(),
+                        signature: String::new(),
+                    };
+                    yield claude_event(StreamEvent::ContentBlockStart {
+                        index,
+                        content_block,
+                    });
+                    let delta = ContentBlockDelta::SignatureDelta { signature };
+                    yield claude_event(StreamEvent::ContentBlockDelta { index, delta });
+                    yield claude_event(StreamEvent::ContentBlockStop { index });
+                }
+                let (kind, delta) = match part {
+                    Part::Text { text, .. } if text.is_empty() && thought_signature.is_none() => {
+                        continue;
+                    }
+                    Part::Text {
+                        text,
+                        thought: Some(true),
+                    } => (
+                        BlockKind::Thinking,
+                        ContentBlockDelta::ThinkingDelta { thinking: text },
+                    ),
+                    Part::Text { text, .. } => {
+                        (BlockKind::Text, ContentBlockDelta::TextDelta { text })
+                    }
+                    Part::functionCall(call) => {
+                        if let Some((index, _)) = open.take() {
+                            yield claude_event(StreamEvent::ContentBlockStop { index });
+                        }
+                        tool_use = true;
+                        let index = next_index;
+                        next_index += 1;
+                        let content_block = ContentBlock::ToolUse {
+                            id: tool_use_id(call.id),
+                            name: call.name,
+                            input: json!({}),
+                        };
+                        yield claude_event(StreamEvent::ContentBlockStart {
+                            index,
+                            content_block,
+                        });
+                        let partial_json = call.args.unwrap_or_else(|| json!({})).to_string();
+                        let delta = ContentBlockDelta::InputJsonDelta { partial_json };
+                        yield claude_event(StreamEvent::ContentBlockDelta { index, delta });
+                        yield claude_event(StreamEvent::ContentBlockStop { index });
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let index = match open {
+                    Some((index, ref k)) if *k == kind => index,
+                    _ => {
+                        if let Some((index, _)) = open.take() {
+                            yield claude_event(StreamEvent::ContentBlockStop { index });
+                        }
+                        let index = next_index;
+                        next_index += 1;
+                        let content_block = match kind {
+                            BlockKind::Text => ContentBlock::text(""),
+                            BlockKind::Thinking => ContentBlock::Thinking {
+                                thinking: String::new(),
+                                signature: String::new(),
+                            },
+                        };
+                        yield claude_event(StreamEvent::ContentBlockStart {
+                            index,
+                            content_block,
+                        });
+                        open = Some((index, kind));
+                        index
+                    }
+                };
+                yield claude_event(StreamEvent::ContentBlockDelta { index, delta });
+                // a signature ends its thinking block
+                if let Some(signature) = thought_signature {
+                    let delta = ContentBlockDelta::SignatureDelta { signature };
+                    yield claude_event(StreamEvent::ContentBlockDelta { index, delta });
+                    yield claude_event(StreamEvent::ContentBlockStop { index });
+                    open = None;
+                }
+            }
+        }
+        if let Some((index, _)) = open.take() {
+            yield claude_event(StreamEvent::ContentBlockStop { index });
+        }
+        if started {
+            yield claude_event(StreamEvent::MessageDelta {
+                delta: MessageDeltaContent {
+                    stop_reason: Some(stop_reason(finish_reason.as_ref(), tool_use)),
+                    stop_sequence: None,
+                },
+                usage: Some(StreamUsage {
+                    input_tokens: usage_out.input_tokens,
+                    output_tokens: usage_out.output_tokens,
+                }),
+            });
+            yield claude_event(StreamEvent::MessageStop);
+        }
+    })
+}
@@ -1,5 +1,7 @@
+mod gemini2claude;
 mod path;
 mod request;
 
+pub use gemini2claude::{transform_claude_stream, transforms_claude_json};
 pub use path::GeminiArgs;
-pub use request::{GeminiContext, GeminiOaiPreprocess, GeminiPreprocess};
+pub use request::{GeminiClaudePreprocess, GeminiContext, GeminiOaiPreprocess, GeminiPreprocess};
@@ -8,7 +8,11 @@ use crate::{
     config::CLEWDR_CONFIG,
     error::ClewdrError,
     gemini_state::{GeminiApiFormat, GeminiState},
-    types::{gemini::request::GeminiRequestBody, oai::CreateMessageParams},
+    types::{
+        claude::CreateMessageParams as ClaudeCreateMessageParams,
+        gemini::request::{GeminiRequestBody, check_image_urls},
+        oai::CreateMessageParams,
+    },
 };
 
 pub struct GeminiContext {
@@ -118,3 +122,48 @@ impl FromRequest<GeminiState> for GeminiOaiPreprocess {
         Ok(GeminiOaiPreprocess(body, ctx))
     }
 }
+
+pub struct GeminiClaudePreprocess(pub GeminiRequestBody, pub GeminiContext);
+
+impl FromRequest<GeminiState> for GeminiClaudePreprocess {
+    type Rejection = ClewdrError;
+
+    async fn from_request(req: Request, state: &GeminiState) -> Result<Self, Self::Rejection> {
+        let uri = req.uri().to_string();
+        let vertex = uri.contains("vertex");
+        if vertex && !CLEWDR_CONFIG.load().vertex.validate() {
+            return Err(ClewdrError::BadRequest {
+                msg: "Vertex is not configured",
+            });
+        }
+        let Json(body) = Json::<ClaudeCreateMessageParams>::from_request(req, &()).await?;
+        check_image_urls(&body)?;
+        let mut model = body.model.to_owned();
+        if vertex && let Some(id) = CLEWDR_CONFIG.load().vertex.model_id.to_owned() {
+            model = id;
+        }
+        let stream = body.stream.unwrap_or_default();
+        // the request is sent to the native Gemini API
+        let method = if stream {
+            "streamGenerateContent"
+        } else {
+            "generateContent"
+        };
+        let ctx = GeminiContext {
+            vertex,
+            path: format!("models/{model}:{method}"),
+            model,
+            stream,
+            query: GeminiArgs {
+                key: String::new(),
+                alt: stream.then(|| "sse".to_string()),
+            },
+            api_format: GeminiApiFormat::Claude,
+            cli_mode: false,
+            auth_bearer: None,
+        };
+        let mut state = state.clone();
+        state.update_from_ctx(&ctx);
+        Ok(GeminiClaudePreprocess(body.into(), ctx))
+    }
+}
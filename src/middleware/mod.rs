@@ -8,6 +8,7 @@
 /// - Response transformation: Convert between different response formats and handle streaming
 mod auth;
 pub mod claude;
+pub mod codex;
 pub mod gemini;
 
 pub use auth::{
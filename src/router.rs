@@ -9,10 +9,11 @@ use crate::routes::{
 use crate::{
     claude_code_state::ClaudeCodeState,
     claude_web_state::ClaudeWebState,
+    codex_state::CodexState,
     gemini_state::GeminiState,
     services::{
-        cli_token_actor::CliTokenActorHandle, cookie_actor::CookieActorHandle,
-        key_actor::KeyActorHandle,
+        cli_token_actor::CliTokenActorHandle, codex_actor::CodexActorHandle,
+        cookie_actor::CookieActorHandle, key_actor::KeyActorHandle,
     },
 };
 
@@ -23,6 +24,7 @@ pub struct RouterBuilder {
     cookie_actor_handle: CookieActorHandle,
     key_actor_handle: KeyActorHandle,
     cli_token_handle: CliTokenActorHandle,
+    codex_state: CodexState,
     gemini_state: GeminiState,
     inner: Router,
 }
@@ -37,7 +39,7 @@ impl RouterBuilder {
         let cookie_handle = CookieActorHandle::start()
             .await
             .expect("Failed to start CookieActor");
-        let claude_web_state = ClaudeWebState::new(cookie_handle.to_owned());
+        let claude_web_state = ClaudeWebState::new(cookie_handle.to_owned()).with_conversations();
         let claude_code_state = ClaudeCodeState::new(cookie_handle.to_owned());
         let key_tx = KeyActorHandle::start()
             .await
@@ -46,14 +48,27 @@ impl RouterBuilder {
             .await
             .expect("Failed to start CliTokenActorHandle");
         let gemini_state = GeminiState::new(key_tx.to_owned(), cli_token_tx.to_owned());
+        let codex_handle = CodexActorHandle::start()
+            .await
+            .expect("Failed to start CodexActor");
+        let codex_state = CodexState::new(codex_handle);
         // Background DB sync (keys/cookies) for multi-instance eventual consistency
         let _bg = crate::services::sync::spawn(cookie_handle.clone(), key_tx.clone());
+        // Background OAuth token refresh for Claude Code cookies
+        let _refresh = crate::services::token_refresh::spawn(claude_code_state.to_owned());
+        // Background deletion of claude.ai conversations
+        let _janitor = crate::services::janitor::spawn(cookie_handle.to_owned());
+        // Background polling of claude.ai account usage
+        let _usage = crate::services::usage::spawn(cookie_handle.to_owned());
+        // Background OAuth token refresh for Codex accounts
+        let _codex_refresh = crate::services::codex_refresh::spawn(codex_state.to_owned());
         RouterBuilder {
             claude_web_state,
             claude_code_state,
             cookie_actor_handle: cookie_handle,
             key_actor_handle: key_tx,
             cli_token_handle: cli_token_tx,
+            codex_state,
             gemini_state,
             inner: Router::new(),
         }
@@ -77,8 +92,8 @@ impl RouterBuilder {
             .merge(build_claude_code_oai_router(
                 self.claude_code_state.to_owned(),
             ))
-            .merge(build_codex_router())
-            .merge(build_codex_oauth_router())
+            .merge(build_codex_router(self.codex_state.to_owned()))
+            .merge(build_codex_oauth_router(self.codex_state.handle.to_owned()))
             .merge(build_admin_router(
                 self.cookie_actor_handle.to_owned(),
                 self.key_actor_handle.to_owned(),
@@ -135,6 +150,7 @@ impl RouterBuilder {
                 AUTHORIZATION,
                 CONTENT_TYPE,
                 HeaderName::from_static("x-api-key"),
+                HeaderName::from_static("anthropic-beta"),
             ]);
 
         self.inner = self.inner.layer(cors);
@@ -15,10 +15,12 @@ use crate::{
 pub fn build_claude_code_router(state: ClaudeCodeState) -> Router {
     Router::new()
         .route("/code/v1/messages", post(api_claude_code))
+        .route("/code/v1/complete", post(api_claude_code))
         .layer(
             ServiceBuilder::new()
                 .layer(from_extractor::<RequireXApiKeyAuth>())
-                .layer(CompressionLayer::new()),
+                .layer(CompressionLayer::new())
+                .layer(map_response(to_oai)),
         )
         .with_state(state)
 }
@@ -26,6 +28,8 @@ pub fn build_claude_code_router(state: ClaudeCodeState) -> Router {
 pub fn build_claude_code_oai_router(state: ClaudeCodeState) -> Router {
     Router::new()
         .route("/code/v1/chat/completions", post(api_claude_code))
+        .route("/code/v1/responses", post(api_claude_code))
+        .route("/code/v1/completions", post(api_claude_code))
         .route("/code/v1/models", get(api_get_models))
         .layer(
             ServiceBuilder::new()
@@ -18,10 +18,12 @@ use crate::{
 pub fn build_claude_web_router(state: ClaudeWebState) -> Router {
     Router::new()
         .route("/v1/messages", post(api_claude_web))
+        .route("/v1/complete", post(api_claude_web))
         .layer(
             ServiceBuilder::new()
                 .layer(from_extractor::<RequireXApiKeyAuth>())
                 .layer(CompressionLayer::new())
+                .layer(map_response(to_oai))
                 .layer(map_response(add_usage_info))
                 .layer(map_response(apply_stop_sequences))
                 .layer(map_response(check_overloaded)),
@@ -32,6 +34,8 @@ pub fn build_claude_web_router(state: ClaudeWebState) -> Router {
 pub fn build_claude_web_oai_router(state: ClaudeWebState) -> Router {
     Router::new()
         .route("/v1/chat/completions", post(api_claude_web))
+        .route("/v1/responses", post(api_claude_web))
+        .route("/v1/completions", post(api_claude_web))
         .route("/v1/models", get(api_get_models))
         .layer(
             ServiceBuilder::new()
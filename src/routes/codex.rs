@@ -6,15 +6,27 @@ use axum::{
 use tower::ServiceBuilder;
 use tower_http::compression::CompressionLayer;
 
-use crate::{api::*, codex_state::CodexState, middleware::RequireBearerAuth};
+use crate::{
+    api::*,
+    codex_state::CodexState,
+    middleware::{RequireBearerAuth, RequireXApiKeyAuth},
+};
 
-pub fn build_codex_router() -> Router {
-    let state = crate::api::codex::CodexApiState {
-        state: CodexState::new(),
-    };
+pub fn build_codex_router(state: CodexState) -> Router {
+    let state = crate::api::codex::CodexApiState { state };
+    // Claude clients authenticate with x-api-key
+    let claude = Router::new()
+        .route("/codex/v1/messages", post(codex_messages))
+        .layer(
+            ServiceBuilder::new()
+                .layer(from_extractor::<RequireXApiKeyAuth>())
+                .layer(CompressionLayer::new()),
+        )
+        .with_state(state.clone());
     Router::new()
         .route("/codex/v1/chat/completions", post(codex_chat_completions))
         .route("/codex/v1/completions", post(codex_completions))
+        .route("/codex/v1/responses", post(codex_responses))
         .route("/codex/v1/models", get(codex_list_models))
         .layer(
             ServiceBuilder::new()
@@ -22,4 +34,5 @@ pub fn build_codex_router() -> Router {
                 .layer(CompressionLayer::new()),
         )
         .with_state(state)
+        .merge(claude)
 }
@@ -1,21 +1,22 @@
 use axum::{
     Router,
     middleware::from_extractor,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 
-use crate::{api::*, middleware::RequireAdminAuth};
+use crate::{api::*, middleware::RequireAdminAuth, services::codex_actor::CodexActorHandle};
 
-pub fn build_codex_oauth_router() -> Router {
+pub fn build_codex_oauth_router(handle: CodexActorHandle) -> Router {
     let admin = Router::new()
         .route("/api/codex/oauth/start", get(api_codex_oauth_start))
         .route("/api/codex/tokens", get(api_codex_tokens))
+        .route("/api/codex/accounts", get(api_codex_accounts))
+        .route("/api/codex/account", delete(api_delete_codex_account))
         .route("/api/codex/logout", post(api_codex_logout))
-        .layer(from_extractor::<RequireAdminAuth>())
-        .with_state(());
+        .layer(from_extractor::<RequireAdminAuth>());
 
     Router::new()
         .route("/codex/oauth/callback", get(api_codex_oauth_callback))
         .merge(admin)
-        .with_state(())
+        .with_state(handle)
 }
@@ -4,7 +4,7 @@ use tower_http::compression::CompressionLayer;
 use crate::{
     api::*,
     gemini_state::GeminiState,
-    middleware::{RequireBearerAuth, RequireQueryKeyAuth},
+    middleware::{RequireBearerAuth, RequireQueryKeyAuth, RequireXApiKeyAuth},
 };
 
 pub fn build_gemini_router(state: GeminiState) -> Router {
@@ -19,6 +19,12 @@ pub fn build_gemini_router(state: GeminiState) -> Router {
         .route("/gemini/vertex/chat/completions", post(api_post_gemini_oai))
         .layer(from_extractor::<RequireBearerAuth>())
         .layer(CompressionLayer::new())
+        .with_state(state.to_owned());
+    let router_claude = Router::new()
+        .route("/gemini/v1/messages", post(api_post_gemini_claude))
+        .route("/gemini/vertex/v1/messages", post(api_post_gemini_claude))
+        .layer(from_extractor::<RequireXApiKeyAuth>())
+        .layer(CompressionLayer::new())
         .with_state(state);
-    router_gemini.merge(router_oai)
+    router_gemini.merge(router_oai).merge(router_claude)
 }
@@ -0,0 +1,371 @@
+use std::collections::{HashSet, VecDeque};
+
+use ractor::{Actor, ActorProcessingErr, ActorRef, RpcReplyPort};
+use serde::Serialize;
+use snafu::{GenerateImplicitData, Location};
+use tracing::{error, info, warn};
+
+use crate::{
+    config::{CLEWDR_CONFIG, ClewdrConfig, CodexAccount},
+    error::ClewdrError,
+};
+
+const INTERVAL: u64 = 300;
+/// Percentage of headroom within which accounts are considered equal
+const HEADROOM_STEP: f64 = 10.0;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CodexStatusInfo {
+    pub valid: Vec<CodexAccount>,
+    pub exhausted: Vec<CodexAccount>,
+    pub invalid: Vec<CodexAccount>,
+}
+
+/// Messages that the CodexActor can handle
+#[derive(Debug)]
+enum CodexActorMessage {
+    /// Return an account, with the time it resets at if it was rate limited
+    Return(CodexAccount, Option<i64>),
+    /// Submit a new account, or new tokens of a known account
+    Submit(CodexAccount),
+    /// Move an account whose tokens can no longer be refreshed out of the pool
+    Invalidate(CodexAccount, String),
+    /// Check for reset accounts
+    CheckReset,
+    /// Request an account
+    Request(RpcReplyPort<Result<CodexAccount, ClewdrError>>),
+    /// Get all account status information
+    GetStatus(RpcReplyPort<CodexStatusInfo>),
+    /// Delete an account by its account id
+    Delete(String, RpcReplyPort<Result<(), ClewdrError>>),
+}
+
+/// CodexActor state - manages the pool of ChatGPT accounts
+#[derive(Debug)]
+struct CodexActorState {
+    valid: VecDeque<CodexAccount>,
+    exhausted: HashSet<CodexAccount>,
+    invalid: HashSet<CodexAccount>,
+}
+
+/// Codex actor that handles account distribution, collection, and status tracking
+struct CodexActor;
+
+impl CodexActor {
+    /// Saves the current state of accounts to the configuration
+    fn save(state: &CodexActorState) {
+        CLEWDR_CONFIG.rcu(|config| {
+            let mut config = ClewdrConfig::clone(config);
+            config.codex.accounts = state
+                .valid
+                .iter()
+                .chain(state.exhausted.iter())
+                .chain(state.invalid.iter())
+                .cloned()
+                .collect();
+            config
+        });
+        tokio::spawn(async move {
+            let result = CLEWDR_CONFIG.load().save().await;
+            match result {
+                Ok(_) => info!("Configuration saved successfully (codex accounts)"),
+                Err(e) => error!("Save task failed: {}", e),
+            }
+        });
+    }
+
+    /// Logs the current state of the pool
+    fn log(state: &CodexActorState) {
+        info!(
+            "Codex accounts valid: {}, exhausted: {}, invalid: {}",
+            state.valid.len(),
+            state.exhausted.len(),
+            state.invalid.len(),
+        );
+    }
+
+    /// Moves accounts that have passed their reset time back to the valid pool
+    fn reset(state: &mut CodexActorState) {
+        let mut reset_accounts = Vec::new();
+        state.exhausted.retain(|account| {
+            let reset_account = account.clone().reset();
+            if reset_account.reset_time.is_none() {
+                reset_accounts.push(reset_account);
+                false
+            } else {
+                true
+            }
+        });
+        if reset_accounts.is_empty() {
+            return;
+        }
+        state.valid.extend(reset_accounts);
+        Self::save(state);
+        Self::log(state);
+    }
+
+    /// Dispatches an account for use
+    /// Accounts with the most headroom are preferred, round robin among similar ones
+    fn dispatch(state: &mut CodexActorState) -> Result<CodexAccount, ClewdrError> {
+        Self::reset(state);
+        if state.valid.is_empty() && state.exhausted.is_empty() {
+            return Err(ClewdrError::BadRequest {
+                msg: "Codex not authenticated. Use /api/codex/oauth/start",
+            });
+        }
+        let headroom = |a: &CodexAccount| {
+            let h = a.usage.as_ref().map_or(100.0, |u| u.headroom());
+            (h / HEADROOM_STEP) as u32
+        };
+        let best = state.valid.iter().map(headroom).max();
+        let mut account = state
+            .valid
+            .iter()
+            .position(|a| Some(headroom(a)) == best)
+            .and_then(|i| state.valid.remove(i))
+            .ok_or(ClewdrError::NoCodexAccountAvailable)?;
+        account.requests += 1;
+        state.valid.push_back(account.clone());
+        Ok(account)
+    }
+
+    /// Collects a returned account, moving it to the exhausted pool if it was rate limited
+    fn collect(state: &mut CodexActorState, account: CodexAccount, reset_time: Option<i64>) {
+        let Some(reset_time) = reset_time else {
+            if let Some(a) = state.valid.iter_mut().find(|a| **a == account) {
+                a.usage = account.usage;
+            }
+            return;
+        };
+        let Some(i) = state.valid.iter().position(|a| *a == account) else {
+            return;
+        };
+        let mut exhausted = state.valid.remove(i).unwrap_or(account);
+        info!(
+            "Codex account {} rate limited until {}",
+            exhausted.id(),
+            reset_time
+        );
+        exhausted.reset_time = Some(reset_time);
+        state.exhausted.insert(exhausted);
+        Self::save(state);
+        Self::log(state);
+    }
+
+    /// Moves an account out of the pool until it is logged in again
+    fn invalidate(state: &mut CodexActorState, account: CodexAccount, reason: String) {
+        let account = match state.valid.iter().position(|a| *a == account) {
+            Some(i) => state.valid.remove(i),
+            None => state.exhausted.take(&account),
+        };
+        let Some(mut account) = account else {
+            return;
+        };
+        warn!("Codex account {} is invalid: {}", account.id(), reason);
+        account.invalid = Some(reason);
+        state.invalid.insert(account);
+        Self::save(state);
+        Self::log(state);
+    }
+
+    /// Accepts a new account, or updates the tokens of a known one
+    /// An invalid account logged in again returns to the pool
+    fn accept(state: &mut CodexActorState, account: CodexAccount) {
+        if !account.is_authenticated() {
+            warn!("Codex account without access token or account id");
+            return;
+        }
+        if let Some(mut a) = state.invalid.take(&account) {
+            a.tokens = account.tokens;
+            a.email = account.email.or(a.email.take());
+            a.invalid = None;
+            state.valid.push_back(a);
+        } else if let Some(a) = state.valid.iter_mut().find(|a| **a == account) {
+            a.tokens = account.tokens;
+            a.email = account.email.or(a.email.take());
+        } else if let Some(mut a) = state.exhausted.take(&account) {
+            a.tokens = account.tokens;
+            a.email = account.email.or(a.email.take());
+            state.exhausted.insert(a);
+        } else {
+            state.valid.push_back(account);
+        }
+        Self::save(state);
+        Self::log(state);
+    }
+
+    /// Creates a report of all account statuses
+    fn report(state: &CodexActorState) -> CodexStatusInfo {
+        CodexStatusInfo {
+            valid: state.valid.clone().into(),
+            exhausted: state.exhausted.iter().cloned().collect(),
+            invalid: state.invalid.iter().cloned().collect(),
+        }
+    }
+
+    /// Deletes an account from all collections
+    fn delete(state: &mut CodexActorState, account_id: &str) -> Result<(), ClewdrError> {
+        let size = state.valid.len() + state.exhausted.len() + state.invalid.len();
+        state.valid.retain(|a| a.id() != account_id);
+        state.exhausted.retain(|a| a.id() != account_id);
+        state.invalid.retain(|a| a.id() != account_id);
+        if state.valid.len() + state.exhausted.len() + state.invalid.len() < size {
+            Self::save(state);
+            Self::log(state);
+            Ok(())
+        } else {
+            Err(ClewdrError::UnexpectedNone {
+                msg: "Delete operation did not find the Codex account",
+            })
+        }
+    }
+}
+
+impl Actor for CodexActor {
+    type Msg = CodexActorMessage;
+    type State = CodexActorState;
+    type Arguments = Vec<CodexAccount>;
+
+    async fn pre_start(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        accounts: Self::Arguments,
+    ) -> Result<Self::State, ActorProcessingErr> {
+        let (invalid, accounts): (Vec<_>, Vec<_>) =
+            accounts.into_iter().partition(|a| a.invalid.is_some());
+        let (exhausted, valid): (Vec<_>, Vec<_>) =
+            accounts.into_iter().partition(|a| a.reset_time.is_some());
+        let state = CodexActorState {
+            valid: valid.into(),
+            exhausted: exhausted.into_iter().collect(),
+            invalid: invalid.into_iter().collect(),
+        };
+        CodexActor::log(&state);
+        Ok(state)
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        message: Self::Msg,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        match message {
+            CodexActorMessage::Return(account, reset_time) => {
+                Self::collect(state, account, reset_time)
+            }
+            CodexActorMessage::Submit(account) => Self::accept(state, account),
+            CodexActorMessage::Invalidate(account, reason) => {
+                Self::invalidate(state, account, reason)
+            }
+            CodexActorMessage::CheckReset => Self::reset(state),
+            CodexActorMessage::Request(reply_port) => {
+                reply_port.send(Self::dispatch(state))?;
+            }
+            CodexActorMessage::GetStatus(reply_port) => {
+                reply_port.send(Self::report(state))?;
+            }
+            CodexActorMessage::Delete(account_id, reply_port) => {
+                reply_port.send(Self::delete(state, &account_id))?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn post_stop(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        state: &mut Self::State,
+    ) -> Result<(), ActorProcessingErr> {
+        CodexActor::save(state);
+        Ok(())
+    }
+}
+
+/// Handle for interacting with the CodexActor
+#[derive(Clone)]
+pub struct CodexActorHandle {
+    actor_ref: ActorRef<CodexActorMessage>,
+}
+
+impl CodexActorHandle {
+    /// Create a new CodexActor with the accounts of the configuration
+    pub async fn start() -> Result<Self, ractor::SpawnErr> {
+        let accounts = CLEWDR_CONFIG.load().codex.accounts.clone();
+        let (actor_ref, _join_handle) = Actor::spawn(None, CodexActor, accounts).await?;
+        let handle = Self { actor_ref };
+        handle.spawn_timeout_checker();
+        Ok(handle)
+    }
+
+    /// Spawns a task that periodically moves reset accounts back to the valid pool
+    fn spawn_timeout_checker(&self) {
+        let actor_ref = self.actor_ref.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(INTERVAL));
+            loop {
+                interval.tick().await;
+                if ractor::cast!(actor_ref, CodexActorMessage::CheckReset).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    fn error(op: &str, e: impl std::fmt::Display) -> ClewdrError {
+        ClewdrError::RactorError {
+            loc: Location::generate(),
+            msg: format!("Failed to communicate with CodexActor for {op} operation: {e}"),
+        }
+    }
+
+    /// Request an account from the pool
+    pub async fn request(&self) -> Result<CodexAccount, ClewdrError> {
+        ractor::call!(self.actor_ref, CodexActorMessage::Request)
+            .map_err(|e| Self::error("request", e))?
+    }
+
+    /// Return an account to the pool, with its reset time if it was rate limited
+    pub async fn return_account(
+        &self,
+        account: CodexAccount,
+        reset_time: Option<i64>,
+    ) -> Result<(), ClewdrError> {
+        ractor::cast!(
+            self.actor_ref,
+            CodexActorMessage::Return(account, reset_time)
+        )
+        .map_err(|e| Self::error("return", e))
+    }
+
+    /// Submit a new account to the pool
+    pub async fn submit(&self, account: CodexAccount) -> Result<(), ClewdrError> {
+        ractor::cast!(self.actor_ref, CodexActorMessage::Submit(account))
+            .map_err(|e| Self::error("submit", e))
+    }
+
+    /// Move an account whose tokens can no longer be refreshed out of the pool
+    pub async fn invalidate(
+        &self,
+        account: CodexAccount,
+        reason: String,
+    ) -> Result<(), ClewdrError> {
+        ractor::cast!(
+            self.actor_ref,
+            CodexActorMessage::Invalidate(account, reason)
+        )
+        .map_err(|e| Self::error("invalidate", e))
+    }
+
+    /// Get status information about all accounts
+    pub async fn get_status(&self) -> Result<CodexStatusInfo, ClewdrError> {
+        ractor::call!(self.actor_ref, CodexActorMessage::GetStatus)
+            .map_err(|e| Self::error("get status", e))
+    }
+
+    /// Delete an account from the pool
+    pub async fn delete(&self, account_id: String) -> Result<(), ClewdrError> {
+        ractor::call!(self.actor_ref, CodexActorMessage::Delete, account_id)
+            .map_err(|e| Self::error("delete", e))?
+    }
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use tracing::{Instrument, error};
+
+use crate::{codex_state::CodexState, config::CLEWDR_CONFIG};
+
+const INTERVAL: u64 = 60;
+
+/// Spawn a background task that keeps Codex access tokens fresh.
+///
+/// Every tick the Codex account pool is walked, and accounts whose access token
+/// expires within `token_refresh_window` minutes are refreshed.
+/// Refreshed accounts are handed back to the Codex actor, which persists them.
+pub fn spawn(state: CodexState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(INTERVAL));
+        loop {
+            interval.tick().await;
+            let window = CLEWDR_CONFIG.load().token_refresh_window;
+            if window == 0 {
+                continue;
+            }
+            let window = Duration::from_secs(window * 60);
+            let Ok(status) = state.handle.get_status().await else {
+                break;
+            };
+            for account in status.valid.into_iter().chain(status.exhausted) {
+                if account.tokens.refresh_token.is_none() || !account.expires_within(window) {
+                    continue;
+                }
+                let span = tracing::info_span!("codex_refresh", "account" = account.id());
+                if let Err(e) = state.refresh_account(account).instrument(span).await {
+                    error!("Background Codex token refresh failed: {}", e);
+                }
+            }
+        }
+    })
+}
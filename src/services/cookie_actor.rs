@@ -8,11 +8,13 @@ use tracing::{error, info, warn};
 
 use crate::persistence::StorageLayer;
 use crate::{
-    config::{CLEWDR_CONFIG, ClewdrConfig, CookieStatus, Reason, UselessCookie},
+    config::{AccountUsage, CLEWDR_CONFIG, ClewdrConfig, CookieStatus, Reason, UselessCookie},
     error::ClewdrError,
 };
 
 const INTERVAL: u64 = 300;
+/// Percentage of headroom within which cookies are considered equal
+const HEADROOM_STEP: f64 = 10.0;
 
 #[derive(Debug, Serialize, Clone)]
 pub struct CookieStatusInfo {
@@ -30,12 +32,18 @@ enum CookieActorMessage {
     Submit(CookieStatus),
     /// Check for timed out Cookies
     CheckReset,
-    /// Request to get a Cookie
-    Request(Option<u64>, RpcReplyPort<Result<CookieStatus, ClewdrError>>),
+    /// Request to get a Cookie, optionally only one capable of the 1M context window
+    Request(
+        Option<u64>,
+        bool,
+        RpcReplyPort<Result<CookieStatus, ClewdrError>>,
+    ),
     /// Get all Cookie status information
     GetStatus(RpcReplyPort<CookieStatusInfo>),
     /// Delete a Cookie
     Delete(CookieStatus, RpcReplyPort<Result<(), ClewdrError>>),
+    /// Record the polled account usage of a Cookie
+    Usage(CookieStatus, AccountUsage),
 }
 
 /// CookieActor state - manages collections of cookies
@@ -115,23 +123,39 @@ impl CookieActor {
     }
 
     /// Dispatches a cookie for use
+    /// When `require_1m` is set, cookies known to lack the 1M context window are skipped
     fn dispatch(
         &self,
         state: &mut CookieActorState,
         hash: Option<u64>,
+        require_1m: bool,
     ) -> Result<CookieStatus, ClewdrError> {
         Self::reset(state, self.storage);
+        let capable = |c: &CookieStatus| !require_1m || c.context_1m != Some(false);
         if let Some(hash) = hash
             && let Some(cookie) = state.moka.get(&hash)
-            && let Some(cookie) = state.valid.iter().find(|&c| c == &cookie)
+            && let Some(cookie) = state.valid.iter().find(|&c| c == &cookie && capable(c))
         {
             // renew moka cache
             state.moka.insert(hash, cookie.clone());
             return Ok(cookie.clone());
         }
+        // prefer the cookies with the most headroom, round robin among similar ones
+        let headroom = |c: &CookieStatus| {
+            let h = c.usage.as_ref().map_or(100.0, |u| u.headroom());
+            (h / HEADROOM_STEP) as u32
+        };
+        let best = state
+            .valid
+            .iter()
+            .filter(|c| capable(c))
+            .map(headroom)
+            .max();
         let cookie = state
             .valid
-            .pop_front()
+            .iter()
+            .position(|c| capable(c) && Some(headroom(c)) == best)
+            .and_then(|i| state.valid.remove(i))
             .ok_or(ClewdrError::NoCookieAvailable)?;
         state.valid.push_back(cookie.clone());
         if let Some(hash) = hash {
@@ -141,63 +165,129 @@ impl CookieActor {
     }
 
     /// Collects a returned cookie and processes it based on the return reason
-    fn collect(state: &mut CookieActorState, mut cookie: CookieStatus, reason: Option<Reason>) {
+    ///
+    /// # Returns
+    /// The stored cookie with the returned fields merged in, if it changed and should be persisted
+    fn collect(
+        state: &mut CookieActorState,
+        cookie: CookieStatus,
+        reason: Option<Reason>,
+    ) -> Option<CookieStatus> {
         let Some(reason) = reason else {
-            // replace the cookie in valid collection
-            if cookie.token.is_some()
-                && let Some(c) = state.valid.iter_mut().find(|c| **c == cookie)
-            {
-                *c = cookie;
-                Self::save(state);
+            // merge what the caller learned into the cookie in valid collection
+            let c = state.valid.iter_mut().find(|c| **c == cookie)?;
+            if !Self::merge(c, cookie) {
+                return None;
             }
-            return;
+            let merged = c.to_owned();
+            Self::save(state);
+            return Some(merged);
         };
-        let mut find_remove = |cookie: &CookieStatus| {
-            state.valid.retain(|c| c != cookie);
+        // the stored cookie is kept, with what the caller learned merged in
+        let mut find_remove = |cookie: CookieStatus| -> CookieStatus {
+            let stored = state
+                .valid
+                .iter()
+                .position(|c| *c == cookie)
+                .and_then(|i| state.valid.remove(i));
+            match stored {
+                Some(mut c) => {
+                    Self::merge(&mut c, cookie);
+                    c
+                }
+                None => cookie,
+            }
         };
-        match reason {
+        let cookie = match reason {
             Reason::NormalPro => {
-                return;
+                return None;
             }
             Reason::TooManyRequest(i) => {
-                find_remove(&cookie);
+                let mut cookie = find_remove(cookie);
                 cookie.reset_time = Some(i);
-                if !state.exhausted.insert(cookie) {
-                    return;
+                if !state.exhausted.insert(cookie.to_owned()) {
+                    return None;
                 }
+                cookie
             }
             Reason::Restricted(i) => {
-                find_remove(&cookie);
+                let mut cookie = find_remove(cookie);
                 cookie.reset_time = Some(i);
-                if !state.exhausted.insert(cookie) {
-                    return;
+                if !state.exhausted.insert(cookie.to_owned()) {
+                    return None;
                 }
+                cookie
             }
             Reason::NonPro => {
-                find_remove(&cookie);
+                let cookie = find_remove(cookie);
                 if !state
                     .invalid
-                    .insert(UselessCookie::new(cookie.cookie, reason))
+                    .insert(UselessCookie::new(cookie.cookie.to_owned(), reason))
                 {
-                    return;
+                    return None;
                 }
+                cookie
             }
             _ => {
-                find_remove(&cookie);
+                let cookie = find_remove(cookie);
                 if !state
                     .invalid
-                    .insert(UselessCookie::new(cookie.cookie, reason))
+                    .insert(UselessCookie::new(cookie.cookie.to_owned(), reason))
                 {
-                    return;
+                    return None;
                 }
+                cookie
             }
-        }
+        };
         Self::save(state);
         Self::log(state);
+        Some(cookie)
+    }
+
+    /// Merges the fields a caller may have changed into a stored cookie
+    ///
+    /// The returned cookie is a snapshot taken when it was dispatched, so fields
+    /// updated meanwhile by other requests or background tasks are kept: a token
+    /// only replaces an older one, an unknown 1M context entitlement never overwrites
+    /// a known one, and usage is only recorded by `record_usage`
+    ///
+    /// # Returns
+    /// Whether the stored cookie was changed
+    fn merge(c: &mut CookieStatus, cookie: CookieStatus) -> bool {
+        let mut changed = false;
+        // tokens and organizations obtained under an outdated preference are dropped
+        let preferred = c.org_preference == cookie.org_preference;
+        if preferred
+            && let Some(token) = cookie.token
+            && c.token
+                .as_ref()
+                .is_none_or(|t| t.expires_at < token.expires_at)
+        {
+            c.token = Some(token);
+            changed = true;
+        }
+        if cookie.context_1m.is_some() && c.context_1m != cookie.context_1m {
+            c.context_1m = cookie.context_1m;
+            changed = true;
+        }
+        if preferred
+            && cookie.org_uuid.is_some()
+            && (c.org_uuid != cookie.org_uuid || c.orgs != cookie.orgs)
+        {
+            c.org_uuid = cookie.org_uuid;
+            c.orgs = cookie.orgs;
+            changed = true;
+        }
+        changed
     }
 
     /// Accepts a new cookie into the valid collection
     fn accept(state: &mut CookieActorState, cookie: CookieStatus) {
+        if Self::update_preference(state, &cookie) {
+            info!("Cookie organization preference updated");
+            Self::save(state);
+            return;
+        }
         if CLEWDR_CONFIG.load().cookie_array.contains(&cookie)
             || CLEWDR_CONFIG
                 .load()
@@ -213,6 +303,51 @@ impl CookieActor {
         Self::log(state);
     }
 
+    /// Records the account usage of a cookie
+    /// A cookie with a used up window is moved to the exhausted pool until it resets
+    fn record_usage(state: &mut CookieActorState, cookie: CookieStatus, usage: AccountUsage) {
+        let reset = usage.exhausted_until();
+        if let Some(c) = state.valid.iter_mut().find(|c| **c == cookie) {
+            c.usage = Some(usage);
+        } else {
+            if let Some(mut c) = state.exhausted.take(&cookie) {
+                c.usage = Some(usage);
+                state.exhausted.insert(c);
+            }
+            return;
+        }
+        if let Some(reset) = reset {
+            info!("Cookie usage exhausted until {}", reset);
+            Self::collect(state, cookie, Some(Reason::TooManyRequest(reset)));
+        }
+    }
+
+    /// Applies the organization preference of a resubmitted cookie
+    /// The token and chosen organization are dropped, so they follow the new preference
+    ///
+    /// # Returns
+    /// Whether a known cookie was updated
+    fn update_preference(state: &mut CookieActorState, cookie: &CookieStatus) -> bool {
+        let update = |c: &mut CookieStatus| {
+            if c.org_preference == cookie.org_preference {
+                return false;
+            }
+            c.org_preference = cookie.org_preference.to_owned();
+            c.org_uuid = None;
+            c.token = None;
+            true
+        };
+        if let Some(c) = state.valid.iter_mut().find(|c| *c == cookie) {
+            return update(c);
+        }
+        if let Some(mut c) = state.exhausted.take(cookie) {
+            let updated = update(&mut c);
+            state.exhausted.insert(c);
+            return updated;
+        }
+        false
+    }
+
     /// Creates a report of all cookie statuses
     fn report(state: &CookieActorState) -> CookieStatusInfo {
         CookieStatusInfo {
@@ -296,23 +431,22 @@ impl Actor for CookieActor {
     ) -> Result<(), ActorProcessingErr> {
         match message {
             CookieActorMessage::Return(cookie, reason) => {
-                let orig = cookie.clone();
                 let r = reason.clone();
-                Self::collect(state, cookie, reason);
+                // persist the merged cookie, not the possibly stale snapshot that was returned
+                let merged = Self::collect(state, cookie, reason);
                 let storage = self.storage;
-                if storage.is_enabled() {
+                if storage.is_enabled()
+                    && let Some(c) = merged
+                {
                     tokio::spawn(async move {
                         match r {
-                            None => {
-                                let _ = storage.persist_cookie_upsert(&orig).await;
-                            }
-                            Some(Reason::TooManyRequest(ts)) | Some(Reason::Restricted(ts)) => {
-                                let mut c = orig.clone();
-                                c.reset_time = Some(ts);
+                            None
+                            | Some(Reason::TooManyRequest(_))
+                            | Some(Reason::Restricted(_)) => {
                                 let _ = storage.persist_cookie_upsert(&c).await;
                             }
                             Some(reason) => {
-                                let u = UselessCookie::new(orig.cookie.clone(), reason);
+                                let u = UselessCookie::new(c.cookie.clone(), reason);
                                 let _ = storage.persist_wasted_upsert(&u).await;
                             }
                         }
@@ -334,14 +468,17 @@ impl Actor for CookieActor {
             CookieActorMessage::CheckReset => {
                 Self::reset(state, self.storage);
             }
-            CookieActorMessage::Request(cache_hash, reply_port) => {
-                let result = self.dispatch(state, cache_hash);
+            CookieActorMessage::Request(cache_hash, require_1m, reply_port) => {
+                let result = self.dispatch(state, cache_hash, require_1m);
                 reply_port.send(result)?;
             }
             CookieActorMessage::GetStatus(reply_port) => {
                 let status_info = Self::report(state);
                 reply_port.send(status_info)?;
             }
+            CookieActorMessage::Usage(cookie, usage) => {
+                Self::record_usage(state, cookie, usage);
+            }
             CookieActorMessage::Delete(cookie, reply_port) => {
                 let result = Self::delete(state, cookie.clone());
                 let ok = result.is_ok();
@@ -414,11 +551,28 @@ impl CookieActorHandle {
 
     /// Request a cookie from the cookie actor
     pub async fn request(&self, cache_hash: Option<u64>) -> Result<CookieStatus, ClewdrError> {
-        ractor::call!(self.actor_ref, CookieActorMessage::Request, cache_hash).map_err(|e| {
-            ClewdrError::RactorError {
-                loc: Location::generate(),
-                msg: format!("Failed to communicate with CookieActor for request operation: {e}"),
-            }
+        self.request_with(cache_hash, false).await
+    }
+
+    /// Request a cookie capable of the 1M context window from the cookie actor
+    pub async fn request_1m(&self, cache_hash: Option<u64>) -> Result<CookieStatus, ClewdrError> {
+        self.request_with(cache_hash, true).await
+    }
+
+    async fn request_with(
+        &self,
+        cache_hash: Option<u64>,
+        require_1m: bool,
+    ) -> Result<CookieStatus, ClewdrError> {
+        ractor::call!(
+            self.actor_ref,
+            CookieActorMessage::Request,
+            cache_hash,
+            require_1m
+        )
+        .map_err(|e| ClewdrError::RactorError {
+            loc: Location::generate(),
+            msg: format!("Failed to communicate with CookieActor for request operation: {e}"),
         })?
     }
 
@@ -458,6 +612,20 @@ impl CookieActorHandle {
         })
     }
 
+    /// Records the polled account usage of a cookie
+    pub async fn update_usage(
+        &self,
+        cookie: CookieStatus,
+        usage: AccountUsage,
+    ) -> Result<(), ClewdrError> {
+        ractor::cast!(self.actor_ref, CookieActorMessage::Usage(cookie, usage)).map_err(|e| {
+            ClewdrError::RactorError {
+                loc: Location::generate(),
+                msg: format!("Failed to communicate with CookieActor for usage operation: {e}"),
+            }
+        })
+    }
+
     /// Delete a cookie from the cookie actor
     pub async fn delete_cookie(&self, cookie: CookieStatus) -> Result<(), ClewdrError> {
         ractor::call!(self.actor_ref, CookieActorMessage::Delete, cookie).map_err(|e| {
@@ -0,0 +1,247 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{Instrument, debug, info, warn};
+
+use crate::{
+    claude_web_state::{CONVERSATION_PREFIX, ClaudeWebState},
+    config::{CLEWDR_CONFIG, CookieStatus, Reason},
+    error::ClewdrError,
+    services::cookie_actor::CookieActorHandle,
+};
+
+/// Maximum number of conversations deleted in one request
+const BATCH_SIZE: usize = 50;
+/// Time to wait for more conversations before deleting a batch
+const BATCH_WINDOW: Duration = Duration::from_secs(2);
+/// Number of attempts before a deletion is given up
+const MAX_ATTEMPTS: u32 = 5;
+/// Seconds between two sweeps of preserved conversations
+const SWEEP_INTERVAL: u64 = 60 * 60;
+/// Number of conversations listed per page during a sweep
+const SWEEP_PAGE_SIZE: usize = 100;
+
+/// A claude.ai conversation waiting for deletion
+#[derive(Clone, Debug)]
+pub struct CleanupJob {
+    pub cookie: CookieStatus,
+    pub org_uuid: String,
+    pub conv_uuid: String,
+    attempts: u32,
+}
+
+impl CleanupJob {
+    pub fn new(cookie: CookieStatus, org_uuid: String, conv_uuid: String) -> Self {
+        Self {
+            cookie,
+            org_uuid,
+            conv_uuid,
+            attempts: 0,
+        }
+    }
+}
+
+type Queue = (
+    UnboundedSender<CleanupJob>,
+    Mutex<Option<UnboundedReceiver<CleanupJob>>>,
+);
+
+static QUEUE: LazyLock<Queue> = LazyLock::new(|| {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (tx, Mutex::new(Some(rx)))
+});
+
+/// Queues a conversation for deletion by the janitor
+pub fn enqueue(job: CleanupJob) {
+    if QUEUE.0.send(job).is_err() {
+        warn!("Janitor queue is closed, conversation is leaked");
+    }
+}
+
+/// Spawn the background janitor that deletes claude.ai conversations.
+///
+/// Queued conversations are deleted in batches per cookie and organization,
+/// failed deletions are retried with backoff.
+/// When `preserve_chats` is on, conversations created by ClewdR that were not updated
+/// for `preserve_chats_ttl` hours are swept periodically.
+pub fn spawn(handle: CookieActorHandle) -> Vec<tokio::task::JoinHandle<()>> {
+    let mut handles = Vec::new();
+    let rx = QUEUE.1.lock().ok().and_then(|mut rx| rx.take());
+    if let Some(mut rx) = rx {
+        let h = handle.to_owned();
+        handles.push(tokio::spawn(async move {
+            while let Some(batch) = next_batch(&mut rx).await {
+                let mut groups: HashMap<(String, String), Vec<CleanupJob>> = HashMap::new();
+                for job in batch {
+                    groups
+                        .entry((job.cookie.cookie.to_string(), job.org_uuid.to_owned()))
+                        .or_default()
+                        .push(job);
+                }
+                for jobs in groups.into_values() {
+                    delete_batch(&h, jobs).await;
+                }
+            }
+        }));
+    }
+    handles.push(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(SWEEP_INTERVAL));
+        loop {
+            interval.tick().await;
+            let config = CLEWDR_CONFIG.load();
+            if !config.preserve_chats || config.preserve_chats_ttl == 0 {
+                continue;
+            }
+            let ttl = chrono::Duration::hours(config.preserve_chats_ttl as i64);
+            let Ok(status) = handle.get_status().await else {
+                break;
+            };
+            for cookie in status.valid {
+                let span = tracing::info_span!("janitor", "cookie" = cookie.cookie.ellipse());
+                if let Err(e) = sweep(&handle, cookie, ttl).instrument(span).await {
+                    warn!("Failed to sweep conversations: {}", e);
+                }
+            }
+        }
+    }));
+    handles
+}
+
+/// Waits for a job, then collects more until the batch is full or the window closes
+async fn next_batch(rx: &mut UnboundedReceiver<CleanupJob>) -> Option<Vec<CleanupJob>> {
+    let mut batch = vec![rx.recv().await?];
+    let window = tokio::time::sleep(BATCH_WINDOW);
+    tokio::pin!(window);
+    while batch.len() < BATCH_SIZE {
+        tokio::select! {
+            Some(job) = rx.recv() => batch.push(job),
+            _ = &mut window => break,
+        }
+    }
+    Some(batch)
+}
+
+/// Deletes conversations sharing a cookie and organization, requeueing them on failure
+async fn delete_batch(handle: &CookieActorHandle, jobs: Vec<CleanupJob>) {
+    let Some(first) = jobs.first() else {
+        return;
+    };
+    let uuids = jobs
+        .iter()
+        .map(|j| j.conv_uuid.to_owned())
+        .collect::<Vec<_>>();
+    let mut state = ClaudeWebState::new(handle.to_owned());
+    let res = match state.set_cookie(first.cookie.to_owned()) {
+        Ok(()) => state.delete_chats(&first.org_uuid, &uuids).await,
+        Err(e) => Err(e),
+    };
+    let Err(e) = res else {
+        debug!("Deleted {} conversations", uuids.len());
+        return;
+    };
+    match e {
+        ClewdrError::InvalidCookie {
+            reason: Reason::Banned | Reason::Disabled | Reason::Null,
+        } => {
+            warn!(
+                "Cookie is no longer valid, dropping {} conversations",
+                uuids.len()
+            );
+            return;
+        }
+        ClewdrError::InvalidCookie {
+            reason: Reason::TooManyRequest(reset) | Reason::Restricted(reset),
+        } => {
+            // not a failed attempt, wait for the cookie to be usable again
+            let delay = (reset - Utc::now().timestamp()).max(0) as u64;
+            warn!(
+                "Cookie is limited, retrying {} conversations in {}s",
+                uuids.len(),
+                delay
+            );
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(delay)).await;
+                jobs.into_iter().for_each(enqueue);
+            });
+            return;
+        }
+        _ => warn!("Failed to delete {} conversations: {}", uuids.len(), e),
+    }
+    for mut job in jobs {
+        job.attempts += 1;
+        if job.attempts >= MAX_ATTEMPTS {
+            warn!("Giving up on deleting conversation {}", job.conv_uuid);
+            continue;
+        }
+        let delay = Duration::from_secs(5 << job.attempts);
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            enqueue(job);
+        });
+    }
+}
+
+/// Queues the ClewdR conversations of a cookie that were not updated within `ttl`
+async fn sweep(
+    handle: &CookieActorHandle,
+    cookie: CookieStatus,
+    ttl: chrono::Duration,
+) -> Result<(), ClewdrError> {
+    let mut state = ClaudeWebState::new(handle.to_owned());
+    state.set_cookie(cookie.to_owned())?;
+    state.bootstrap().await?;
+    let Some(org_uuid) = state.org_uuid.to_owned() else {
+        return Ok(());
+    };
+    let deadline = Utc::now() - ttl;
+    let mut chats = Vec::new();
+    let mut seen = HashSet::new();
+    loop {
+        let page = state
+            .list_chats(&org_uuid, SWEEP_PAGE_SIZE, chats.len())
+            .await?;
+        let len = page.len();
+        // stop if the listing ignores the offset and repeats a page
+        let before = seen.len();
+        seen.extend(
+            page.iter()
+                .filter_map(|c| c["uuid"].as_str().map(ToString::to_string)),
+        );
+        chats.extend(page);
+        if len < SWEEP_PAGE_SIZE || seen.len() == before {
+            break;
+        }
+    }
+    let stale = chats
+        .into_iter()
+        .filter(|c| {
+            c["name"]
+                .as_str()
+                .is_some_and(|n| n.starts_with(CONVERSATION_PREFIX))
+        })
+        .filter(|c| {
+            c["updated_at"]
+                .as_str()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .is_some_and(|t| t < deadline)
+        })
+        .filter_map(|c| c["uuid"].as_str().map(ToString::to_string))
+        .collect::<Vec<_>>();
+    if stale.is_empty() {
+        return Ok(());
+    }
+    info!("Sweeping {} expired conversations", stale.len());
+    for conv_uuid in stale {
+        enqueue(CleanupJob::new(
+            cookie.to_owned(),
+            org_uuid.to_owned(),
+            conv_uuid,
+        ));
+    }
+    Ok(())
+}
@@ -1,6 +1,11 @@
 pub mod cli_token_actor;
+pub mod codex_actor;
+pub mod codex_refresh;
 pub mod cookie_actor;
+pub mod janitor;
 pub mod key_actor;
 pub mod sync;
+pub mod token_refresh;
 #[cfg(feature = "portable")]
 pub mod update;
+pub mod usage;
@@ -84,8 +84,7 @@ pub fn spawn(
                 if !cur_inv.contains(&key) {
                     let tmp = CookieStatus {
                         cookie: u.cookie.clone(),
-                        token: None,
-                        reset_time: None,
+                        ..Default::default()
                     };
                     let _ = c_handle.return_cookie(tmp, Some(u.reason.clone())).await;
                 }
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use tracing::{Instrument, error, info};
+
+use crate::{
+    claude_code_state::ClaudeCodeState,
+    config::{CLEWDR_CONFIG, CookieStatus},
+    error::ClewdrError,
+};
+
+const INTERVAL: u64 = 60;
+
+/// Spawn a background task that keeps Claude Code OAuth tokens fresh.
+///
+/// Every tick the valid cookie pool is walked: cookies without a token get one minted,
+/// and tokens expiring within `token_refresh_window` minutes are refreshed.
+/// Updated cookies are handed back to the cookie actor, which persists them.
+pub fn spawn(state: ClaudeCodeState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(INTERVAL));
+        loop {
+            interval.tick().await;
+            let window = CLEWDR_CONFIG.load().token_refresh_window;
+            if window == 0 {
+                continue;
+            }
+            let window = Duration::from_secs(window * 60);
+            let Ok(status) = state.cookie_actor_handle.get_status().await else {
+                break;
+            };
+            for cookie in status.valid {
+                let needs_refresh = cookie
+                    .token
+                    .as_ref()
+                    .is_none_or(|t| t.expires_within(window));
+                if !needs_refresh {
+                    continue;
+                }
+                let span = tracing::info_span!("token_refresh", "cookie" = cookie.cookie.ellipse());
+                let mut state = state.to_owned();
+                if let Err(e) = refresh(&mut state, cookie).instrument(span).await {
+                    error!("Background token refresh failed: {}", e);
+                    if let ClewdrError::InvalidCookie { reason } = e {
+                        state.return_cookie(Some(reason)).await;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Mints or refreshes the token of a single cookie and returns it to the pool
+async fn refresh(state: &mut ClaudeCodeState, cookie: CookieStatus) -> Result<(), ClewdrError> {
+    state.set_cookie(cookie)?;
+    if state.cookie.as_ref().is_some_and(|c| c.token.is_some()) {
+        info!("Token expiring soon, refreshing in background");
+        state.renew_token().await?;
+    } else {
+        info!("No token found, minting in background");
+        let org = state.get_organization().await?;
+        let code_res = state.exchange_code(&org).await?;
+        state.exchange_token(code_res).await?;
+    }
+    state.return_cookie(None).await;
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use tracing::{Instrument, debug, warn};
+
+use crate::{
+    claude_web_state::ClaudeWebState,
+    config::{CLEWDR_CONFIG, CookieStatus},
+    error::ClewdrError,
+    services::cookie_actor::CookieActorHandle,
+};
+
+/// Seconds to wait before checking the config again while polling is disabled
+const IDLE_INTERVAL: u64 = 60;
+
+/// Spawn a background task that polls the claude.ai account usage of every cookie.
+///
+/// Every `usage_poll_interval` minutes the valid and exhausted cookies are walked
+/// and the utilization of their rate limit windows is handed to the cookie actor,
+/// which prefers accounts with the most headroom when dispatching.
+pub fn spawn(handle: CookieActorHandle) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let interval = CLEWDR_CONFIG.load().usage_poll_interval;
+            if interval == 0 {
+                tokio::time::sleep(Duration::from_secs(IDLE_INTERVAL)).await;
+                continue;
+            }
+            let Ok(status) = handle.get_status().await else {
+                break;
+            };
+            for cookie in status.valid.into_iter().chain(status.exhausted) {
+                let span = tracing::info_span!("usage", "cookie" = cookie.cookie.ellipse());
+                let mut state = ClaudeWebState::new(handle.to_owned());
+                if let Err(e) = poll(&mut state, cookie).instrument(span).await {
+                    warn!("Failed to poll account usage: {}", e);
+                    if let ClewdrError::InvalidCookie { reason } = e {
+                        state.return_cookie(Some(reason)).await;
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(interval * 60)).await;
+        }
+    })
+}
+
+/// Fetches the usage of a single cookie and records it in the cookie actor
+async fn poll(state: &mut ClaudeWebState, cookie: CookieStatus) -> Result<(), ClewdrError> {
+    state.set_cookie(cookie.to_owned())?;
+    let org_uuid = match cookie.org_uuid.to_owned() {
+        Some(uuid) => uuid,
+        None => {
+            state.bootstrap().await?;
+            let Some(uuid) = state.org_uuid.to_owned() else {
+                return Ok(());
+            };
+            uuid
+        }
+    };
+    let usage = state.fetch_usage(&org_uuid).await?;
+    debug!("Account headroom: {:.0}%", usage.headroom());
+    state.cookie_actor_handle.update_usage(cookie, usage).await
+}
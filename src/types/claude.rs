@@ -77,7 +77,11 @@ impl CreateMessageParams {
                 MessageContent::Blocks { ref content } => content
                     .iter()
                     .map(|block| match block {
-                        ContentBlock::Text { text } => text,
+                        ContentBlock::Text { text, .. } => text,
+                        ContentBlock::Document {
+                            source: DocumentSource::Text { data, .. },
+                            ..
+                        } => data,
                         _ => "",
                     })
                     .collect::<String>(),
@@ -205,12 +209,38 @@ pub enum MessageContent {
 pub enum ContentBlock {
     /// Text content
     #[serde(rename = "text")]
-    Text { text: String },
+    Text {
+        text: String,
+        /// Sources cited by the text, such as web search results
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        citations: Option<Vec<serde_json::Value>>,
+    },
     /// Image content
     #[serde(rename = "image")]
     Image { source: ImageSource },
     #[serde(rename = "image_url")]
     ImageUrl { image_url: ImageUrl },
+    /// Document content
+    #[serde(rename = "document")]
+    Document {
+        source: DocumentSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        citations: Option<serde_json::Value>,
+    },
+    /// Extended thinking content
+    #[serde(rename = "thinking")]
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: String,
+    },
+    /// Thinking content encrypted by the safety system
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
     /// Tool use content
     #[serde(rename = "tool_use")]
     ToolUse {
@@ -218,14 +248,61 @@ pub enum ContentBlock {
         name: String,
         input: serde_json::Value,
     },
+    /// Server tool use content, such as a web search
+    #[serde(rename = "server_tool_use")]
+    ServerToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// Web search result content
+    #[serde(rename = "web_search_tool_result")]
+    WebSearchToolResult {
+        tool_use_id: String,
+        content: serde_json::Value,
+    },
     /// Tool result content
     #[serde(rename = "tool_result")]
     ToolResult {
         tool_use_id: String,
-        content: String,
+        #[serde(default)]
+        content: ToolResultContent,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        is_error: Option<bool>,
     },
 }
 
+/// Content of a tool result, either plain text or content blocks
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum ToolResultContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl Default for ToolResultContent {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl ToolResultContent {
+    /// Joins the text of the result, ignoring non-text blocks
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.to_owned(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|b| match b {
+                    ContentBlock::Text { text, .. } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
 /// Source of an image
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct ImageSource {
@@ -238,6 +315,24 @@ pub struct ImageSource {
     pub data: String,
 }
 
+/// Source of a document
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[serde(tag = "type")]
+pub enum DocumentSource {
+    /// Base64-encoded file, e.g. a PDF
+    #[serde(rename = "base64")]
+    Base64 { media_type: String, data: String },
+    /// Plain text
+    #[serde(rename = "text")]
+    Text { media_type: String, data: String },
+    /// Content blocks
+    #[serde(rename = "content")]
+    Content { content: Vec<ContentBlock> },
+    /// Remote file
+    #[serde(rename = "url")]
+    Url { url: String },
+}
+
 // oai image
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct ImageUrl {
@@ -269,6 +364,9 @@ pub enum ToolChoice {
     /// Model must use a specific tool
     #[serde(rename = "tool")]
     Tool { name: String },
+    /// Model must not use tools
+    #[serde(rename = "none")]
+    None,
 }
 
 /// Message metadata
@@ -308,7 +406,7 @@ impl CreateMessageResponse {
             .content
             .iter()
             .map(|block| match block {
-                ContentBlock::Text { text } => text,
+                ContentBlock::Text { text, .. } => text,
                 ContentBlock::Image { source } => &source.data,
                 _ => "",
             })
@@ -387,7 +485,10 @@ impl Message {
 impl ContentBlock {
     /// Create a new text block
     pub fn text(text: impl Into<String>) -> Self {
-        Self::Text { text: text.into() }
+        Self::Text {
+            text: text.into(),
+            citations: None,
+        }
     }
 
     /// Create a new image block
@@ -471,6 +572,8 @@ pub enum ContentBlockDelta {
     ThinkingDelta { thinking: String },
     #[serde(rename = "signature_delta")]
     SignatureDelta { signature: String },
+    #[serde(rename = "citations_delta")]
+    CitationsDelta { citation: Value },
 }
 
 #[derive(Debug, Deserialize, Serialize, Default)]
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use crate::types::claude::ImageSource;
+use crate::types::claude::{DocumentSource, ImageSource};
 
 /// Claude.ai attachment
 #[derive(Deserialize, Serialize, Debug)]
@@ -27,6 +27,28 @@ impl Attachment {
             file_type: "txt".to_string(),
         }
     }
+
+    /// Creates an Attachment for a text document
+    ///
+    /// # Arguments
+    /// * `file_name` - Name of the document
+    /// * `file_type` - Media type of the document
+    /// * `content` - The text content of the document
+    pub fn document(file_name: String, file_type: String, content: String) -> Self {
+        Attachment {
+            file_size: content.len() as u64,
+            extracted_content: content,
+            file_name,
+            file_type,
+        }
+    }
+}
+
+/// A document to be uploaded to claude.ai
+#[derive(Debug)]
+pub struct WebDocument {
+    pub title: Option<String>,
+    pub source: DocumentSource,
 }
 
 /// Request body to be sent to the Claude.ai
@@ -42,7 +64,11 @@ pub struct WebRequestBody {
     pub timezone: String,
     #[serde(skip)]
     pub images: Vec<ImageSource>,
+    #[serde(skip)]
+    pub documents: Vec<WebDocument>,
     pub tools: Vec<Tool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_message_uuid: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
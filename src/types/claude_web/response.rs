@@ -1,39 +1,109 @@
-use axum::{Json, response::IntoResponse};
+use std::{
+    collections::{BTreeMap, HashMap},
+    pin::pin,
+};
+
+use axum::{Json, body::Body, response::IntoResponse};
 use bytes::Bytes;
 use eventsource_stream::{EventStream, Eventsource};
 use futures::{Stream, TryStreamExt};
-use serde::Deserialize;
+use serde_json::Value;
 
 use crate::{
-    claude_web_state::ClaudeWebState,
+    claude_web_state::{ClaudeWebState, SearchStream, parse_tool_calls, transform_web_stream},
+    config::CLEWDR_CONFIG,
     error::ClewdrError,
-    types::claude::{ContentBlock, CreateMessageResponse, Message, Role},
+    types::claude::{
+        ContentBlock, ContentBlockDelta, CreateMessageResponse, Message, Role, StopReason,
+        StreamEvent,
+    },
     utils::{forward_response, print_out_text},
 };
 
-/// Merges server-sent events (SSE) from a stream into a single string
-/// Extracts and concatenates completion data from events
+/// Merges server-sent events (SSE) from a stream into content blocks
+/// Concatenates the completion data of raw rendering, or rebuilds the blocks
+/// of messages rendering, including web search results and citations
 ///
 /// # Arguments
 /// * `stream` - Event stream to process
 ///
 /// # Returns
-/// Combined completion text from all events
+/// Content blocks of the reply
 pub async fn merge_sse(
     stream: EventStream<impl Stream<Item = Result<Bytes, wreq::Error>>>,
-) -> Result<String, ClewdrError> {
-    #[derive(Deserialize)]
-    struct Data {
-        completion: String,
+) -> Result<Vec<ContentBlock>, ClewdrError> {
+    let mut stream = pin!(stream);
+    let mut completion = String::new();
+    let mut search = SearchStream::default();
+    let mut blocks = BTreeMap::<usize, ContentBlock>::new();
+    let mut inputs = HashMap::<usize, String>::new();
+    while let Some(event) = stream.try_next().await? {
+        let Ok(data) = serde_json::from_str::<Value>(&event.data) else {
+            continue;
+        };
+        if let Some(text) = data["completion"].as_str() {
+            completion += text;
+            continue;
+        }
+        let Some(data) = search.process(data) else {
+            continue;
+        };
+        let Ok(event) = serde_json::from_value::<StreamEvent>(data) else {
+            continue;
+        };
+        match event {
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                blocks.insert(index, content_block);
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                match (blocks.get_mut(&index), delta) {
+                    (
+                        Some(ContentBlock::Text { text, .. }),
+                        ContentBlockDelta::TextDelta { text: t },
+                    ) => text.push_str(&t),
+                    (
+                        Some(ContentBlock::Text { citations, .. }),
+                        ContentBlockDelta::CitationsDelta { citation },
+                    ) => citations.get_or_insert_default().push(citation),
+                    (
+                        Some(ContentBlock::Thinking { thinking, .. }),
+                        ContentBlockDelta::ThinkingDelta { thinking: t },
+                    ) => thinking.push_str(&t),
+                    (
+                        Some(ContentBlock::Thinking { signature, .. }),
+                        ContentBlockDelta::SignatureDelta { signature: s },
+                    ) => signature.push_str(&s),
+                    (_, ContentBlockDelta::InputJsonDelta { partial_json }) => {
+                        inputs.entry(index).or_default().push_str(&partial_json)
+                    }
+                    _ => {}
+                }
+            }
+            StreamEvent::ContentBlockStop { index } => {
+                let input = inputs
+                    .remove(&index)
+                    .and_then(|json| serde_json::from_str::<Value>(&json).ok());
+                if let (
+                    Some(
+                        ContentBlock::ToolUse { input, .. }
+                        | ContentBlock::ServerToolUse { input, .. },
+                    ),
+                    Some(parsed),
+                ) = (blocks.get_mut(&index), input)
+                {
+                    *input = parsed;
+                }
+            }
+            _ => {}
+        }
+    }
+    if blocks.is_empty() {
+        return Ok(vec![ContentBlock::text(completion)]);
     }
-    Ok(stream
-        .try_filter_map(async |event| {
-            Ok(serde_json::from_str::<Data>(&event.data)
-                .map(|data| data.completion)
-                .ok())
-        })
-        .try_collect()
-        .await?)
+    Ok(blocks.into_values().collect())
 }
 
 impl<S> From<S> for Message
@@ -48,10 +118,7 @@ where
     /// # Returns
     /// * `Message` - A message with assistant role and text content
     fn from(str: S) -> Self {
-        Message::new_blocks(
-            Role::Assistant,
-            vec![ContentBlock::Text { text: str.into() }],
-        )
+        Message::new_blocks(Role::Assistant, vec![ContentBlock::text(str)])
     }
 }
 
@@ -72,18 +139,41 @@ impl ClaudeWebState {
         wreq_res: wreq::Response,
     ) -> Result<axum::response::Response, ClewdrError> {
         if self.stream {
-            return forward_response(wreq_res);
+            let res = if self.emulate_tools || CLEWDR_CONFIG.load().web_search {
+                transform_web_stream(wreq_res, self.emulate_tools)
+            } else {
+                forward_response(wreq_res)?
+            };
+            return Ok(
+                res.map(|body| Body::from_stream(self.watch_limits(body.into_data_stream())))
+            );
         }
 
-        let stream = wreq_res.bytes_stream();
+        let stream = self.watch_limits(wreq_res.bytes_stream());
         let stream = stream.eventsource();
-        let text = merge_sse(stream).await?;
+        let mut content = merge_sse(stream).await?;
+        let text = content
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<String>();
         print_out_text(text.to_owned(), "claude_web_non_stream.txt");
-        Ok(Json(CreateMessageResponse::text(
-            text,
-            Default::default(),
-            self.usage.to_owned(),
-        ))
-        .into_response())
+        let mut res =
+            CreateMessageResponse::text(String::new(), Default::default(), self.usage.to_owned());
+        if self.emulate_tools {
+            // search blocks are kept, the text is split into text and tool calls
+            content.retain(|b| !matches!(b, ContentBlock::Text { .. }));
+            content.extend(parse_tool_calls(&text));
+            if content
+                .iter()
+                .any(|b| matches!(b, ContentBlock::ToolUse { .. }))
+            {
+                res.stop_reason = Some(StopReason::ToolUse);
+            }
+        }
+        res.content = content;
+        Ok(Json(res).into_response())
     }
 }
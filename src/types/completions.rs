@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    claude::{
+        CreateMessageParams as ClaudeCreateMessageParams, Message, MessageContent, Metadata, Role,
+        default_max_tokens,
+    },
+    oai::StreamOptions,
+};
+
+/// A string or a list of strings, as accepted by legacy completion fields
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum StringOrList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl StringOrList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            StringOrList::One(s) => vec![s],
+            StringOrList::Many(v) => v,
+        }
+    }
+}
+
+/// Parameters of an OpenAI legacy `/v1/completions` request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateCompletionParams {
+    /// Model to use
+    pub model: String,
+    /// Prompt to complete, a list may hold a single prompt
+    pub prompt: StringOrList,
+    /// Maximum number of tokens to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Temperature for response generation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Top-p sampling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Custom stop sequences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<StringOrList>,
+    /// Whether to stream the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// Options for streaming responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    /// Number of completions to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+}
+
+impl From<CreateCompletionParams> for ClaudeCreateMessageParams {
+    fn from(params: CreateCompletionParams) -> Self {
+        let prompt = params.prompt.into_vec().concat();
+        Self {
+            // the legacy default of 16 tokens is too short for a chat model
+            max_tokens: params.max_tokens.unwrap_or_else(default_max_tokens),
+            messages: vec![Message::new_text(Role::User, prompt)],
+            model: params.model,
+            temperature: params.temperature,
+            top_p: params.top_p,
+            stop_sequences: params.stop.map(StringOrList::into_vec),
+            stream: params.stream,
+            n: params.n,
+            ..Default::default()
+        }
+    }
+}
+
+/// Parameters of an Anthropic legacy `/v1/complete` request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateCompleteParams {
+    /// Model to use
+    pub model: String,
+    /// Prompt with `\n\nHuman:` and `\n\nAssistant:` turns
+    pub prompt: String,
+    /// Maximum number of tokens to generate
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens_to_sample: u32,
+    /// Custom stop sequences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    /// Temperature for response generation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Top-k sampling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Top-p sampling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Whether to stream the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// Request metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+const HUMAN_PROMPT: &str = "\n\nHuman:";
+const AI_PROMPT: &str = "\n\nAssistant:";
+
+/// Appends text to the last message if it has the same role, or starts a new turn
+fn push_text(messages: &mut Vec<Message>, role: Role, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(Message {
+        role: last_role,
+        content: MessageContent::Text { content },
+    }) = messages.last_mut()
+        && *last_role == role
+    {
+        content.push_str("\n\n");
+        content.push_str(text);
+        return;
+    }
+    messages.push(Message::new_text(role, text));
+}
+
+/// Splits a legacy prompt into a system prompt and conversation turns
+///
+/// Text before the first turn becomes the system prompt. A trailing
+/// `\n\nAssistant:` with text is kept as a prefill of the reply.
+fn parse_prompt(prompt: &str) -> (Option<String>, Vec<Message>) {
+    let prompt = match prompt.strip_prefix("Human:") {
+        Some(rest) => format!("{HUMAN_PROMPT}{rest}"),
+        None => prompt.to_string(),
+    };
+    let mut system = None;
+    let mut messages = vec![];
+    let mut role = None;
+    let mut rest = prompt.as_str();
+    loop {
+        let next = [(HUMAN_PROMPT, Role::User), (AI_PROMPT, Role::Assistant)]
+            .into_iter()
+            .filter_map(|(marker, r)| rest.find(marker).map(|i| (i, marker, r)))
+            .min_by_key(|(i, ..)| *i);
+        let text = match next {
+            Some((i, ..)) => &rest[..i],
+            None => rest,
+        };
+        let text = text.trim();
+        match role {
+            None if !text.is_empty() => system = Some(text.to_string()),
+            None => {}
+            Some(r) => push_text(&mut messages, r, text),
+        }
+        let Some((i, marker, r)) = next else {
+            break;
+        };
+        rest = &rest[i + marker.len()..];
+        role = Some(r);
+    }
+    (system, messages)
+}
+
+impl From<CreateCompleteParams> for ClaudeCreateMessageParams {
+    fn from(params: CreateCompleteParams) -> Self {
+        let (system, messages) = parse_prompt(&params.prompt);
+        Self {
+            max_tokens: params.max_tokens_to_sample,
+            system: system.map(serde_json::Value::String),
+            messages,
+            model: params.model,
+            temperature: params.temperature,
+            stop_sequences: params.stop_sequences,
+            stream: params.stream,
+            top_k: params.top_k,
+            top_p: params.top_p,
+            metadata: params.metadata,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prompt() {
+        let (system, messages) = parse_prompt(
+            "Be brief.\n\nHuman: Hi\n\nHuman: there\n\nAssistant: Hello\n\nHuman: Bye\n\nAssistant:",
+        );
+        assert_eq!(system.as_deref(), Some("Be brief."));
+        let turns = messages
+            .iter()
+            .map(|m| match &m.content {
+                MessageContent::Text { content } => (m.role, content.as_str()),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            turns,
+            [
+                (Role::User, "Hi\n\nthere"),
+                (Role::Assistant, "Hello"),
+                (Role::User, "Bye"),
+            ]
+        );
+    }
+}
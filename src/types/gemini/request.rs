@@ -1,6 +1,16 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 
+use crate::{
+    error::ClewdrError,
+    types::claude::{
+        ContentBlock, CreateMessageParams as ClaudeCreateMessageParams, MessageContent,
+        Role as ClaudeRole, ToolChoice,
+    },
+};
+
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Default)]
 #[allow(non_camel_case_types)]
 pub enum Role {
@@ -34,10 +44,10 @@ pub struct ExecutableCode {
 #[derive(Serialize, Deserialize, Clone, Debug, Hash)]
 pub struct FunctionCall {
     #[serde(skip_serializing_if = "Option::is_none")]
-    id: Option<String>,
-    name: String,
+    pub id: Option<String>,
+    pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    args: Option<Value>,
+    pub args: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Hash)]
@@ -97,11 +107,22 @@ pub enum Part {
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Hash)]
+/// A part of a chat turn, with the thought signature Gemini attaches to it
+#[derive(Serialize, Deserialize, Clone, Debug, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentPart {
+    #[serde(flatten)]
+    pub part: Part,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thought_signature: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash, Default)]
 pub struct Chat {
     #[serde(default)]
-    role: Role,
-    parts: Vec<Part>,
+    pub role: Role,
+    #[serde(default)]
+    pub parts: Vec<ContentPart>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash)]
@@ -126,6 +147,8 @@ pub struct GeminiRequestBody {
     pub system_instruction: Option<SystemInstruction>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_config: Option<Value>,
     pub contents: Vec<Chat>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub generation_config: Option<Value>,
@@ -160,3 +183,217 @@ pub enum Tool {
     #[serde(untagged)]
     Unknown(Value),
 }
+
+impl Part {
+    fn text(text: impl Into<String>) -> Self {
+        Part::Text {
+            text: text.into(),
+            thought: None,
+        }
+    }
+
+    /// Converts a Claude content block, `tool_names` maps tool use ids to tool names
+    fn from_claude(block: ContentBlock, tool_names: &HashMap<String, String>) -> Option<Self> {
+        Some(match block {
+            ContentBlock::Text { text, .. } => Part::text(text),
+            ContentBlock::Image { source } => Part::inline_data(InlineData {
+                mime_type: source.media_type,
+                data: source.data,
+            }),
+            ContentBlock::ImageUrl { image_url } => {
+                // data URLs are sent inline, Files API and gs:// URIs as file references
+                match image_url
+                    .url
+                    .strip_prefix("data:")
+                    .and_then(|u| u.split_once(";base64,"))
+                {
+                    Some((mime_type, data)) => Part::inline_data(InlineData {
+                        mime_type: mime_type.to_string(),
+                        data: data.to_string(),
+                    }),
+                    None => Part::fileData(FileData {
+                        mimeType: None,
+                        fileUrl: image_url.url,
+                    }),
+                }
+            }
+            ContentBlock::ToolUse { id, name, input } => Part::functionCall(FunctionCall {
+                id: Some(id),
+                name,
+                args: Some(input),
+            }),
+            ContentBlock::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => {
+                let response = if is_error.unwrap_or_default() {
+                    json!({ "error": content.text() })
+                } else {
+                    json!({ "output": content.text() })
+                };
+                Part::functionResponse(FunctionResponse {
+                    name: tool_names.get(&tool_use_id).cloned().unwrap_or_default(),
+                    id: Some(tool_use_id),
+                    response,
+                })
+            }
+            // signed thinking came from Gemini and is replayed with its signature
+            ContentBlock::Thinking {
+                thinking,
+                signature,
+            } if !signature.is_empty() => Part::Text {
+                text: thinking,
+                thought: Some(true),
+            },
+            // thinking of Claude can not be replayed to Gemini
+            _ => return None,
+        })
+    }
+}
+
+/// Checks whether Gemini can read an image URL as a file reference
+///
+/// Gemini only reads files uploaded through its Files API or stored in Cloud Storage
+pub fn is_gemini_file_url(url: &str) -> bool {
+    url.starts_with("gs://") || url.starts_with("https://generativelanguage.googleapis.com/")
+}
+
+/// Checks that every image URL of a Claude request can be sent to Gemini
+pub fn check_image_urls(params: &ClaudeCreateMessageParams) -> Result<(), ClewdrError> {
+    let unsupported = params.messages.iter().any(|m| match &m.content {
+        MessageContent::Blocks { content } => content.iter().any(|b| {
+            matches!(b, ContentBlock::ImageUrl { image_url }
+                if !image_url.url.starts_with("data:") && !is_gemini_file_url(&image_url.url))
+        }),
+        _ => false,
+    });
+    if unsupported {
+        return Err(ClewdrError::BadRequest {
+            msg: "Gemini only reads images sent inline, by Files API URI or by gs:// URI",
+        });
+    }
+    Ok(())
+}
+
+impl From<ClaudeCreateMessageParams> for GeminiRequestBody {
+    fn from(params: ClaudeCreateMessageParams) -> Self {
+        let system_instruction = match params.system {
+            Some(Value::String(s)) => Some(SystemInstruction::from_string(s)),
+            Some(Value::Array(blocks)) => Some(SystemInstruction {
+                parts: blocks
+                    .iter()
+                    .filter_map(|b| b["text"].as_str())
+                    .map(Part::text)
+                    .collect(),
+            }),
+            _ => None,
+        }
+        .filter(|s| !s.parts.is_empty());
+        // function responses of Gemini are matched to calls by name
+        let mut tool_names = HashMap::new();
+        let mut contents = vec![];
+        for message in params.messages {
+            let role = match message.role {
+                ClaudeRole::Assistant => Role::model,
+                _ => Role::user,
+            };
+            let parts = match message.content {
+                MessageContent::Text { content } => vec![ContentPart {
+                    part: Part::text(content),
+                    thought_signature: None,
+                }],
+                MessageContent::Blocks { content } => {
+                    let mut parts = vec![];
+                    // a thinking block without text signs the part after it
+                    let mut pending = None;
+                    for b in content {
+                        let thought_signature = match &b {
+                            ContentBlock::Thinking {
+                                thinking,
+                                signature,
+                            } if !signature.is_empty() => {
+                                if thinking.is_empty() {
+                                    pending = Some(signature.to_owned());
+                                    continue;
+                                }
+                                Some(signature.to_owned())
+                            }
+                            _ => pending.take(),
+                        };
+                        if let ContentBlock::ToolUse { id, name, .. } = &b {
+                            tool_names.insert(id.to_owned(), name.to_owned());
+                        }
+                        if let Some(part) = Part::from_claude(b, &tool_names) {
+                            parts.push(ContentPart {
+                                part,
+                                thought_signature,
+                            });
+                        }
+                    }
+                    parts
+                }
+            };
+            if !parts.is_empty() {
+                contents.push(Chat { role, parts });
+            }
+        }
+        let declarations = params
+            .tools
+            .unwrap_or_default()
+            .into_iter()
+            .map(|t| {
+                json!({
+                    "name": t.name,
+                    "description": t.description.unwrap_or_default(),
+                    "parametersJsonSchema": t.input_schema,
+                })
+            })
+            .collect::<Vec<_>>();
+        let tool_config = params
+            .tool_choice
+            .filter(|_| !declarations.is_empty())
+            .map(|c| {
+                let config = match c {
+                    ToolChoice::Auto => json!({"mode": "AUTO"}),
+                    ToolChoice::Any => json!({"mode": "ANY"}),
+                    ToolChoice::None => json!({"mode": "NONE"}),
+                    ToolChoice::Tool { name } => {
+                        json!({"mode": "ANY", "allowedFunctionNames": [name]})
+                    }
+                };
+                json!({ "functionCallingConfig": config })
+            });
+        let tools =
+            (!declarations.is_empty()).then(|| vec![Tool::functionDeclarations(declarations)]);
+        let mut generation_config = json!({ "maxOutputTokens": params.max_tokens });
+        if let Some(t) = params.temperature {
+            generation_config["temperature"] = json!(t);
+        }
+        if let Some(p) = params.top_p {
+            generation_config["topP"] = json!(p);
+        }
+        if let Some(k) = params.top_k {
+            generation_config["topK"] = json!(k);
+        }
+        if let Some(s) = params.stop_sequences.filter(|s| !s.is_empty()) {
+            generation_config["stopSequences"] = json!(s);
+        }
+        if let Some(t) = params.thinking {
+            generation_config["thinkingConfig"] = json!({
+                "thinkingBudget": t.budget_tokens,
+                "includeThoughts": true,
+            });
+        }
+        let mut body = Self {
+            system_instruction,
+            tools,
+            tool_config,
+            contents,
+            generation_config: Some(generation_config),
+            safety_settings: None,
+        };
+        body.safety_off();
+        body
+    }
+}
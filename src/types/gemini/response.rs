@@ -35,15 +35,19 @@ pub enum FinishReason {
 #[derive(Serialize, Deserialize)]
 #[allow(non_snake_case)]
 pub struct Candidate {
-    content: Chat,
+    #[serde(default)]
+    pub content: Chat,
     pub finishReason: Option<FinishReason>,
 }
 
 #[derive(Serialize, Deserialize)]
 #[allow(non_snake_case)]
 pub struct GeminiResponse {
+    #[serde(default)]
     pub candidates: Vec<Candidate>,
+    #[serde(default)]
     pub usageMetadata: Value,
+    #[serde(default)]
     pub modelVersion: String,
     pub promptFeedback: Option<Value>,
 }
@@ -1,4 +1,6 @@
 pub mod claude;
 pub mod claude_web;
+pub mod completions;
 pub mod gemini;
 pub mod oai;
+pub mod responses;
@@ -2,8 +2,11 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use tiktoken_rs::o200k_base;
 
-use super::claude::{CreateMessageParams as ClaudeCreateMessageParams, *};
-use crate::{config::CLEWDR_CONFIG, types::claude::Message};
+use super::claude::{
+    self, ContentBlock, CreateMessageParams as ClaudeCreateMessageParams, Metadata, Thinking,
+    ToolResultContent, default_max_tokens,
+};
+use crate::config::CLEWDR_CONFIG;
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 #[serde(rename_all = "snake_case")]
@@ -16,16 +19,16 @@ pub enum Effort {
 
 impl From<CreateMessageParams> for ClaudeCreateMessageParams {
     fn from(params: CreateMessageParams) -> Self {
-        let (systems, messages): (Vec<Message>, Vec<Message>) = params
-            .messages
-            .into_iter()
-            .partition(|m| m.role == Role::System);
+        let (systems, messages): (Vec<claude::Message>, Vec<claude::Message>) =
+            into_claude_messages(params.messages)
+                .into_iter()
+                .partition(|m| m.role == claude::Role::System);
         let systems = systems
             .into_iter()
             .map(|m| m.content)
             .flat_map(|c| match c {
-                MessageContent::Text { content } => vec![ContentBlock::Text { text: content }],
-                MessageContent::Blocks { content } => content,
+                claude::MessageContent::Text { content } => vec![ContentBlock::text(content)],
+                claude::MessageContent::Blocks { content } => content,
             })
             .filter(|b| matches!(b, ContentBlock::Text { .. }))
             .map(|b| json!(b))
@@ -45,14 +48,265 @@ impl From<CreateMessageParams> for ClaudeCreateMessageParams {
             stream: params.stream,
             top_k: params.top_k,
             top_p: params.top_p,
-            tools: params.tools,
-            tool_choice: params.tool_choice,
+            tools: params
+                .tools
+                .map(|t| t.into_iter().map(Into::into).collect()),
+            tool_choice: params.tool_choice.map(Into::into),
             metadata: params.metadata,
             n: params.n,
         }
     }
 }
 
+/// Parses the JSON arguments of a tool call into a `tool_use` input
+///
+/// Arguments that are not a JSON object are kept verbatim under `raw_arguments`,
+/// so the call is replayed as it was made
+pub fn tool_call_input(arguments: &str) -> Value {
+    match serde_json::from_str::<Value>(arguments) {
+        Ok(input) if input.is_object() => input,
+        _ => json!({ "raw_arguments": arguments }),
+    }
+}
+
+/// Serializes a `tool_use` input back into the JSON arguments of a tool call
+///
+/// The inverse of [`tool_call_input`], arguments kept under `raw_arguments` are sent as they were
+pub fn tool_call_arguments(input: &Value) -> String {
+    match input.as_object() {
+        Some(o) if o.len() == 1 && o.contains_key("raw_arguments") => {
+            o["raw_arguments"].as_str().unwrap_or_default().to_string()
+        }
+        _ => input.to_string(),
+    }
+}
+
+/// Converts OpenAI messages into Claude messages
+///
+/// Assistant tool calls become `tool_use` blocks and tool messages become
+/// `tool_result` blocks, consecutive results being merged into one user turn.
+/// System and developer messages are kept as system messages.
+pub fn into_claude_messages(messages: Vec<Message>) -> Vec<claude::Message> {
+    let mut out: Vec<claude::Message> = vec![];
+    for msg in messages {
+        match msg.role {
+            Role::Tool => {
+                let result = ContentBlock::ToolResult {
+                    tool_use_id: msg.tool_call_id.unwrap_or_default(),
+                    content: match msg.content {
+                        Some(Content::Text(text)) => ToolResultContent::Text(text),
+                        Some(Content::Parts(parts)) => ToolResultContent::Blocks(parts),
+                        None => ToolResultContent::default(),
+                    },
+                    is_error: None,
+                };
+                // merge into the previous turn if it only holds tool results
+                if let Some(claude::Message {
+                    role: claude::Role::User,
+                    content: claude::MessageContent::Blocks { content },
+                }) = out.last_mut()
+                    && content
+                        .iter()
+                        .all(|b| matches!(b, ContentBlock::ToolResult { .. }))
+                {
+                    content.push(result);
+                    continue;
+                }
+                out.push(claude::Message::new_blocks(
+                    claude::Role::User,
+                    vec![result],
+                ));
+            }
+            Role::Assistant if !msg.tool_calls.is_empty() => {
+                let mut blocks = match msg.content {
+                    Some(Content::Text(text)) => vec![ContentBlock::text(text)],
+                    Some(Content::Parts(parts)) => parts,
+                    None => vec![],
+                };
+                // Claude rejects empty text blocks
+                blocks.retain(|b| !matches!(b, ContentBlock::Text { text, .. } if text.is_empty()));
+                blocks.extend(
+                    msg.tool_calls
+                        .into_iter()
+                        .map(|call| ContentBlock::ToolUse {
+                            id: call.id,
+                            input: tool_call_input(&call.function.arguments),
+                            name: call.function.name,
+                        }),
+                );
+                out.push(claude::Message::new_blocks(claude::Role::Assistant, blocks));
+            }
+            role => {
+                let role = match role {
+                    Role::System | Role::Developer => claude::Role::System,
+                    Role::Assistant => claude::Role::Assistant,
+                    _ => claude::Role::User,
+                };
+                let content = match msg.content {
+                    Some(Content::Text(text)) => claude::MessageContent::Text { content: text },
+                    Some(Content::Parts(parts)) => {
+                        claude::MessageContent::Blocks { content: parts }
+                    }
+                    None => claude::MessageContent::Text {
+                        content: String::new(),
+                    },
+                };
+                out.push(claude::Message { role, content });
+            }
+        }
+    }
+    out
+}
+
+/// Role of the sender of an OpenAI message
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    Developer,
+    #[default]
+    User,
+    Assistant,
+    Tool,
+}
+
+/// A message in OpenAI format
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Message {
+    /// Role of the message sender
+    pub role: Role,
+    /// Text or content parts, null for assistant messages with only tool calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<Content>,
+    /// Name of the participant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Tool calls made by the assistant
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Id of the tool call answered by a tool message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl Message {
+    /// Create a new message with the given text content
+    pub fn new_text(role: Role, text: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: Some(Content::Text(text.into())),
+            ..Default::default()
+        }
+    }
+}
+
+/// Content of an OpenAI message, either plain text or content parts
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Parts(Vec<ContentBlock>),
+}
+
+/// A tool call made by the assistant
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub type_: String,
+    pub function: FunctionCall,
+}
+
+/// Name and arguments of a function called by the assistant
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+    /// Arguments as a JSON encoded string
+    #[serde(default)]
+    pub arguments: String,
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+/// A tool, either an OpenAI function or a tool in Claude format
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Tool {
+    Function {
+        #[serde(rename = "type", default = "function_type")]
+        type_: String,
+        function: FunctionDefinition,
+    },
+    Claude(claude::Tool),
+}
+
+/// Definition of a function the model may call
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON schema of the arguments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl From<Tool> for claude::Tool {
+    fn from(tool: Tool) -> Self {
+        match tool {
+            Tool::Function { function, .. } => claude::Tool {
+                name: function.name,
+                description: function.description,
+                input_schema: function
+                    .parameters
+                    .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+            },
+            Tool::Claude(tool) => tool,
+        }
+    }
+}
+
+/// How the model should use tools, in OpenAI or Claude format
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    /// `none`, `auto` or `required`
+    Mode(String),
+    /// A specific function the model must call
+    Function {
+        #[serde(rename = "type")]
+        type_: String,
+        function: FunctionName,
+    },
+    Claude(claude::ToolChoice),
+}
+
+/// Name of a function in a tool choice
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FunctionName {
+    pub name: String,
+}
+
+impl From<ToolChoice> for claude::ToolChoice {
+    fn from(choice: ToolChoice) -> Self {
+        match choice {
+            ToolChoice::Mode(mode) => match mode.as_str() {
+                "none" => claude::ToolChoice::None,
+                "required" => claude::ToolChoice::Any,
+                _ => claude::ToolChoice::Auto,
+            },
+            ToolChoice::Function { function, .. } => claude::ToolChoice::Tool {
+                name: function.name,
+            },
+            ToolChoice::Claude(choice) => choice,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct CreateMessageParams {
     /// Maximum number of tokens to generate
@@ -106,6 +360,65 @@ pub struct CreateMessageParams {
     /// Number of completions to generate
     #[serde(skip_serializing_if = "Option::is_none")]
     pub n: Option<u32>,
+    /// Options for streaming responses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    /// Format the output must follow
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+/// Options for streaming responses
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct StreamOptions {
+    /// Whether to send a final chunk with the token usage of the request
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// Format the output must follow, plain text or JSON
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema { json_schema: JsonSchema },
+}
+
+/// A named JSON schema the output must match
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JsonSchema {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl ResponseFormat {
+    /// JSON schema of the output, `None` for plain text
+    pub fn schema(&self) -> Option<Value> {
+        match self {
+            ResponseFormat::Text => None,
+            ResponseFormat::JsonObject => Some(json!({"type": "object"})),
+            ResponseFormat::JsonSchema { json_schema } => Some(
+                json_schema
+                    .schema
+                    .to_owned()
+                    .unwrap_or_else(|| json!({"type": "object"})),
+            ),
+        }
+    }
+
+    /// Description of the output, given by the client
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            ResponseFormat::JsonSchema { json_schema } => json_schema.description.as_deref(),
+            _ => None,
+        }
+    }
 }
 
 impl CreateMessageParams {
@@ -115,14 +428,15 @@ impl CreateMessageParams {
             .messages
             .iter()
             .map(|msg| match msg.content {
-                MessageContent::Text { ref content } => content.to_string(),
-                MessageContent::Blocks { ref content } => content
+                Some(Content::Text(ref content)) => content.to_string(),
+                Some(Content::Parts(ref content)) => content
                     .iter()
                     .map(|block| match block {
-                        ContentBlock::Text { text } => text,
+                        ContentBlock::Text { text, .. } => text,
                         _ => "",
                     })
                     .collect::<String>(),
+                None => String::new(),
             })
             .collect::<Vec<_>>()
             .join("\n");
@@ -0,0 +1,326 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::{
+    claude::{
+        self, ContentBlock, CreateMessageParams as ClaudeCreateMessageParams, ImageUrl, Thinking,
+        ToolResultContent, default_max_tokens,
+    },
+    oai::{Effort, tool_call_input},
+};
+
+/// Smallest thinking budget accepted by Claude
+const MIN_THINKING_BUDGET: u64 = 1024;
+
+/// Parameters of an OpenAI Responses API request
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct CreateResponseParams {
+    /// Model to use
+    pub model: String,
+    /// Text or input items of the conversation
+    #[serde(default)]
+    pub input: ResponseInput,
+    /// System instructions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    /// Maximum number of tokens to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    /// Temperature for response generation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Top-p sampling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Whether to stream the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// Tools that the model may use, only function tools are supported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Value>>,
+    /// How the model should use tools
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+    /// Reasoning configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<Reasoning>,
+}
+
+/// Input of a Responses API request, either plain text or input items
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ResponseInput {
+    Text(String),
+    Items(Vec<InputItem>),
+}
+
+impl Default for ResponseInput {
+    fn default() -> Self {
+        Self::Items(vec![])
+    }
+}
+
+/// An input item, messages may omit their `type`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum InputItem {
+    Typed(TypedItem),
+    Message(InputMessage),
+}
+
+/// An input item with an explicit `type`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TypedItem {
+    Message(InputMessage),
+    FunctionCall {
+        call_id: String,
+        name: String,
+        #[serde(default)]
+        arguments: String,
+    },
+    FunctionCallOutput {
+        call_id: String,
+        #[serde(default)]
+        output: Value,
+    },
+    /// Thinking of a previous turn, with its signature as encrypted content
+    Reasoning {
+        #[serde(default)]
+        summary: Vec<Value>,
+        #[serde(default)]
+        encrypted_content: Option<String>,
+    },
+    /// Other items Claude has no use for
+    #[serde(other)]
+    Other,
+}
+
+/// A message input item
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InputMessage {
+    pub role: String,
+    pub content: InputContent,
+}
+
+/// Content of an input message, either plain text or content parts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum InputContent {
+    Text(String),
+    Parts(Vec<InputPart>),
+}
+
+/// A content part of an input message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputPart {
+    InputText {
+        text: String,
+    },
+    OutputText {
+        text: String,
+    },
+    Refusal {
+        refusal: String,
+    },
+    InputImage {
+        #[serde(default)]
+        image_url: Option<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Reasoning configuration of a Responses API request
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct Reasoning {
+    /// `minimal`, `low`, `medium` or `high`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<String>,
+    /// Requested summary, Claude always returns its full thinking
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+impl InputContent {
+    fn into_blocks(self) -> Vec<ContentBlock> {
+        let parts = match self {
+            InputContent::Text(text) => return vec![ContentBlock::text(text)],
+            InputContent::Parts(parts) => parts,
+        };
+        parts
+            .into_iter()
+            .filter_map(|p| match p {
+                InputPart::InputText { text }
+                | InputPart::OutputText { text }
+                | InputPart::Refusal { refusal: text } => {
+                    (!text.is_empty()).then(|| ContentBlock::text(text))
+                }
+                InputPart::InputImage {
+                    image_url: Some(url),
+                } => Some(ContentBlock::ImageUrl {
+                    image_url: ImageUrl { url },
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Appends blocks to the last message if it has the same role, or starts a new turn
+fn push_blocks(out: &mut Vec<claude::Message>, role: claude::Role, blocks: Vec<ContentBlock>) {
+    if blocks.is_empty() {
+        return;
+    }
+    if let Some(claude::Message {
+        role: last_role,
+        content: claude::MessageContent::Blocks { content },
+    }) = out.last_mut()
+        && *last_role == role
+    {
+        content.extend(blocks);
+        return;
+    }
+    out.push(claude::Message::new_blocks(role, blocks));
+}
+
+/// Converts a Responses API tool into a Claude tool, skipping built-in tools
+fn convert_tool(tool: Value) -> Option<claude::Tool> {
+    if tool["type"] != "function" {
+        return None;
+    }
+    Some(claude::Tool {
+        name: tool["name"].as_str()?.to_string(),
+        description: tool["description"].as_str().map(ToString::to_string),
+        input_schema: match tool.get("parameters") {
+            Some(p) if p.is_object() => p.to_owned(),
+            _ => json!({"type": "object", "properties": {}}),
+        },
+    })
+}
+
+/// Converts a Responses API tool choice into a Claude tool choice
+fn convert_tool_choice(choice: Value) -> Option<claude::ToolChoice> {
+    match choice.as_str() {
+        Some("none") => return Some(claude::ToolChoice::None),
+        Some("required") => return Some(claude::ToolChoice::Any),
+        Some(_) => return Some(claude::ToolChoice::Auto),
+        None => {}
+    }
+    let name = choice["name"].as_str()?;
+    Some(claude::ToolChoice::Tool {
+        name: name.to_string(),
+    })
+}
+
+impl From<CreateResponseParams> for ClaudeCreateMessageParams {
+    fn from(params: CreateResponseParams) -> Self {
+        let mut systems = vec![];
+        systems.extend(params.instructions.map(ContentBlock::text));
+        let items = match params.input {
+            ResponseInput::Text(text) => vec![InputItem::Message(InputMessage {
+                role: "user".to_string(),
+                content: InputContent::Text(text),
+            })],
+            ResponseInput::Items(items) => items,
+        };
+        let mut messages = vec![];
+        for item in items {
+            let item = match item {
+                InputItem::Message(m) => TypedItem::Message(m),
+                InputItem::Typed(t) => t,
+            };
+            match item {
+                TypedItem::Message(m) => {
+                    let blocks = m.content.into_blocks();
+                    match m.role.as_str() {
+                        "system" | "developer" => systems.extend(blocks),
+                        "assistant" => push_blocks(&mut messages, claude::Role::Assistant, blocks),
+                        _ => push_blocks(&mut messages, claude::Role::User, blocks),
+                    }
+                }
+                TypedItem::FunctionCall {
+                    call_id,
+                    name,
+                    arguments,
+                } => {
+                    let block = ContentBlock::ToolUse {
+                        id: call_id,
+                        name,
+                        input: tool_call_input(&arguments),
+                    };
+                    push_blocks(&mut messages, claude::Role::Assistant, vec![block]);
+                }
+                TypedItem::FunctionCallOutput { call_id, output } => {
+                    let content = match output {
+                        Value::String(s) => ToolResultContent::Text(s),
+                        other => ToolResultContent::Text(other.to_string()),
+                    };
+                    let block = ContentBlock::ToolResult {
+                        tool_use_id: call_id,
+                        content,
+                        is_error: None,
+                    };
+                    push_blocks(&mut messages, claude::Role::User, vec![block]);
+                }
+                TypedItem::Reasoning {
+                    summary,
+                    encrypted_content: Some(signature),
+                } if !signature.is_empty() => {
+                    let thinking = summary
+                        .iter()
+                        .filter_map(|s| s["text"].as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    let block = ContentBlock::Thinking {
+                        thinking,
+                        signature,
+                    };
+                    push_blocks(&mut messages, claude::Role::Assistant, vec![block]);
+                }
+                // thinking without a signature is rejected by Claude
+                TypedItem::Reasoning { .. } | TypedItem::Other => {}
+            }
+        }
+        let system = (!systems.is_empty()).then(|| json!(systems));
+        let max_tokens = params.max_output_tokens.unwrap_or_else(default_max_tokens);
+        let thinking = params
+            .reasoning
+            .and_then(|r| r.effort)
+            .and_then(|e| match e.as_str() {
+                "minimal" => Some(MIN_THINKING_BUDGET),
+                "low" => Some(Effort::Low as u64),
+                "medium" => Some(Effort::Medium as u64),
+                "high" => Some(Effort::High as u64),
+                _ => None,
+            })
+            // the budget must leave room for the answer, thinking is off if there is none
+            .map(|b| {
+                b.max(MIN_THINKING_BUDGET)
+                    .min((max_tokens as u64).saturating_sub(1))
+            })
+            .filter(|&b| b >= MIN_THINKING_BUDGET)
+            .map(Thinking::new);
+        let tools = params
+            .tools
+            .map(|t| t.into_iter().filter_map(convert_tool).collect::<Vec<_>>())
+            .filter(|t| !t.is_empty());
+        Self {
+            max_tokens,
+            system,
+            messages,
+            model: params.model,
+            thinking,
+            temperature: params.temperature,
+            stream: params.stream,
+            top_p: params.top_p,
+            tool_choice: params
+                .tool_choice
+                .filter(|_| tools.is_some())
+                .and_then(convert_tool_choice),
+            tools,
+            ..Default::default()
+        }
+    }
+}
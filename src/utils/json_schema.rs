@@ -0,0 +1,184 @@
+use serde_json::Value;
+
+/// Validates a value against a JSON schema
+///
+/// Covers the subset of JSON schema used for structured outputs: `type`, `enum`, `const`,
+/// `properties`, `required`, `additionalProperties`, `items`, `anyOf`, `oneOf`, `allOf`,
+/// local `$ref`s, and length and range limits. Other keywords are ignored.
+///
+/// # Returns
+/// * `Err` - The path of the first invalid value and the reason
+pub fn validate(schema: &Value, value: &Value) -> Result<(), String> {
+    Validator { root: schema }.check(schema, value, "$")
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl Validator<'_> {
+    fn check(&self, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+        let Some(schema) = schema.as_object() else {
+            return Ok(());
+        };
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let target = reference
+                .strip_prefix('#')
+                .and_then(|p| self.root.pointer(p))
+                .ok_or_else(|| format!("{path}: unresolved reference `{reference}`"))?;
+            self.check(target, value, path)?;
+        }
+        let type_ok = match schema.get("type") {
+            Some(Value::String(t)) => type_matches(t, value),
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|t| type_matches(t, value)),
+            _ => true,
+        };
+        if !type_ok {
+            return Err(format!(
+                "{path}: expected {}, found {}",
+                schema["type"],
+                type_name(value)
+            ));
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array)
+            && !options.contains(value)
+        {
+            return Err(format!("{path}: {value} is not one of {}", schema["enum"]));
+        }
+        if let Some(expected) = schema.get("const")
+            && expected != value
+        {
+            return Err(format!("{path}: expected {expected}, found {value}"));
+        }
+        for key in ["anyOf", "oneOf"] {
+            if let Some(variants) = schema.get(key).and_then(Value::as_array)
+                && !variants.iter().any(|s| self.check(s, value, path).is_ok())
+            {
+                return Err(format!("{path}: matches none of the `{key}` schemas"));
+            }
+        }
+        for s in schema
+            .get("allOf")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            self.check(s, value, path)?;
+        }
+        let limit = |key: &str| schema.get(key).and_then(Value::as_f64);
+        match value {
+            Value::Object(object) => {
+                let required = schema.get("required").and_then(Value::as_array);
+                for name in required.into_iter().flatten().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        return Err(format!("{path}: missing required property `{name}`"));
+                    }
+                }
+                let properties = schema.get("properties").and_then(Value::as_object);
+                for (name, v) in object {
+                    let child = format!("{path}.{name}");
+                    match (
+                        properties.and_then(|p| p.get(name)),
+                        schema.get("additionalProperties"),
+                    ) {
+                        (Some(s), _) => self.check(s, v, &child)?,
+                        (None, Some(Value::Bool(false))) => {
+                            return Err(format!("{path}: unexpected property `{name}`"));
+                        }
+                        (None, Some(s)) => self.check(s, v, &child)?,
+                        (None, None) => {}
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(s) = schema.get("items") {
+                    for (i, v) in items.iter().enumerate() {
+                        self.check(s, v, &format!("{path}[{i}]"))?;
+                    }
+                }
+                let len = items.len() as f64;
+                if limit("minItems").is_some_and(|m| len < m) {
+                    return Err(format!("{path}: fewer than {} items", schema["minItems"]));
+                }
+                if limit("maxItems").is_some_and(|m| len > m) {
+                    return Err(format!("{path}: more than {} items", schema["maxItems"]));
+                }
+            }
+            Value::String(s) => {
+                let len = s.chars().count() as f64;
+                if limit("minLength").is_some_and(|m| len < m) {
+                    return Err(format!("{path}: shorter than {}", schema["minLength"]));
+                }
+                if limit("maxLength").is_some_and(|m| len > m) {
+                    return Err(format!("{path}: longer than {}", schema["maxLength"]));
+                }
+            }
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                if limit("minimum").is_some_and(|m| n < m) {
+                    return Err(format!("{path}: less than {}", schema["minimum"]));
+                }
+                if limit("maximum").is_some_and(|m| n > m) {
+                    return Err(format!("{path}: greater than {}", schema["maximum"]));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn type_matches(kind: &str, value: &Value) -> bool {
+    match kind {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_object() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}},
+            },
+            "required": ["name"],
+            "additionalProperties": false,
+            "$defs": {"tag": {"enum": ["a", "b"]}},
+        });
+        assert!(validate(&schema, &json!({"name": "x", "tags": ["a"]})).is_ok());
+        assert!(validate(&schema, &json!({"tags": []})).is_err());
+        assert!(validate(&schema, &json!({"name": "x", "extra": 1})).is_err());
+        assert_eq!(
+            validate(&schema, &json!({"name": "x", "tags": ["c"]})).unwrap_err(),
+            "$.tags[0]: \"c\" is not one of [\"a\",\"b\"]"
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod json_schema;
+
 use axum::body::Body;
 use colored::{ColoredString, Colorize};
 use tokio::spawn;